@@ -0,0 +1,113 @@
+#![feature(test)]
+#![recursion_limit = "512"]
+
+extern crate test;
+
+use ironties::{
+    _TypeInfoImpl,
+    layout::{DefinedTypes, TypeUid},
+    types::{SBox, SOption, SVec},
+    TypeInfo,
+};
+use std::any::type_name;
+use test::{black_box, Bencher};
+
+/// Generates a chain of structs, each of which refers to the two previous ones in a few different ways
+///
+/// The previous types are only referred to through pointers, so that the sizes of the types don't grow.
+macro_rules! type_graph {
+    ($prev2:ident $prev:ident) => {};
+    ($prev2:ident $prev:ident $name:ident $($rest:ident)*) => {
+        #[repr(C)]
+        #[derive(TypeInfo)]
+        pub struct $name {
+            a: SBox<$prev>,
+            b: *const $prev2,
+            c: SVec<$prev>,
+            d: [SOption<SBox<$prev2>>; 2],
+            e: extern "C" fn(*const $prev, u64) -> SBox<$prev2>,
+        }
+
+        type_graph!($prev $name $($rest)*);
+    };
+}
+
+/// Generates functions looking up all of the types, either by scanning a list of their [`TypeUid`]s
+/// (as was done before defined types were indexed), or through [`DefinedTypes`]
+macro_rules! lookups {
+    ($($name:ident)*) => {
+        fn uids() -> Vec<(TypeUid, &'static str)> {
+            vec![$(($name::_UID, type_name::<$name>())),*]
+        }
+
+        fn linear_lookups(uids: &[(TypeUid, &'static str)]) -> usize {
+            [$(uids.iter().position(|(uid, _)| *uid == $name::_UID)),*]
+                .into_iter()
+                .flatten()
+                .sum()
+        }
+
+        fn indexed_lookups(defined_types: &DefinedTypes) -> usize {
+            [$(defined_types.id_of::<$name>()),*]
+                .into_iter()
+                .flatten()
+                .sum()
+        }
+    };
+}
+
+macro_rules! graph {
+    ($($name:ident)*) => {
+        type_graph!($($name)*);
+        lookups!($($name)*);
+    };
+}
+
+#[repr(C)]
+#[derive(TypeInfo)]
+pub struct T0(u8);
+#[repr(C)]
+#[derive(TypeInfo)]
+pub struct T1(u16);
+
+graph!(T0 T1
+    T2 T3 T4 T5 T6 T7 T8 T9 T10 T11 T12 T13 T14 T15 T16 T17 T18 T19 T20 T21 T22 T23 T24 T25 T26 T27 T28 T29
+    T30 T31 T32 T33 T34 T35 T36 T37 T38 T39 T40 T41 T42 T43 T44 T45 T46 T47 T48 T49 T50 T51 T52 T53 T54 T55
+    T56 T57 T58 T59 T60 T61 T62 T63 T64 T65 T66 T67 T68 T69 T70 T71 T72 T73 T74 T75 T76 T77 T78 T79 T80 T81
+    T82 T83 T84 T85 T86 T87 T88 T89 T90 T91 T92 T93 T94 T95 T96 T97 T98 T99 T100 T101 T102 T103 T104 T105
+    T106 T107 T108 T109 T110 T111 T112 T113 T114 T115 T116 T117 T118 T119 T120 T121 T122 T123 T124 T125
+    T126 T127
+);
+
+/// Generating the layout from scratch, as [`TypeInfo::layout`] did on every call before layouts were cached
+#[bench]
+fn build_layout(b: &mut Bencher) {
+    b.iter(|| black_box(T127::_layout_impl(DefinedTypes::new())));
+}
+
+#[bench]
+fn cached_layout_ref(b: &mut Bencher) {
+    T127::layout_ref();
+    b.iter(|| black_box(T127::layout_ref()));
+}
+
+#[bench]
+fn cached_layout_clone(b: &mut Bencher) {
+    T127::layout_ref();
+    b.iter(|| black_box(T127::layout()));
+}
+
+/// Looking up every type of the graph by scanning the list of defined types
+#[bench]
+fn linear_scan_lookup(b: &mut Bencher) {
+    let uids = uids();
+    b.iter(|| black_box(linear_lookups(black_box(&uids))));
+}
+
+/// Looking up every type of the graph through the index of [`DefinedTypes`]
+#[bench]
+fn indexed_lookup(b: &mut Bencher) {
+    let defined_types = T127::_layout_impl(DefinedTypes::new()).defined_types;
+    assert_eq!(defined_types.id_of::<T127>(), Some(0));
+    b.iter(|| black_box(indexed_lookups(black_box(&defined_types))));
+}
@@ -0,0 +1,18 @@
+use std::{env, process::Command};
+
+fn main() {
+    // Recorded in the header of every `TypeLayout`
+    let target = env::var("TARGET").unwrap();
+    let rustc = env::var("RUSTC").unwrap_or_else(|_| "rustc".to_owned());
+    let rustc_version = Command::new(rustc)
+        .arg("--version")
+        .output()
+        .ok()
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map(|version| version.trim().to_owned())
+        .unwrap_or_else(|| "unknown".to_owned());
+
+    println!("cargo:rustc-env=IRONTIES_TARGET={target}");
+    println!("cargo:rustc-env=IRONTIES_RUSTC_VERSION={rustc_version}");
+    println!("cargo:rerun-if-changed=build.rs");
+}
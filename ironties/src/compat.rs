@@ -0,0 +1,471 @@
+//! Compatibility checks between [`TypeLayout`]s that are more lenient than plain equality.
+
+use crate::{
+    layout::{
+        DefinedType, EnumVariant, EnumVariantType, Layout, NamedField, TypeType, UnnamedField,
+    },
+    TypeLayout,
+};
+use std::fmt::Display;
+
+impl TypeLayout {
+    /// Checks whether values of this layout and `other` can be used interchangeably.
+    ///
+    /// This is the same as comparing for equality, except that [non-exhaustive][crate::types::SNonExhaustive]
+    /// enums may have different sets of variants, as long as the variants that are present in both are the same.
+    /// Like with equality, defined types must also have the same [flags][crate::layout::TypeFlags].
+    ///
+    /// Layouts produced for different targets are never compatible (see [`LayoutHeader::same_target`][crate::LayoutHeader::same_target]).
+    pub fn is_compatible_with(&self, other: &TypeLayout) -> bool {
+        self.header.same_target(&other.header)
+            && LayoutComparer::new(&self.defined_types, &other.defined_types)
+                .layouts(&self.layout, &other.layout)
+    }
+    /// Checks whether this layout is the same as `old`, or extends it by only appending fields.
+    ///
+    /// A `#[repr(C)]` struct with named fields extends an older version of itself if all the old fields
+    /// are still there, in the same order and with the same layouts, and the new fields are only added
+    /// after them. The last of the old fields may itself be extended in the same manner. The alignment
+    /// of the struct can't grow, since that would move it inside of an [`Extensible`][crate::types::Extensible].
+    ///
+    /// This means that a value of this layout can be safely used where `old` is expected, but not the other way around.
+    /// Use [`Extensible`][crate::types::Extensible] to access the trailing fields in a value that might
+    /// have been produced by an older version.
+    ///
+    /// Layouts produced for different targets never extend each other (see [`LayoutHeader::same_target`][crate::LayoutHeader::same_target]).
+    pub fn extends(&self, old: &TypeLayout) -> bool {
+        self.header.same_target(&old.header)
+            && LayoutComparer::new(&self.defined_types, &old.defined_types)
+                .extends(&self.layout, &old.layout)
+    }
+}
+
+/// Compares layouts from two different [`TypeLayout`]s.
+///
+/// Defined types are compared structurally instead of by their ids, since those
+/// depend on the order in which the types were encountered.
+struct LayoutComparer<'a> {
+    a: &'a [DefinedType],
+    b: &'a [DefinedType],
+    /// Pairs of defined types that are currently being compared, and are assumed to be equal
+    /// to avoid infinite recursion on recursive types.
+    assumed: Vec<(usize, usize)>,
+}
+
+impl<'a> LayoutComparer<'a> {
+    fn new(a: &'a [DefinedType], b: &'a [DefinedType]) -> Self {
+        Self {
+            a,
+            b,
+            assumed: Vec::new(),
+        }
+    }
+
+    /// Checks whether `new` is equal to `old` or extends it with trailing fields
+    fn extends(&mut self, new: &Layout, old: &Layout) -> bool {
+        let (Layout::DefinedType { id: new_id }, Layout::DefinedType { id: old_id }) = (new, old)
+        else {
+            return self.layouts(new, old);
+        };
+
+        let (new_ty, old_ty) = (&self.a[*new_id], &self.b[*old_id]);
+        match (&new_ty.ty, &old_ty.ty) {
+            (
+                TypeType::StructNamed { fields: new_fields },
+                TypeType::StructNamed { fields: old_fields },
+            ) if new_ty.name == old_ty.name
+                && new_ty.repr == old_ty.repr
+                && new_ty.flags == old_ty.flags
+                && new_ty.known == old_ty.known
+                && new_ty.align == old_ty.align =>
+            {
+                let Some((old_last, old_rest)) = old_fields.split_last() else {
+                    return true;
+                };
+                if new_fields.len() < old_fields.len() {
+                    return false;
+                }
+
+                self.named_fields(&new_fields[..old_rest.len()], old_rest)
+                    && new_fields[old_rest.len()].name == old_last.name
+                    && new_fields[old_rest.len()].offset == old_last.offset
+                    && self.extends(&new_fields[old_rest.len()].layout, &old_last.layout)
+            }
+            _ => self.layouts(new, old),
+        }
+    }
+
+    fn layouts(&mut self, a: &Layout, b: &Layout) -> bool {
+        match (a, b) {
+            (Layout::DefinedType { id: a }, Layout::DefinedType { id: b }) => {
+                self.defined_types(*a, *b)
+            }
+            (Layout::ConstPtr(a), Layout::ConstPtr(b))
+            | (Layout::MutPtr(a), Layout::MutPtr(b))
+            | (Layout::Ref(a), Layout::Ref(b))
+            | (Layout::MutRef(a), Layout::MutRef(b))
+            | (Layout::Atomic(a), Layout::Atomic(b))
+            | (Layout::NonZero(a), Layout::NonZero(b))
+            | (Layout::NicheOption(a), Layout::NicheOption(b))
+            | (Layout::RelPtr(a), Layout::RelPtr(b)) => self.layouts(a, b),
+            (
+                Layout::Array {
+                    len: a_len,
+                    layout: a,
+                },
+                Layout::Array {
+                    len: b_len,
+                    layout: b,
+                },
+            ) => a_len == b_len && self.layouts(a, b),
+            (Layout::NonExhaustive(a), Layout::NonExhaustive(b)) => self.open_enums(a, b),
+            (
+                Layout::C {
+                    ty: a_ty,
+                    layout: a,
+                },
+                Layout::C {
+                    ty: b_ty,
+                    layout: b,
+                },
+            ) => a_ty == b_ty && self.layouts(a, b),
+            (
+                Layout::FunctionPointer {
+                    is_unsafe: a_is_unsafe,
+                    abi: a_abi,
+                    args: a_args,
+                    return_ty: a_return_ty,
+                },
+                Layout::FunctionPointer {
+                    is_unsafe: b_is_unsafe,
+                    abi: b_abi,
+                    args: b_args,
+                    return_ty: b_return_ty,
+                },
+            ) => {
+                a_is_unsafe == b_is_unsafe
+                    && a_abi == b_abi
+                    && self.layout_lists(a_args, b_args)
+                    && self.layouts(a_return_ty, b_return_ty)
+            }
+            (
+                Layout::DefinedType { .. }
+                | Layout::ConstPtr(_)
+                | Layout::MutPtr(_)
+                | Layout::Ref(_)
+                | Layout::MutRef(_)
+                | Layout::Array { .. }
+                | Layout::FunctionPointer { .. }
+                | Layout::NonExhaustive(_)
+                | Layout::Atomic(_)
+                | Layout::NonZero(_)
+                | Layout::NicheOption(_)
+                | Layout::C { .. }
+                | Layout::RelPtr(_),
+                _,
+            ) => false,
+            // Primitives
+            (a, b) => a == b,
+        }
+    }
+
+    /// Compares two enums which may have more variants than listed.
+    /// Only the variants with the same discriminants are compared.
+    fn open_enums(&mut self, a: &Layout, b: &Layout) -> bool {
+        let (Layout::DefinedType { id: a }, Layout::DefinedType { id: b }) = (a, b) else {
+            return self.layouts(a, b);
+        };
+        let (a, b) = (&self.a[*a], &self.b[*b]);
+
+        match (&a.ty, &b.ty) {
+            (
+                TypeType::Enum {
+                    variants: a_variants,
+                    layout: a_layout,
+                },
+                TypeType::Enum {
+                    variants: b_variants,
+                    layout: b_layout,
+                },
+            ) if a.name == b.name
+                && a.repr == b.repr
+                && a.flags == b.flags
+                && a.known == b.known
+                && a.size == b.size
+                && a.align == b.align
+                && a_layout == b_layout =>
+            {
+                a_variants.iter().all(|a| {
+                    match b_variants.iter().find(|b| b.discriminant == a.discriminant) {
+                        Some(b) => self.enum_variants(a, b),
+                        None => true,
+                    }
+                })
+            }
+            _ => false,
+        }
+    }
+
+    fn defined_types(&mut self, a: usize, b: usize) -> bool {
+        if self.assumed.contains(&(a, b)) {
+            return true;
+        }
+        self.assumed.push((a, b));
+
+        let (a, b) = (&self.a[a], &self.b[b]);
+        a.name == b.name
+            && a.repr == b.repr
+            && a.flags == b.flags
+            && a.known == b.known
+            && a.size == b.size
+            && a.align == b.align
+            && self.type_types(&a.ty, &b.ty)
+    }
+
+    fn type_types(&mut self, a: &TypeType, b: &TypeType) -> bool {
+        match (a, b) {
+            (TypeType::StructNamed { fields: a }, TypeType::StructNamed { fields: b })
+            | (TypeType::Union { fields: a }, TypeType::Union { fields: b }) => {
+                self.named_fields(a, b)
+            }
+            (TypeType::StructUnnamed { fields: a }, TypeType::StructUnnamed { fields: b }) => {
+                self.unnamed_fields(a, b)
+            }
+            (TypeType::StructUnit, TypeType::StructUnit) => true,
+            (TypeType::Opaque, TypeType::Opaque) => true,
+            (
+                TypeType::Enum {
+                    variants: a_variants,
+                    layout: a_layout,
+                },
+                TypeType::Enum {
+                    variants: b_variants,
+                    layout: b_layout,
+                },
+            ) => {
+                a_layout == b_layout
+                    && a_variants.len() == b_variants.len()
+                    && a_variants
+                        .iter()
+                        .zip(b_variants.iter())
+                        .all(|(a, b)| self.enum_variants(a, b))
+            }
+            _ => false,
+        }
+    }
+
+    fn enum_variants(&mut self, a: &EnumVariant, b: &EnumVariant) -> bool {
+        a.name == b.name
+            && a.discriminant == b.discriminant
+            && a.payload_offset == b.payload_offset
+            && match (&a.ty, &b.ty) {
+                (EnumVariantType::Unit, EnumVariantType::Unit) => true,
+                (EnumVariantType::Tuple(a), EnumVariantType::Tuple(b)) => self.unnamed_fields(a, b),
+                (EnumVariantType::Struct(a), EnumVariantType::Struct(b)) => self.named_fields(a, b),
+                _ => false,
+            }
+    }
+
+    fn named_fields(&mut self, a: &[NamedField], b: &[NamedField]) -> bool {
+        a.len() == b.len()
+            && a.iter().zip(b).all(|(a, b)| {
+                a.name == b.name && a.offset == b.offset && self.layouts(&a.layout, &b.layout)
+            })
+    }
+
+    fn unnamed_fields(&mut self, a: &[UnnamedField], b: &[UnnamedField]) -> bool {
+        a.len() == b.len()
+            && a.iter()
+                .zip(b)
+                .all(|(a, b)| a.offset == b.offset && self.layouts(&a.layout, &b.layout))
+    }
+
+    fn layout_lists(&mut self, a: &[Layout], b: &[Layout]) -> bool {
+        a.len() == b.len() && a.iter().zip(b).all(|(a, b)| self.layouts(a, b))
+    }
+}
+
+/// Describes how two incompatible layouts differ, for error messages
+pub(crate) struct Mismatch<'a> {
+    pub(crate) expected: &'a TypeLayout,
+    pub(crate) found: &'a TypeLayout,
+}
+
+impl Display for Mismatch<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (expected, found) = (&self.expected.header, &self.found.header);
+        if expected.same_target(found) {
+            write!(
+                f,
+                "{} is not compatible with {}",
+                describe(self.found),
+                describe(self.expected)
+            )
+        } else {
+            write!(
+                f,
+                "the layout was produced for {} (layout format {}), not {} (layout format {})",
+                &*found.target, found.format_version, &*expected.target, expected.format_version
+            )
+        }
+    }
+}
+
+/// The name, size and alignment of the type of a layout
+fn describe(layout: &TypeLayout) -> String {
+    match &layout.layout {
+        Layout::DefinedType { id } => {
+            let ty = &layout.defined_types[*id];
+            format!("`{}` (size {}, align {})", &*ty.name, ty.size, ty.align)
+        }
+        layout => format!("`{layout:?}`"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        types::{Extensible, ExtensibleRef, SBox, SStr},
+        TypeInfo,
+    };
+    use std::ptr::NonNull;
+
+    mod v1 {
+        use crate::TypeInfo;
+
+        #[repr(C)]
+        #[derive(TypeInfo)]
+        pub struct Interface {
+            pub version: u64,
+            pub inner: Inner,
+        }
+
+        #[repr(C)]
+        #[derive(TypeInfo)]
+        pub struct Inner {
+            pub a: u8,
+        }
+
+        #[repr(C)]
+        #[derive(TypeInfo)]
+        pub struct Padded {
+            pub a: u32,
+            pub b: u8,
+        }
+    }
+
+    mod v2 {
+        use crate::TypeInfo;
+
+        #[repr(C)]
+        #[derive(TypeInfo)]
+        pub struct Interface {
+            pub version: u64,
+            pub inner: Inner,
+            pub extra: extern "C" fn() -> u64,
+        }
+
+        #[repr(C)]
+        #[derive(TypeInfo)]
+        pub struct Inner {
+            pub a: u8,
+            pub b: u8,
+        }
+
+        #[repr(C)]
+        #[derive(TypeInfo)]
+        pub struct Padded {
+            pub a: u32,
+            pub b: u8,
+            pub c: u8,
+        }
+    }
+
+    mod v3 {
+        use crate::TypeInfo;
+
+        #[repr(C)]
+        #[derive(TypeInfo)]
+        pub struct Padded {
+            pub a: u32,
+            pub b: u8,
+            pub c: u64,
+        }
+    }
+
+    #[test]
+    fn extends() {
+        let (old, new) = (v1::Interface::layout(), v2::Interface::layout());
+
+        assert!(old.extends(&old));
+        assert!(new.extends(&old));
+        assert!(!old.extends(&new));
+        assert!(!<SBox<v2::Interface>>::layout().extends(&<SBox<v1::Interface>>::layout()));
+        assert!(v2::Padded::layout().extends(&v1::Padded::layout()));
+        // The value would start at a different offset inside of an `Extensible`
+        assert!(!v3::Padded::layout().extends(&v1::Padded::layout()));
+    }
+
+    #[test]
+    fn cross_target() {
+        let layout = usize::layout();
+        let mut other_target = usize::layout();
+        other_target.header.pointer_width = 32;
+        other_target.header.target = SStr::new("i686-unknown-linux-gnu");
+
+        assert!(layout.is_compatible_with(&usize::layout()));
+        assert!(!layout.is_compatible_with(&other_target));
+        assert!(!layout.extends(&other_target));
+        assert_ne!(layout, other_target);
+
+        // The compiler version doesn't matter
+        let mut other_compiler = usize::layout();
+        other_compiler.header.rustc_version = SStr::new("rustc 1.0.0 (a59de37e9 2015-05-13)");
+        assert!(layout.is_compatible_with(&other_compiler));
+        assert_eq!(layout, other_compiler);
+    }
+
+    #[test]
+    fn extensible_trailing_fields() {
+        extern "C" fn extra() -> u64 {
+            5
+        }
+
+        let old = Extensible::new(v1::Interface {
+            version: 1,
+            inner: v1::Inner { a: 2 },
+        });
+        let new = Extensible::new(v2::Interface {
+            version: 2,
+            inner: v2::Inner { a: 3, b: 4 },
+            extra,
+        });
+
+        // SAFETY: v2::Interface extends v1::Interface
+        let from_old: ExtensibleRef<v2::Interface> =
+            unsafe { ExtensibleRef::from_ptr(NonNull::from(&old).cast()) };
+        assert!(from_old.get().is_none());
+        assert_eq!(crate::extensible_field!(from_old, version), Some(&1));
+        assert!(crate::extensible_field!(from_old, extra).is_none());
+
+        let from_new = ExtensibleRef::new(&new);
+        assert_eq!(from_new.get().map(|i| i.inner.b), Some(4));
+        assert_eq!(
+            crate::extensible_field!(from_new, extra).map(|f| f()),
+            Some(5)
+        );
+    }
+
+    #[test]
+    fn extensible_trailing_padding() {
+        let old = Extensible::new(v1::Padded { a: 1, b: 2 });
+
+        // SAFETY: v2::Padded extends v1::Padded
+        let from_old: ExtensibleRef<v2::Padded> =
+            unsafe { ExtensibleRef::from_ptr(NonNull::from(&old).cast()) };
+        // `c` would be in the trailing padding of the old version
+        assert!(from_old.get().is_none());
+        assert_eq!(crate::extensible_field!(from_old, b), Some(&2));
+        assert!(crate::extensible_field!(from_old, c).is_none());
+    }
+}
@@ -0,0 +1,97 @@
+use super::{read_discriminant, size_of_layout};
+use crate::layout::{DefinedType, EnumLayout, EnumVariantType, Layout, TypeType};
+use std::{
+    ptr::copy_nonoverlapping,
+    sync::atomic::{AtomicU16, AtomicU32, AtomicU64, AtomicU8, Ordering},
+};
+
+/// Copies a value without its padding, which is uninitialized
+pub(crate) struct Copier<'a> {
+    pub(crate) defined_types: &'a [DefinedType],
+}
+
+impl<'a> Copier<'a> {
+    /// `src` must point to a valid value of the layout, which must not contain any types found by [`unchecked_type`][super::unchecked_type]
+    pub(crate) unsafe fn copy(&self, layout: &Layout, src: *const u8, dst: *mut u8) {
+        match layout {
+            Layout::Array { len, layout } => {
+                let size = size_of_layout(self.defined_types, layout);
+                for i in 0..*len {
+                    self.copy(layout, src.add(i * size), dst.add(i * size));
+                }
+            }
+            Layout::DefinedType { id } => self.defined_type(*id, src, dst),
+            Layout::NonExhaustive(layout) | Layout::NonZero(layout) | Layout::C { layout, .. } => {
+                self.copy(layout, src, dst)
+            }
+            Layout::Atomic(layout) => {
+                // The value might be modified concurrently, so it has to be loaded atomically
+                let bytes: &[u8] = match size_of_layout(self.defined_types, layout) {
+                    1 => &AtomicU8::from_ptr(src as *mut u8)
+                        .load(Ordering::Relaxed)
+                        .to_ne_bytes(),
+                    2 => &AtomicU16::from_ptr(src as *mut u16)
+                        .load(Ordering::Relaxed)
+                        .to_ne_bytes(),
+                    4 => &AtomicU32::from_ptr(src as *mut u32)
+                        .load(Ordering::Relaxed)
+                        .to_ne_bytes(),
+                    8 => &AtomicU64::from_ptr(src as *mut u64)
+                        .load(Ordering::Relaxed)
+                        .to_ne_bytes(),
+                    _ => unreachable!("atomics are at most 8 bytes"),
+                };
+                copy_nonoverlapping(bytes.as_ptr(), dst, bytes.len());
+            }
+            // Primitives and niche-optimized options don't have padding
+            layout => copy_nonoverlapping(src, dst, size_of_layout(self.defined_types, layout)),
+        }
+    }
+
+    unsafe fn defined_type(&self, id: usize, src: *const u8, dst: *mut u8) {
+        let ty = &self.defined_types[id];
+
+        match &ty.ty {
+            TypeType::StructNamed { fields } => {
+                for f in fields.iter() {
+                    self.copy(&f.layout, src.add(f.offset), dst.add(f.offset));
+                }
+            }
+            TypeType::StructUnnamed { fields } => {
+                for f in fields.iter() {
+                    self.copy(&f.layout, src.add(f.offset), dst.add(f.offset));
+                }
+            }
+            TypeType::Enum { variants, layout } => {
+                let (EnumLayout::Inline { tag_size } | EnumLayout::Separate { tag_size }) = layout
+                else {
+                    unreachable!("rejected by unchecked_type")
+                };
+                copy_nonoverlapping(src, dst, *tag_size);
+
+                let discriminant = read_discriminant(&ty.repr, src);
+                // Only the tag of variants unknown to this version of a non-exhaustive enum can be copied
+                let Some(variant) = variants
+                    .iter()
+                    .find(|v| Some(v.discriminant) == discriminant)
+                else {
+                    return;
+                };
+                match &variant.ty {
+                    EnumVariantType::Unit => {}
+                    EnumVariantType::Tuple(fields) => {
+                        for f in fields.iter() {
+                            self.copy(&f.layout, src.add(f.offset), dst.add(f.offset));
+                        }
+                    }
+                    EnumVariantType::Struct(fields) => {
+                        for f in fields.iter() {
+                            self.copy(&f.layout, src.add(f.offset), dst.add(f.offset));
+                        }
+                    }
+                }
+            }
+            TypeType::StructUnit | TypeType::Union { .. } | TypeType::Opaque => {}
+        }
+    }
+}
@@ -0,0 +1,587 @@
+use super::{
+    unchecked_type, validate, write::integer, Fields, InvalidValue, ReadError, Value, ValueBuffer,
+    WriteError,
+};
+use crate::{
+    layout::{
+        Discriminant, EnumVariantType, KnownType, Layout, NamedField, TypeType, UnnamedField,
+    },
+    types::SOption,
+    TypeLayout,
+};
+use std::fmt::Display;
+
+/// Why a value couldn't be migrated
+#[derive(Debug, PartialEq, Clone)]
+pub enum MigrateError {
+    /// One of the types contains pointers, unions or opaque types (`name` is the type containing them, or the type itself)
+    Unsupported {
+        name: String,
+    },
+    /// The bytes are not a valid value of the old layout
+    InvalidValue(InvalidValue),
+    Read(ReadError),
+    /// A field which is not in the old layout can't be zero (a `NonZero*` for example),
+    /// and no value was given for it by the callback
+    NoDefault {
+        name: String,
+        field: String,
+    },
+    /// The value doesn't fit the new layout (a variant was removed, or a number doesn't fit in a narrower type for example)
+    Write(WriteError),
+}
+
+impl Display for MigrateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MigrateError::Unsupported { name } => write!(
+                f,
+                "`{name}` contains pointers, unions or opaque types, which can't be migrated"
+            ),
+            MigrateError::InvalidValue(err) => write!(f, "invalid value: {err}"),
+            MigrateError::Read(err) => Display::fmt(err, f),
+            MigrateError::NoDefault { name, field } => {
+                write!(
+                    f,
+                    "no default value for the new field `{field}` of `{name}`"
+                )
+            }
+            MigrateError::Write(err) => Display::fmt(err, f),
+        }
+    }
+}
+
+impl std::error::Error for MigrateError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            MigrateError::InvalidValue(err) => Some(err),
+            MigrateError::Read(err) => Some(err),
+            MigrateError::Write(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+/// The fields of a struct or an enum variant in the new layout
+enum NewFields<'a> {
+    Unit,
+    Named(&'a [NamedField]),
+    Unnamed(&'a [UnnamedField]),
+}
+
+/// Converts values from an older layout of a type to a newer one
+///
+/// - Struct fields and enum variants are matched by name, so they can be reordered.
+/// - Fields which are not in the old layout are zeroed, unless the [callback](Migration::with_defaults) gives a value for them.
+///   Fields which are not in the new layout are dropped.
+/// - Numbers can be changed to wider types (`u16` to `u32`, `f32` to `f64` or `u32` to `f64` for example),
+///   and to narrower ones as long as the value fits.
+/// - Arrays can be made longer, with the new elements zeroed.
+///
+/// A value of a variant that was removed can't be migrated.
+pub struct Migration<'a> {
+    old: &'a TypeLayout,
+    new: &'a TypeLayout,
+    #[allow(clippy::type_complexity)]
+    defaults: Option<Box<dyn FnMut(&str, &str) -> Option<Value<'static>> + 'a>>,
+}
+
+impl<'a> Migration<'a> {
+    pub fn new(old: &'a TypeLayout, new: &'a TypeLayout) -> Self {
+        Self {
+            old,
+            new,
+            defaults: None,
+        }
+    }
+    /// Sets a callback giving the values of fields which are not in the old layout
+    ///
+    /// It's called with the name of the struct (or `Enum::Variant`) and the name of the field
+    /// (or its index, for tuple structs and variants). If it returns `None`, the field is zeroed.
+    pub fn with_defaults(
+        mut self,
+        defaults: impl FnMut(&str, &str) -> Option<Value<'static>> + 'a,
+    ) -> Self {
+        self.defaults = Some(Box::new(defaults));
+        self
+    }
+    /// Converts a value of the old layout, which must be aligned, into a value of the new layout
+    pub fn migrate(&mut self, bytes: &[u8]) -> Result<ValueBuffer, MigrateError> {
+        for layout in [self.old, self.new] {
+            if let Some(name) = unchecked_type(layout, "the value") {
+                return Err(MigrateError::Unsupported {
+                    name: name.to_owned(),
+                });
+            }
+        }
+        validate(bytes, self.old).map_err(MigrateError::InvalidValue)?;
+
+        // SAFETY: the bytes are a valid value of the old layout, which has no pointers
+        let value = unsafe { Value::from_bytes(self.old, bytes) }.map_err(MigrateError::Read)?;
+        let new = self.new;
+        let value = self.convert(value, &new.layout)?;
+
+        value.write(new).map_err(MigrateError::Write)
+    }
+
+    /// Converts a value to fit the layout from the new [`TypeLayout`]
+    fn convert(&mut self, value: Value<'a>, layout: &'a Layout) -> Result<Value<'a>, MigrateError> {
+        let new = self.new;
+
+        Ok(match (layout, value) {
+            (Layout::F32, Value::F32(v)) => Value::F32(v),
+            (Layout::F32, value) => match float(&value) {
+                Some(v) if v as f32 as f64 == v => Value::F32(v as f32),
+                _ => value,
+            },
+            (Layout::F64, value) => float(&value).map(Value::F64).unwrap_or(value),
+            (Layout::Array { len, layout }, Value::Array(values)) => {
+                let mut values = values
+                    .into_iter()
+                    .map(|value| self.convert(value, layout))
+                    .collect::<Result<Vec<_>, _>>()?;
+                while values.len() < *len {
+                    let index = values.len().to_string();
+                    values.push(self.zero(layout, "[_]", &index)?);
+                }
+                Value::Array(values)
+            }
+            (Layout::NicheOption(layout), Value::Option(Some(value))) => {
+                Value::Option(Some(Box::new(self.convert(*value, layout)?)))
+            }
+            (
+                Layout::NonExhaustive(layout)
+                | Layout::Atomic(layout)
+                | Layout::NonZero(layout)
+                | Layout::C { layout, .. },
+                value,
+            ) => self.convert(value, layout)?,
+            (Layout::DefinedType { id }, value) => {
+                let ty = &new.defined_types[*id];
+                let name: &'a str = &ty.name;
+
+                match (&ty.ty, value) {
+                    (TypeType::StructNamed { fields: new }, Value::Struct { fields, .. }) => {
+                        Value::Struct {
+                            name,
+                            fields: self.fields(name, NewFields::Named(new), fields)?,
+                        }
+                    }
+                    (TypeType::StructUnnamed { fields: new }, Value::Struct { fields, .. }) => {
+                        Value::Struct {
+                            name,
+                            fields: self.fields(name, NewFields::Unnamed(new), fields)?,
+                        }
+                    }
+                    (TypeType::Enum { variants, .. }, Value::Option(Some(value)))
+                        if ty.known == SOption::Some(KnownType::Option) =>
+                    {
+                        let layout = variants
+                            .iter()
+                            .find(|v| &*v.name == "Some")
+                            .and_then(|v| match &v.ty {
+                                EnumVariantType::Tuple(fields) => fields.first(),
+                                _ => None,
+                            })
+                            .map(|f| &f.layout);
+                        match layout {
+                            Some(layout) => {
+                                Value::Option(Some(Box::new(self.convert(*value, layout)?)))
+                            }
+                            None => Value::Option(Some(value)),
+                        }
+                    }
+                    (
+                        TypeType::Enum { variants, .. },
+                        Value::Enum {
+                            variant, fields, ..
+                        },
+                    ) => {
+                        let Some(new) = variants.iter().find(|v| &*v.name == variant) else {
+                            // Can't be written, which is reported by the writer
+                            return Ok(Value::Enum {
+                                name,
+                                variant,
+                                fields,
+                            });
+                        };
+                        let variant: &'a str = &new.name;
+                        let new = match &new.ty {
+                            EnumVariantType::Unit => NewFields::Unit,
+                            EnumVariantType::Tuple(fields) => NewFields::Unnamed(fields),
+                            EnumVariantType::Struct(fields) => NewFields::Named(fields),
+                        };
+
+                        Value::Enum {
+                            name,
+                            variant,
+                            fields: self.fields(&format!("{name}::{variant}"), new, fields)?,
+                        }
+                    }
+                    (_, value) => value,
+                }
+            }
+            // Everything else is either the same, or converted by the writer if possible
+            (_, value) => value,
+        })
+    }
+
+    /// Converts the fields of a struct or an enum variant to the `new` ones, matching them by name
+    fn fields(
+        &mut self,
+        name: &str,
+        new: NewFields<'a>,
+        mut old: Fields<'a>,
+    ) -> Result<Fields<'a>, MigrateError> {
+        Ok(match new {
+            NewFields::Unit => Fields::Unit,
+            NewFields::Named(new) => {
+                let mut old = match &mut old {
+                    Fields::Named(old) => std::mem::take(old),
+                    _ => Vec::new(),
+                };
+
+                let mut fields = Vec::with_capacity(new.len());
+                for f in new {
+                    let value = match old.iter().position(|(n, _)| *n == &*f.name) {
+                        Some(i) => self.convert(old.swap_remove(i).1, &f.layout)?,
+                        None => self.default(&f.layout, name, &f.name)?,
+                    };
+                    fields.push((&*f.name, value));
+                }
+                Fields::Named(fields)
+            }
+            NewFields::Unnamed(new) => {
+                let mut old = match old {
+                    Fields::Unnamed(old) => old.into_iter(),
+                    _ => Vec::new().into_iter(),
+                };
+
+                let mut fields = Vec::with_capacity(new.len());
+                for (i, f) in new.iter().enumerate() {
+                    let value = match old.next() {
+                        Some(value) => self.convert(value, &f.layout)?,
+                        None => self.default(&f.layout, name, &i.to_string())?,
+                    };
+                    fields.push(value);
+                }
+                Fields::Unnamed(fields)
+            }
+        })
+    }
+
+    /// The value of a field which is not in the old layout
+    fn default(
+        &mut self,
+        layout: &'a Layout,
+        name: &str,
+        field: &str,
+    ) -> Result<Value<'a>, MigrateError> {
+        match self.defaults.as_mut().and_then(|f| f(name, field)) {
+            Some(value) => self.convert(value, layout),
+            None => self.zero(layout, name, field),
+        }
+    }
+
+    /// A value of the layout with all bytes zero if there is one, or `None` for [`SOption`][crate::types::SOption]s
+    fn zero(
+        &mut self,
+        layout: &'a Layout,
+        name: &str,
+        field: &str,
+    ) -> Result<Value<'a>, MigrateError> {
+        let no_default = || MigrateError::NoDefault {
+            name: name.to_owned(),
+            field: field.to_owned(),
+        };
+
+        Ok(match layout {
+            Layout::Void => Value::Unit,
+            Layout::Bool => Value::Bool(false),
+            Layout::F32 => Value::F32(0.0),
+            Layout::F64 => Value::F64(0.0),
+            Layout::Char => Value::Char('\0'),
+            Layout::U8
+            | Layout::U16
+            | Layout::U32
+            | Layout::U64
+            | Layout::U128
+            | Layout::USize
+            | Layout::I8
+            | Layout::I16
+            | Layout::I32
+            | Layout::I64
+            | Layout::I128
+            | Layout::ISize => Value::U8(0),
+            Layout::Array { len, layout } => Value::Array(
+                (0..*len)
+                    .map(|_| self.zero(layout, name, field))
+                    .collect::<Result<_, _>>()?,
+            ),
+            Layout::NicheOption(_) => Value::Option(None),
+            Layout::NonExhaustive(layout) | Layout::Atomic(layout) | Layout::C { layout, .. } => {
+                self.zero(layout, name, field)?
+            }
+            Layout::DefinedType { id } => {
+                let ty = &self.new.defined_types[*id];
+                let name: &'a str = &ty.name;
+
+                match &ty.ty {
+                    TypeType::StructNamed { fields } => Value::Struct {
+                        name,
+                        fields: Fields::Named(
+                            fields
+                                .iter()
+                                .map(|f| Ok((&*f.name, self.zero(&f.layout, name, &f.name)?)))
+                                .collect::<Result<_, _>>()?,
+                        ),
+                    },
+                    TypeType::StructUnnamed { fields } => Value::Struct {
+                        name,
+                        fields: Fields::Unnamed(
+                            fields
+                                .iter()
+                                .enumerate()
+                                .map(|(i, f)| self.zero(&f.layout, name, &i.to_string()))
+                                .collect::<Result<_, _>>()?,
+                        ),
+                    },
+                    TypeType::StructUnit => Value::Struct {
+                        name,
+                        fields: Fields::Unit,
+                    },
+                    TypeType::Enum { .. } if ty.known == SOption::Some(KnownType::Option) => {
+                        Value::Option(None)
+                    }
+                    // Only an enum with a variant of discriminant 0 can be zero
+                    TypeType::Enum { variants, .. } => {
+                        let Some(variant) = variants.iter().find(|v| {
+                            matches!(
+                                v.discriminant,
+                                Discriminant::Unsigned(0) | Discriminant::Signed(0)
+                            )
+                        }) else {
+                            return Err(no_default());
+                        };
+
+                        let variant_name: &'a str = &variant.name;
+                        let fields = match &variant.ty {
+                            EnumVariantType::Unit => Fields::Unit,
+                            EnumVariantType::Tuple(fields) => Fields::Unnamed(
+                                fields
+                                    .iter()
+                                    .enumerate()
+                                    .map(|(i, f)| self.zero(&f.layout, name, &i.to_string()))
+                                    .collect::<Result<_, _>>()?,
+                            ),
+                            EnumVariantType::Struct(fields) => Fields::Named(
+                                fields
+                                    .iter()
+                                    .map(|f| Ok((&*f.name, self.zero(&f.layout, name, &f.name)?)))
+                                    .collect::<Result<_, _>>()?,
+                            ),
+                        };
+
+                        Value::Enum {
+                            name,
+                            variant: variant_name,
+                            fields,
+                        }
+                    }
+                    TypeType::Union { .. } | TypeType::Opaque => return Err(no_default()),
+                }
+            }
+            Layout::NonZero(_)
+            | Layout::ConstPtr(_)
+            | Layout::MutPtr(_)
+            | Layout::Ref(_)
+            | Layout::MutRef(_)
+            | Layout::FunctionPointer { .. }
+            | Layout::RelPtr(_) => return Err(no_default()),
+        })
+    }
+}
+
+/// The value as a float, if it can be converted losslessly
+fn float(value: &Value) -> Option<f64> {
+    match *value {
+        Value::F32(v) => Some(v as f64),
+        Value::F64(v) => Some(v),
+        // Casts from floats saturate, so values that round up to 2^128 or 2^127 would compare equal to
+        // `u128::MAX` and `i128::MAX` after the round trip, which is why they are excluded first.
+        // Both bounds are exact, since `u128::MAX as f64` is 2^128 and `i128::MAX as f64` is 2^127.
+        _ => match integer(value)? {
+            Discriminant::Unsigned(v) => {
+                let f = v as f64;
+                (f < u128::MAX as f64 && f as u128 == v).then_some(f)
+            }
+            Discriminant::Signed(v) => {
+                let f = v as f64;
+                (f < i128::MAX as f64 && f as i128 == v).then_some(f)
+            }
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{float, MigrateError, Migration};
+    use crate::{
+        dynamic::{Value, WriteError},
+        TypeInfo,
+    };
+    use std::num::NonZeroU32;
+
+    mod v1 {
+        use crate::TypeInfo;
+
+        #[repr(u8)]
+        #[derive(TypeInfo)]
+        #[allow(dead_code)]
+        pub enum Mode {
+            Fast,
+            Careful { retries: u8 },
+            Legacy,
+        }
+
+        #[repr(C)]
+        #[derive(TypeInfo)]
+        pub struct State {
+            pub version: u16,
+            pub ratio: f32,
+            pub mode: Mode,
+            pub removed: bool,
+            pub history: [i8; 2],
+        }
+    }
+
+    mod v2 {
+        use crate::{types::SOption, TypeInfo};
+
+        #[repr(u8)]
+        #[derive(TypeInfo, Debug, PartialEq)]
+        pub enum Mode {
+            Careful { timeout: u32, retries: u16 },
+            Fast,
+        }
+
+        #[repr(C)]
+        #[derive(TypeInfo, Debug, PartialEq)]
+        pub struct State {
+            pub mode: Mode,
+            pub history: [i64; 3],
+            pub version: u32,
+            pub ratio: f64,
+            pub added: u64,
+            pub label: SOption<u8>,
+        }
+    }
+
+    #[repr(C)]
+    #[derive(TypeInfo)]
+    struct OldCounter {
+        step: u8,
+    }
+
+    #[repr(C)]
+    #[derive(TypeInfo)]
+    #[allow(dead_code)]
+    struct Counter {
+        step: u8,
+        count: NonZeroU32,
+    }
+
+    fn migrate<Old: TypeInfo, New: TypeInfo>(
+        old: &Old,
+        migration: impl FnOnce(Migration) -> Migration,
+    ) -> Result<New, MigrateError> {
+        let bytes = Value::of(old).unwrap().write(Old::layout_ref()).unwrap();
+        let new = migration(Migration::new(Old::layout_ref(), New::layout_ref()))
+            .migrate(bytes.as_bytes())?;
+
+        // SAFETY: the value was written according to the layout of New, and has no pointers
+        Ok(unsafe { new.as_ptr().cast::<New>().read() })
+    }
+
+    #[test]
+    fn fields_and_variants() {
+        let old = v1::State {
+            version: 3,
+            ratio: 0.5,
+            mode: v1::Mode::Careful { retries: 4 },
+            removed: true,
+            history: [-1, 2],
+        };
+
+        assert_eq!(
+            migrate::<_, v2::State>(&old, |m| m),
+            Ok(v2::State {
+                mode: v2::Mode::Careful {
+                    timeout: 0,
+                    retries: 4
+                },
+                history: [-1, 2, 0],
+                version: 3,
+                ratio: 0.5,
+                added: 0,
+                label: None.into(),
+            })
+        );
+
+        let old = v1::State {
+            mode: v1::Mode::Fast,
+            ..old
+        };
+        assert_eq!(
+            migrate::<_, v2::State>(&old, |m| m.with_defaults(|name, field| {
+                match (name, field) {
+                    ("State", "added") => Some(Value::U64(7)),
+                    ("State", "label") => Some(Value::Option(Some(Box::new(Value::U8(1))))),
+                    _ => None,
+                }
+            })),
+            Ok(v2::State {
+                mode: v2::Mode::Fast,
+                history: [-1, 2, 0],
+                version: 3,
+                ratio: 0.5,
+                added: 7,
+                label: Some(1).into(),
+            })
+        );
+
+        let old = v1::State {
+            mode: v1::Mode::Legacy,
+            ..old
+        };
+        assert!(matches!(
+            migrate::<_, v2::State>(&old, |m| m),
+            Err(MigrateError::Write(WriteError::UnknownVariant { .. }))
+        ));
+    }
+
+    #[test]
+    fn lossless_floats() {
+        assert_eq!(float(&Value::U64(1 << 53)), Some(9007199254740992.0));
+        assert_eq!(float(&Value::U64((1 << 53) + 1)), None);
+        assert_eq!(float(&Value::U128(1 << 127)), Some(2f64.powi(127)));
+        assert_eq!(float(&Value::I128(i128::MIN)), Some(-(2f64.powi(127))));
+        // Round to 2^128 and 2^127, which saturate back to the original values
+        assert_eq!(float(&Value::U128(u128::MAX)), None);
+        assert_eq!(float(&Value::I128(i128::MAX)), None);
+    }
+
+    #[test]
+    fn no_default() {
+        assert_eq!(
+            migrate::<_, Counter>(&OldCounter { step: 1 }, |m| m).err(),
+            Some(MigrateError::NoDefault {
+                name: "Counter".to_owned(),
+                field: "count".to_owned()
+            })
+        );
+        assert!(migrate::<_, Counter>(&OldCounter { step: 1 }, |m| m
+            .with_defaults(|_, _| Some(Value::U32(1))))
+        .is_ok());
+    }
+}
@@ -0,0 +1,412 @@
+//! Reading and writing values whose types are only known at runtime, from their [`TypeLayout`]s.
+
+mod copy;
+mod migrate;
+mod read;
+mod validate;
+mod write;
+
+pub(crate) use copy::Copier;
+pub use migrate::{MigrateError, Migration};
+pub use read::ReadError;
+pub(crate) use validate::validate_relative;
+pub use validate::{validate, InvalidValue};
+pub use write::{ValueBuffer, WriteError};
+
+use crate::{
+    layout::{
+        DefinedType, Discriminant, EnumLayout, EnumVariantType, KnownType, Layout, NamedField,
+        Repr, ReprPrimitive, TypeType,
+    },
+    types::SOption,
+    TypeLayout,
+};
+use std::{
+    collections::HashSet,
+    ffi::c_int,
+    fmt::Debug,
+    mem::{align_of, size_of},
+    ptr::read_unaligned,
+};
+
+/// A value read from memory according to a [`TypeLayout`]
+///
+/// [`SStr`][crate::types::SStr], [`SVec`][crate::types::SVec], [`SOption`] and
+/// the other FFI-safe equivalents from [`types`][crate::types] are recognized and read as the values they contain.
+/// Raw pointers, references and function pointers are only read as addresses.
+///
+/// The [`Debug`] implementation prints values like `#[derive(Debug)]` would, so use `{:#?}` for pretty-printing.
+#[derive(Clone, PartialEq)]
+pub enum Value<'a> {
+    /// Zero-sized types without fields (`()` for example)
+    Unit,
+    Bool(bool),
+    U8(u8),
+    U16(u16),
+    U32(u32),
+    U64(u64),
+    U128(u128),
+    USize(usize),
+    I8(i8),
+    I16(i16),
+    I32(i32),
+    I64(i64),
+    I128(i128),
+    ISize(isize),
+    F32(f32),
+    F64(f64),
+    Char(char),
+    /// The address of a raw pointer, a reference or a function pointer
+    Pointer(usize),
+    Array(Vec<Value<'a>>),
+    Struct {
+        name: &'a str,
+        fields: Fields<'a>,
+    },
+    Enum {
+        name: &'a str,
+        variant: &'a str,
+        fields: Fields<'a>,
+    },
+    /// A value of a [non-exhaustive][crate::types::SNonExhaustive] enum, of a variant not listed in the layout
+    UnknownVariant {
+        name: &'a str,
+        discriminant: Discriminant,
+    },
+    /// Unions and [opaque][derive@crate::TypeInfo] types, whose contents can't be interpreted
+    Opaque {
+        name: &'a str,
+        size: usize,
+    },
+    /// String types like [`SStr`][crate::types::SStr] and [`SString`][crate::types::SString].
+    /// Invalid UTF-8 is replaced with `U+FFFD`.
+    Str(String),
+    /// Slice types like [`SSlice`][crate::types::SSlice] and [`SVec`][crate::types::SVec]
+    List(Vec<Value<'a>>),
+    /// [`SOption`] and `Option`s of [niche-optimized][crate::NicheOptimized] types
+    Option(Option<Box<Value<'a>>>),
+    /// [`SBox`][crate::types::SBox]
+    Box(Box<Value<'a>>),
+}
+
+/// The fields of a [`Value::Struct`] or [`Value::Enum`]
+#[derive(Clone, PartialEq)]
+pub enum Fields<'a> {
+    Unit,
+    Named(Vec<(&'a str, Value<'a>)>),
+    Unnamed(Vec<Value<'a>>),
+}
+
+impl<'a> Debug for Value<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Unit => f.write_str("()"),
+            Value::Bool(v) => Debug::fmt(v, f),
+            Value::U8(v) => Debug::fmt(v, f),
+            Value::U16(v) => Debug::fmt(v, f),
+            Value::U32(v) => Debug::fmt(v, f),
+            Value::U64(v) => Debug::fmt(v, f),
+            Value::U128(v) => Debug::fmt(v, f),
+            Value::USize(v) => Debug::fmt(v, f),
+            Value::I8(v) => Debug::fmt(v, f),
+            Value::I16(v) => Debug::fmt(v, f),
+            Value::I32(v) => Debug::fmt(v, f),
+            Value::I64(v) => Debug::fmt(v, f),
+            Value::I128(v) => Debug::fmt(v, f),
+            Value::ISize(v) => Debug::fmt(v, f),
+            Value::F32(v) => Debug::fmt(v, f),
+            Value::F64(v) => Debug::fmt(v, f),
+            Value::Char(v) => Debug::fmt(v, f),
+            Value::Pointer(address) => write!(f, "{address:#x}"),
+            Value::Array(values) | Value::List(values) => f.debug_list().entries(values).finish(),
+            Value::Struct { name, fields } => fields.fmt(name, f),
+            Value::Enum {
+                variant, fields, ..
+            } => fields.fmt(variant, f),
+            Value::UnknownVariant { name, discriminant } => match discriminant {
+                Discriminant::Unsigned(d) => write!(f, "<unknown variant {d} of {name}>"),
+                Discriminant::Signed(d) => write!(f, "<unknown variant {d} of {name}>"),
+            },
+            Value::Opaque { name, size } => write!(f, "<{size} bytes of {name}>"),
+            Value::Str(s) => Debug::fmt(s, f),
+            Value::Option(None) => f.write_str("None"),
+            Value::Option(Some(value)) => f.debug_tuple("Some").field(value).finish(),
+            Value::Box(value) => Debug::fmt(value, f),
+        }
+    }
+}
+
+impl<'a> Fields<'a> {
+    fn fmt(&self, name: &str, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Fields::Unit => f.write_str(name),
+            Fields::Named(fields) => {
+                let mut s = f.debug_struct(name);
+                for (name, value) in fields {
+                    s.field(name, value);
+                }
+                s.finish()
+            }
+            Fields::Unnamed(fields) => {
+                let mut t = f.debug_tuple(name);
+                for value in fields {
+                    t.field(value);
+                }
+                t.finish()
+            }
+        }
+    }
+}
+
+impl<'a> Debug for Fields<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.fmt("", f)
+    }
+}
+
+/// The size of a value of the given layout, on the current target
+pub(crate) fn size_of_layout(defined_types: &[DefinedType], layout: &Layout) -> usize {
+    match layout {
+        Layout::Void => 0,
+        Layout::U8 | Layout::I8 | Layout::Bool => 1,
+        Layout::U16 | Layout::I16 => 2,
+        Layout::U32 | Layout::I32 | Layout::F32 | Layout::Char => 4,
+        Layout::U64 | Layout::I64 | Layout::F64 => 8,
+        Layout::U128 | Layout::I128 => 16,
+        Layout::USize
+        | Layout::ISize
+        | Layout::ConstPtr(_)
+        | Layout::MutPtr(_)
+        | Layout::Ref(_)
+        | Layout::MutRef(_)
+        | Layout::FunctionPointer { .. }
+        | Layout::RelPtr(_) => size_of::<usize>(),
+        // Decoded layouts are checked not to overflow, so this only fails for layouts constructed by hand
+        Layout::Array { len, layout } => len
+            .checked_mul(size_of_layout(defined_types, layout))
+            .expect("the size of the array overflows"),
+        Layout::DefinedType { id } => defined_types[*id].size,
+        Layout::NonExhaustive(layout)
+        | Layout::Atomic(layout)
+        | Layout::NonZero(layout)
+        | Layout::NicheOption(layout)
+        | Layout::C { layout, .. } => size_of_layout(defined_types, layout),
+    }
+}
+
+/// The alignment of a value of the given layout, on the current target
+pub(crate) fn align_of_layout(defined_types: &[DefinedType], layout: &Layout) -> usize {
+    match layout {
+        Layout::Void | Layout::U8 | Layout::I8 | Layout::Bool => 1,
+        Layout::U16 | Layout::I16 => align_of::<u16>(),
+        Layout::U32 | Layout::I32 | Layout::F32 | Layout::Char => align_of::<u32>(),
+        Layout::U64 | Layout::I64 | Layout::F64 => align_of::<u64>(),
+        Layout::U128 | Layout::I128 => align_of::<u128>(),
+        Layout::USize
+        | Layout::ISize
+        | Layout::ConstPtr(_)
+        | Layout::MutPtr(_)
+        | Layout::Ref(_)
+        | Layout::MutRef(_)
+        | Layout::FunctionPointer { .. }
+        | Layout::RelPtr(_) => align_of::<usize>(),
+        Layout::Array { layout, .. } => align_of_layout(defined_types, layout),
+        Layout::DefinedType { id } => defined_types[*id].align,
+        Layout::NonExhaustive(layout)
+        | Layout::NonZero(layout)
+        | Layout::NicheOption(layout)
+        | Layout::C { layout, .. } => align_of_layout(defined_types, layout),
+        // Atomics are always aligned to their size
+        Layout::Atomic(layout) => size_of_layout(defined_types, layout),
+    }
+}
+
+/// Finds a type whose values can't be fully checked by [`validate`]: one containing pointers (other than
+/// [relative][crate::types::RelPtr] ones), unions or opaque types, or an enum without a stable layout.
+/// Returns its name, or `root` if the layout itself is a pointer.
+pub(crate) fn unchecked_type<'a>(layout: &'a TypeLayout, root: &'a str) -> Option<&'a str> {
+    fn check<'a>(
+        defined_types: &'a [DefinedType],
+        layout: &'a Layout,
+        parent: &'a str,
+        visited: &mut HashSet<usize>,
+    ) -> Option<&'a str> {
+        match layout {
+            Layout::ConstPtr(_)
+            | Layout::MutPtr(_)
+            | Layout::Ref(_)
+            | Layout::MutRef(_)
+            | Layout::FunctionPointer { .. } => Some(parent),
+            Layout::Array { layout, .. }
+            | Layout::NonExhaustive(layout)
+            | Layout::Atomic(layout)
+            | Layout::NonZero(layout)
+            | Layout::NicheOption(layout)
+            | Layout::C { layout, .. }
+            | Layout::RelPtr(layout) => check(defined_types, layout, parent, visited),
+            Layout::DefinedType { id } => {
+                if !visited.insert(*id) {
+                    return None;
+                }
+                let ty = &defined_types[*id];
+                let name = &*ty.name;
+
+                let fields: Vec<&Layout> = match &ty.ty {
+                    TypeType::StructNamed { fields } => fields.iter().map(|f| &f.layout).collect(),
+                    TypeType::StructUnnamed { fields } => {
+                        fields.iter().map(|f| &f.layout).collect()
+                    }
+                    TypeType::StructUnit => Vec::new(),
+                    TypeType::Enum { layout, .. } if *layout == EnumLayout::Unspecified => {
+                        return Some(name)
+                    }
+                    TypeType::Enum { variants, .. } => variants
+                        .iter()
+                        .flat_map(|v| match &v.ty {
+                            EnumVariantType::Unit => Vec::new(),
+                            EnumVariantType::Tuple(fields) => {
+                                fields.iter().map(|f| &f.layout).collect()
+                            }
+                            EnumVariantType::Struct(fields) => {
+                                fields.iter().map(|f| &f.layout).collect()
+                            }
+                        })
+                        .collect(),
+                    TypeType::Union { .. } | TypeType::Opaque => return Some(name),
+                };
+                fields
+                    .into_iter()
+                    .find_map(|layout| check(defined_types, layout, name, visited))
+            }
+            _ => None,
+        }
+    }
+
+    check(
+        &layout.defined_types,
+        &layout.layout,
+        root,
+        &mut HashSet::new(),
+    )
+}
+
+/// An FFI-safe equivalent of a standard library type from [`types`][crate::types],
+/// whose values are read as the values they contain
+enum Equivalent<'a> {
+    /// [`SSlice`][crate::types::SSlice], [`SVec`][crate::types::SVec] and the other slice-like types
+    Slice(SliceFields<'a>),
+    /// [`SStr`][crate::types::SStr], [`SString`][crate::types::SString] and the other string types,
+    /// whose `inner` field at `offset` is a slice of bytes
+    Str {
+        offset: usize,
+        slice: SliceFields<'a>,
+    },
+    /// [`SBox`][crate::types::SBox], whose `ptr` field at `offset` points to a value of `layout`
+    Box { offset: usize, layout: &'a Layout },
+}
+
+/// The fields of a slice-like type that describe its elements
+struct SliceFields<'a> {
+    /// The field with the pointer to the elements
+    ptr: &'a NamedField,
+    /// Whether the pointer is a [relative][crate::types::RelPtr] one
+    relative: bool,
+    /// Whether the elements are owned through an allocator, like those of [`SVec`][crate::types::SVec]
+    owned: bool,
+    len_offset: usize,
+    /// The layout of the elements
+    layout: &'a Layout,
+}
+
+/// Recognizes the FFI-safe equivalents of standard library types by their [markers][KnownType], reprs and all of their fields,
+/// so that other types which happen to share their names are read like any other type.
+fn ffi_safe_equivalent<'a>(
+    defined_types: &'a [DefinedType],
+    ty: &'a DefinedType,
+) -> Option<Equivalent<'a>> {
+    let TypeType::StructNamed { fields } = &ty.ty else {
+        return None;
+    };
+    let SOption::Some(known) = ty.known else {
+        return None;
+    };
+    if !ty.repr.c {
+        return None;
+    }
+
+    let names: Vec<&str> = fields.iter().map(|f| &*f.name).collect();
+    let slice = |relative, owned| {
+        let (ptr, len) = (&fields[0], &fields[1]);
+        let layout = match (&ptr.layout, relative) {
+            (Layout::ConstPtr(layout) | Layout::MutPtr(layout), false)
+            | (Layout::RelPtr(layout), true) => layout,
+            _ => return None,
+        };
+
+        (len.layout == Layout::USize).then_some(SliceFields {
+            ptr,
+            relative,
+            owned,
+            len_offset: len.offset,
+            layout,
+        })
+    };
+
+    match (known, &names[..]) {
+        (KnownType::Slice, ["ptr", "len", "_phantom"]) => {
+            slice(false, false).map(Equivalent::Slice)
+        }
+        (
+            KnownType::Slice,
+            ["ptr", "len", "capacity", "allocator"] | ["ptr", "len", "allocator"],
+        ) => slice(false, true).map(Equivalent::Slice),
+        (KnownType::Slice, ["ptr", "len"]) => slice(true, false).map(Equivalent::Slice),
+        (KnownType::Str, ["inner"]) => {
+            let Layout::DefinedType { id } = fields[0].layout else {
+                return None;
+            };
+            match ffi_safe_equivalent(defined_types, &defined_types[id])? {
+                Equivalent::Slice(slice) if *slice.layout == Layout::U8 => Some(Equivalent::Str {
+                    offset: fields[0].offset,
+                    slice,
+                }),
+                _ => None,
+            }
+        }
+        (KnownType::Box, ["ptr", "allocator"]) => match &fields[0].layout {
+            Layout::ConstPtr(layout) => Some(Equivalent::Box {
+                offset: fields[0].offset,
+                layout,
+            }),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Reads the tag of an enum with the given repr, if it has a stable layout
+///
+/// # Safety
+///
+/// `ptr` must point to a value of an enum with this repr.
+pub(crate) unsafe fn read_discriminant(repr: &Repr, ptr: *const u8) -> Option<Discriminant> {
+    // SAFETY: the discriminant is always at the start of the enum, and has the size of the repr type
+    let discriminant = match repr.primitive {
+        SOption::Some(ReprPrimitive::U8) => read_unaligned(ptr.cast::<u8>()).into(),
+        SOption::Some(ReprPrimitive::U16) => read_unaligned(ptr.cast::<u16>()).into(),
+        SOption::Some(ReprPrimitive::U32) => read_unaligned(ptr.cast::<u32>()).into(),
+        SOption::Some(ReprPrimitive::U64) => read_unaligned(ptr.cast::<u64>()).into(),
+        SOption::Some(ReprPrimitive::U128) => read_unaligned(ptr.cast::<u128>()).into(),
+        SOption::Some(ReprPrimitive::USize) => read_unaligned(ptr.cast::<usize>()).into(),
+        SOption::Some(ReprPrimitive::I8) => read_unaligned(ptr.cast::<i8>()).into(),
+        SOption::Some(ReprPrimitive::I16) => read_unaligned(ptr.cast::<i16>()).into(),
+        SOption::Some(ReprPrimitive::I32) => read_unaligned(ptr.cast::<i32>()).into(),
+        SOption::Some(ReprPrimitive::I64) => read_unaligned(ptr.cast::<i64>()).into(),
+        SOption::Some(ReprPrimitive::I128) => read_unaligned(ptr.cast::<i128>()).into(),
+        SOption::Some(ReprPrimitive::ISize) => read_unaligned(ptr.cast::<isize>()).into(),
+        SOption::None if repr.c => read_unaligned(ptr.cast::<c_int>()).into(),
+        SOption::None => return None,
+    };
+
+    Some(discriminant)
+}
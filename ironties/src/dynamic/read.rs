@@ -0,0 +1,448 @@
+use super::{
+    ffi_safe_equivalent, read_discriminant, size_of_layout, Equivalent, Fields, SliceFields, Value,
+};
+use crate::{
+    layout::{
+        DefinedType, Discriminant, EnumLayout, EnumVariantType, KnownType, Layout, NamedField,
+        TypeType,
+    },
+    types::SOption,
+    LayoutHeader, TypeInfo, TypeLayout,
+};
+use std::{fmt::Display, ptr::read_unaligned};
+
+/// Why a [`Value`] couldn't be read
+#[derive(Debug, PartialEq, Clone)]
+pub enum ReadError {
+    /// The layout was produced for a different target (see [`LayoutHeader::same_target`])
+    DifferentTarget,
+    /// The buffer is smaller than the value
+    TooShort { size: usize, len: usize },
+    /// The variant of an enum without a stable layout (`#[ironties(unsafe_allow_repr_rust)]`) can't be determined
+    UnspecifiedEnumLayout { name: String },
+    /// The discriminant of an enum doesn't belong to any of its variants
+    InvalidDiscriminant {
+        name: String,
+        discriminant: Discriminant,
+    },
+    /// A `char` which is not a Unicode scalar value
+    InvalidChar(u32),
+}
+
+impl Display for ReadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReadError::DifferentTarget => {
+                write!(f, "the layout was produced for a different target")
+            }
+            ReadError::TooShort { size, len } => {
+                write!(
+                    f,
+                    "the value needs {size} bytes, but the buffer only has {len}"
+                )
+            }
+            ReadError::UnspecifiedEnumLayout { name } => {
+                write!(f, "the enum `{name}` doesn't have a stable layout")
+            }
+            ReadError::InvalidDiscriminant { name, discriminant } => {
+                write!(
+                    f,
+                    "invalid discriminant {discriminant:?} of the enum `{name}`"
+                )
+            }
+            ReadError::InvalidChar(value) => write!(f, "{value:#x} is not a valid `char`"),
+        }
+    }
+}
+
+impl std::error::Error for ReadError {}
+
+impl<'a> Value<'a> {
+    /// Reads a value of a type known at compile time, through its layout
+    pub fn of<T: TypeInfo>(value: &T) -> Result<Value<'static>, ReadError> {
+        // SAFETY: the layout describes T
+        unsafe { Value::read(T::layout_ref(), (value as *const T).cast()) }
+    }
+    /// Reads a value of the type described by `layout`, which must have been produced for the current target
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must point to a valid value of the type described by `layout`, which is not modified while it's being read.
+    /// The pointer doesn't need to be aligned.
+    pub unsafe fn read(layout: &'a TypeLayout, ptr: *const u8) -> Result<Self, ReadError> {
+        if !layout.header.same_target(&LayoutHeader::current()) {
+            return Err(ReadError::DifferentTarget);
+        }
+
+        Reader {
+            defined_types: &layout.defined_types,
+        }
+        .read(&layout.layout, ptr)
+    }
+    /// Reads a value of the type described by `layout` from the start of the buffer
+    ///
+    /// # Safety
+    ///
+    /// The same as for [`Value::read`]: the bytes must be a valid value, and any pointers in it must be valid too.
+    pub unsafe fn from_bytes(layout: &'a TypeLayout, bytes: &[u8]) -> Result<Self, ReadError> {
+        let size = size_of_layout(&layout.defined_types, &layout.layout);
+        if bytes.len() < size {
+            return Err(ReadError::TooShort {
+                size,
+                len: bytes.len(),
+            });
+        }
+
+        Value::read(layout, bytes.as_ptr())
+    }
+}
+
+struct Reader<'a> {
+    defined_types: &'a [DefinedType],
+}
+
+impl<'a> Reader<'a> {
+    unsafe fn read(&self, layout: &'a Layout, ptr: *const u8) -> Result<Value<'a>, ReadError> {
+        Ok(match layout {
+            Layout::Void => Value::Unit,
+            Layout::U8 => Value::U8(read_unaligned(ptr.cast())),
+            Layout::U16 => Value::U16(read_unaligned(ptr.cast())),
+            Layout::U32 => Value::U32(read_unaligned(ptr.cast())),
+            Layout::U64 => Value::U64(read_unaligned(ptr.cast())),
+            Layout::U128 => Value::U128(read_unaligned(ptr.cast())),
+            Layout::USize => Value::USize(read_unaligned(ptr.cast())),
+            Layout::I8 => Value::I8(read_unaligned(ptr.cast())),
+            Layout::I16 => Value::I16(read_unaligned(ptr.cast())),
+            Layout::I32 => Value::I32(read_unaligned(ptr.cast())),
+            Layout::I64 => Value::I64(read_unaligned(ptr.cast())),
+            Layout::I128 => Value::I128(read_unaligned(ptr.cast())),
+            Layout::ISize => Value::ISize(read_unaligned(ptr.cast())),
+            Layout::F32 => Value::F32(read_unaligned(ptr.cast())),
+            Layout::F64 => Value::F64(read_unaligned(ptr.cast())),
+            // Read as integers, so that invalid values don't cause undefined behaviour
+            Layout::Bool => Value::Bool(read_unaligned(ptr) != 0),
+            Layout::Char => {
+                let value: u32 = read_unaligned(ptr.cast());
+                Value::Char(char::from_u32(value).ok_or(ReadError::InvalidChar(value))?)
+            }
+            Layout::ConstPtr(_)
+            | Layout::MutPtr(_)
+            | Layout::Ref(_)
+            | Layout::MutRef(_)
+            | Layout::FunctionPointer { .. } => Value::Pointer(read_unaligned(ptr.cast())),
+            Layout::RelPtr(_) => {
+                let offset: isize = read_unaligned(ptr.cast());
+                Value::Pointer((ptr as usize).wrapping_add_signed(offset))
+            }
+            Layout::Array { len, layout } => {
+                let size = size_of_layout(self.defined_types, layout);
+                Value::Array(
+                    (0..*len)
+                        .map(|i| self.read(layout, ptr.add(i * size)))
+                        .collect::<Result<_, _>>()?,
+                )
+            }
+            Layout::DefinedType { id } => self.defined_type(*id, ptr, false)?,
+            Layout::NonExhaustive(layout) => match &**layout {
+                Layout::DefinedType { id } => self.defined_type(*id, ptr, true)?,
+                layout => self.read(layout, ptr)?,
+            },
+            Layout::NicheOption(layout) => {
+                let size = size_of_layout(self.defined_types, layout);
+                // Niche-optimized types don't have padding, so all of their bytes can be read
+                if (0..size).all(|i| read_unaligned(ptr.add(i)) == 0) {
+                    Value::Option(None)
+                } else {
+                    Value::Option(Some(Box::new(self.read(layout, ptr)?)))
+                }
+            }
+            Layout::Atomic(layout) | Layout::NonZero(layout) | Layout::C { layout, .. } => {
+                self.read(layout, ptr)?
+            }
+        })
+    }
+
+    /// `open` is whether the enum may have variants that are not listed (see [`SNonExhaustive`][crate::types::SNonExhaustive])
+    unsafe fn defined_type(
+        &self,
+        id: usize,
+        ptr: *const u8,
+        open: bool,
+    ) -> Result<Value<'a>, ReadError> {
+        let ty = &self.defined_types[id];
+        let name: &'a str = &ty.name;
+
+        Ok(match &ty.ty {
+            TypeType::StructNamed { fields } => {
+                if let Some(equivalent) = ffi_safe_equivalent(self.defined_types, ty) {
+                    return self.ffi_safe_equivalent(equivalent, ptr);
+                }
+
+                Value::Struct {
+                    name,
+                    fields: self.named_fields(fields, ptr)?,
+                }
+            }
+            TypeType::StructUnnamed { fields } => Value::Struct {
+                name,
+                fields: Fields::Unnamed(
+                    fields
+                        .iter()
+                        .map(|f| self.read(&f.layout, ptr.add(f.offset)))
+                        .collect::<Result<_, _>>()?,
+                ),
+            },
+            TypeType::StructUnit => Value::Struct {
+                name,
+                fields: Fields::Unit,
+            },
+            TypeType::Enum { variants, layout } => {
+                let discriminant = match (layout, read_discriminant(&ty.repr, ptr)) {
+                    (EnumLayout::Unspecified, _) | (_, None) => {
+                        return Err(ReadError::UnspecifiedEnumLayout {
+                            name: name.to_owned(),
+                        })
+                    }
+                    (_, Some(discriminant)) => discriminant,
+                };
+                let Some(variant) = variants.iter().find(|v| v.discriminant == discriminant) else {
+                    return match open {
+                        true => Ok(Value::UnknownVariant { name, discriminant }),
+                        false => Err(ReadError::InvalidDiscriminant {
+                            name: name.to_owned(),
+                            discriminant,
+                        }),
+                    };
+                };
+
+                let fields = match &variant.ty {
+                    EnumVariantType::Unit => Fields::Unit,
+                    EnumVariantType::Tuple(fields) => Fields::Unnamed(
+                        fields
+                            .iter()
+                            .map(|f| self.read(&f.layout, ptr.add(f.offset)))
+                            .collect::<Result<_, _>>()?,
+                    ),
+                    EnumVariantType::Struct(fields) => self.named_fields(fields, ptr)?,
+                };
+
+                let option = ty.known == SOption::Some(KnownType::Option);
+                match (option, &*variant.name, fields) {
+                    (true, "None", Fields::Unit) => Value::Option(None),
+                    (true, "Some", Fields::Unnamed(mut fields)) if fields.len() == 1 => {
+                        Value::Option(Some(Box::new(fields.remove(0))))
+                    }
+                    (_, variant, fields) => Value::Enum {
+                        name,
+                        variant,
+                        fields,
+                    },
+                }
+            }
+            TypeType::Union { .. } | TypeType::Opaque => Value::Opaque {
+                name,
+                size: ty.size,
+            },
+        })
+    }
+
+    unsafe fn named_fields(
+        &self,
+        fields: &'a [NamedField],
+        ptr: *const u8,
+    ) -> Result<Fields<'a>, ReadError> {
+        Ok(Fields::Named(
+            fields
+                .iter()
+                .map(|f| Ok((&*f.name, self.read(&f.layout, ptr.add(f.offset))?)))
+                .collect::<Result<_, _>>()?,
+        ))
+    }
+
+    /// Reads the values contained in FFI-safe equivalents of standard library types
+    unsafe fn ffi_safe_equivalent(
+        &self,
+        equivalent: Equivalent<'a>,
+        ptr: *const u8,
+    ) -> Result<Value<'a>, ReadError> {
+        Ok(match equivalent {
+            Equivalent::Slice(slice) => {
+                let (elements, len) = self.slice_parts(&slice, ptr);
+                let size = size_of_layout(self.defined_types, slice.layout);
+
+                Value::List(
+                    (0..len)
+                        .map(|i| self.read(slice.layout, elements.add(i * size)))
+                        .collect::<Result<_, _>>()?,
+                )
+            }
+            Equivalent::Str { offset, slice } => {
+                let (bytes, len) = self.slice_parts(&slice, ptr.add(offset));
+
+                let bytes = std::slice::from_raw_parts(bytes, len);
+                Value::Str(String::from_utf8_lossy(bytes).into_owned())
+            }
+            Equivalent::Box { offset, layout } => {
+                let value: *const u8 = read_unaligned(ptr.add(offset).cast());
+
+                Value::Box(Box::new(self.read(layout, value)?))
+            }
+        })
+    }
+
+    /// The pointer to the elements and the length of the slice-like type at `ptr`
+    unsafe fn slice_parts(&self, slice: &SliceFields, ptr: *const u8) -> (*const u8, usize) {
+        let field_ptr = ptr.add(slice.ptr.offset);
+        let elements = match slice.relative {
+            true => {
+                let offset: isize = read_unaligned(field_ptr.cast());
+                field_ptr.wrapping_offset(offset)
+            }
+            false => read_unaligned(field_ptr.cast()),
+        };
+
+        (elements, read_unaligned(ptr.add(slice.len_offset).cast()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        dynamic::{Fields, Value},
+        types::{SBox, SNonExhaustive, SOption, SStr, SVec},
+        TypeInfo,
+    };
+    use std::{marker::PhantomData, num::NonZeroU32};
+
+    #[repr(C)]
+    #[derive(TypeInfo)]
+    struct Message<'a> {
+        id: NonZeroU32,
+        text: SStr<'a>,
+        tags: SVec<u16>,
+        reply_to: Option<&'a u8>,
+        priority: SOption<Priority>,
+        payload: SBox<[u8; 2]>,
+    }
+
+    #[repr(u8)]
+    #[derive(TypeInfo)]
+    #[allow(dead_code)]
+    enum Priority {
+        Low,
+        High { level: i8 },
+    }
+
+    #[test]
+    fn read_values() {
+        let message = Message {
+            id: NonZeroU32::new(7).unwrap(),
+            text: SStr::new("hi"),
+            tags: SVec::from_vec(vec![1, 2]),
+            reply_to: None,
+            priority: SOption::Some(Priority::High { level: -3 }),
+            payload: SBox::new([4, 5]),
+        };
+        let value = Value::of(&message).unwrap();
+
+        let Value::Struct {
+            name: "Message",
+            fields: Fields::Named(fields),
+        } = &value
+        else {
+            panic!("expected a struct, got {value:?}");
+        };
+        assert_eq!(fields[0], ("id", Value::U32(7)));
+        assert_eq!(fields[1], ("text", Value::Str("hi".to_owned())));
+        assert_eq!(
+            fields[2],
+            ("tags", Value::List(vec![Value::U16(1), Value::U16(2)]))
+        );
+        assert_eq!(fields[3], ("reply_to", Value::Option(None)));
+        assert_eq!(
+            fields[4],
+            (
+                "priority",
+                Value::Option(Some(Box::new(Value::Enum {
+                    name: "Priority",
+                    variant: "High",
+                    fields: Fields::Named(vec![("level", Value::I8(-3))]),
+                })))
+            )
+        );
+
+        assert_eq!(
+            format!("{value:?}"),
+            r#"Message { id: 7, text: "hi", tags: [1, 2], reply_to: None, priority: Some(High { level: -3 }), payload: [4, 5] }"#
+        );
+    }
+
+    #[test]
+    fn unknown_variant() {
+        #[repr(u8)]
+        #[derive(TypeInfo)]
+        #[allow(dead_code)]
+        enum Old {
+            A,
+        }
+        #[repr(u8)]
+        #[derive(TypeInfo)]
+        #[allow(dead_code)]
+        enum New {
+            A,
+            B,
+        }
+
+        let new = SNonExhaustive::new(New::B);
+        // SAFETY: both enums have the same size, and the unknown variant is only read through the layout
+        let old: SNonExhaustive<Old> = unsafe { std::mem::transmute(new) };
+
+        assert_eq!(
+            format!("{:?}", Value::of(&old).unwrap()),
+            "<unknown variant 1 of Old>"
+        );
+    }
+
+    #[test]
+    fn lookalikes() {
+        #[repr(C)]
+        #[derive(TypeInfo)]
+        #[ironties(rename = "SSlice")]
+        struct Fake {
+            ptr: *const u8,
+            len: usize,
+            _phantom: PhantomData<u8>,
+        }
+
+        // Shares the name and fields of `SSlice`, but isn't marked as a slice
+        let bytes = [1u8, 2];
+        let fake = Fake {
+            ptr: bytes.as_ptr(),
+            len: 2,
+            _phantom: PhantomData,
+        };
+        let Value::Struct {
+            name: "SSlice",
+            fields: Fields::Named(fields),
+        } = Value::of(&fake).unwrap()
+        else {
+            panic!("expected a struct");
+        };
+        assert_eq!(fields[1], ("len", Value::USize(2)));
+    }
+
+    #[test]
+    fn from_bytes() {
+        let layout = u32::layout();
+        let bytes = 5u32.to_ne_bytes();
+
+        assert_eq!(
+            unsafe { Value::from_bytes(&layout, &bytes[1..]) },
+            Err(super::ReadError::TooShort { size: 4, len: 3 })
+        );
+        assert_eq!(
+            unsafe { Value::from_bytes(&layout, &bytes) },
+            Ok(Value::U32(5))
+        );
+    }
+}
@@ -0,0 +1,634 @@
+use super::{
+    align_of_layout, ffi_safe_equivalent, read_discriminant, size_of_layout, Equivalent,
+    SliceFields,
+};
+use crate::{
+    layout::{DefinedType, Discriminant, EnumLayout, EnumVariantType, KnownType, Layout, TypeType},
+    types::SOption,
+    LayoutHeader, TypeLayout,
+};
+use std::{collections::HashSet, fmt::Display};
+
+/// Why bytes are not a valid value of a type
+///
+/// Offsets are from the start of the validated bytes.
+#[derive(Debug, PartialEq, Clone)]
+pub enum InvalidValue {
+    /// The layout was produced for a different target (see [`LayoutHeader::same_target`])
+    DifferentTarget,
+    /// There are fewer bytes than the size of the type
+    TooShort { size: usize, len: usize },
+    /// The bytes are not aligned to the alignment of the type
+    Misaligned { align: usize },
+    /// A `bool` which is not 0 or 1
+    InvalidBool { offset: usize, value: u8 },
+    /// A `char` which is not a Unicode scalar value
+    InvalidChar { offset: usize, value: u32 },
+    /// The discriminant of an enum doesn't belong to any of its variants
+    InvalidDiscriminant {
+        offset: usize,
+        name: String,
+        discriminant: Discriminant,
+    },
+    /// The variant of an enum without a stable layout (`#[ironties(unsafe_allow_repr_rust)]`) can't be determined
+    UnspecifiedEnumLayout { name: String },
+    /// A `NonZero*` integer which is zero
+    Zero { offset: usize },
+    /// A reference, function pointer or `NonNull` which is null
+    NullPointer { offset: usize },
+    /// A pointer which is not aligned to the alignment of its target
+    MisalignedPointer { offset: usize, align: usize },
+    /// A string, slice or box whose contents are not within the validated bytes
+    OutOfBounds { offset: usize },
+    /// A string whose contents are not UTF-8
+    InvalidUtf8 { offset: usize },
+}
+
+impl Display for InvalidValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InvalidValue::DifferentTarget => {
+                write!(f, "the layout was produced for a different target")
+            }
+            InvalidValue::TooShort { size, len } => {
+                write!(f, "the value needs {size} bytes, but only {len} are given")
+            }
+            InvalidValue::Misaligned { align } => {
+                write!(f, "the bytes are not aligned to {align}")
+            }
+            InvalidValue::InvalidBool { offset, value } => {
+                write!(f, "invalid bool {value} at offset {offset}")
+            }
+            InvalidValue::InvalidChar { offset, value } => {
+                write!(f, "invalid char {value:#x} at offset {offset}")
+            }
+            InvalidValue::InvalidDiscriminant {
+                offset,
+                name,
+                discriminant,
+            } => write!(
+                f,
+                "invalid discriminant {discriminant:?} of the enum `{name}` at offset {offset}"
+            ),
+            InvalidValue::UnspecifiedEnumLayout { name } => {
+                write!(f, "the enum `{name}` doesn't have a stable layout")
+            }
+            InvalidValue::Zero { offset } => write!(f, "zero non-zero integer at offset {offset}"),
+            InvalidValue::NullPointer { offset } => write!(f, "null pointer at offset {offset}"),
+            InvalidValue::MisalignedPointer { offset, align } => {
+                write!(f, "pointer at offset {offset} is not aligned to {align}")
+            }
+            InvalidValue::OutOfBounds { offset } => {
+                write!(f, "pointer at offset {offset} points outside of the bytes")
+            }
+            InvalidValue::InvalidUtf8 { offset } => {
+                write!(f, "string at offset {offset} is not valid UTF-8")
+            }
+        }
+    }
+}
+
+impl std::error::Error for InvalidValue {}
+
+/// Checks that the bytes are a valid value of the type described by `layout`
+///
+/// The contents of strings, slices and boxes ([`SStr`][crate::types::SStr], [`SVec`][crate::types::SVec],
+/// [`SBox`][crate::types::SBox] and the like) and the targets of [relative pointers][crate::types::RelPtr]
+/// must lie within `bytes` too, and are validated recursively.
+/// References, function pointers and `NonNull`s are only checked to be non-null and aligned, since their targets
+/// can be anywhere. Padding bytes and the contents of unions and opaque types are never looked at.
+///
+/// If this succeeds and the layout contains no pointers other than [relative][crate::types::RelPtr] ones, nor unions,
+/// opaque types or enums without a stable layout, the bytes can be used as a value of the type (for example by casting
+/// the pointer to a reference), as long as they are not modified. Otherwise the value is not necessarily valid:
+/// references may point to anything, and owned types like [`SVec`][crate::types::SVec] would free memory they
+/// don't own when dropped.
+pub fn validate(bytes: &[u8], layout: &TypeLayout) -> Result<(), InvalidValue> {
+    if !layout.header.same_target(&LayoutHeader::current()) {
+        return Err(InvalidValue::DifferentTarget);
+    }
+
+    let size = size_of_layout(&layout.defined_types, &layout.layout);
+    if bytes.len() < size {
+        return Err(InvalidValue::TooShort {
+            size,
+            len: bytes.len(),
+        });
+    }
+    let align = align_of_layout(&layout.defined_types, &layout.layout);
+    if !(bytes.as_ptr() as usize).is_multiple_of(align) {
+        return Err(InvalidValue::Misaligned { align });
+    }
+
+    let mut validator = Validator {
+        defined_types: &layout.defined_types,
+        bytes,
+        visited: HashSet::new(),
+        pending: Vec::new(),
+    };
+    validator.validate(&layout.layout, 0)?;
+    validator.pending()
+}
+
+/// Validates `len` values of the layout pointed to by the [relative pointer][crate::types::RelPtr] at `offset`,
+/// and returns the offset of the values
+pub(crate) fn validate_relative(
+    bytes: &[u8],
+    offset: usize,
+    layout: &TypeLayout,
+    len: usize,
+) -> Result<usize, InvalidValue> {
+    let mut validator = Validator {
+        defined_types: &layout.defined_types,
+        bytes,
+        visited: HashSet::new(),
+        pending: Vec::new(),
+    };
+    let start = validator.relative(&layout.layout, offset, len)?;
+    validator.pending()?;
+
+    Ok(start)
+}
+
+struct Validator<'a> {
+    defined_types: &'a [DefinedType],
+    bytes: &'a [u8],
+    /// Slices which were already validated (offset, length and element layout),
+    /// so that values pointing to themselves don't recurse forever
+    visited: HashSet<(usize, usize, *const Layout)>,
+    /// Values pointed to which are yet to be validated (offset, number and layout).
+    /// They are validated after the value pointing to them instead of recursively,
+    /// so that long chains of pointers can't overflow the stack.
+    pending: Vec<(usize, usize, &'a Layout)>,
+}
+
+impl<'a> Validator<'a> {
+    /// Validates the values pointed to, and the values that they point to in turn
+    fn pending(&mut self) -> Result<(), InvalidValue> {
+        while let Some((start, len, layout)) = self.pending.pop() {
+            let size = size_of_layout(self.defined_types, layout);
+            for i in 0..len {
+                self.validate(layout, start + i * size)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The value must be within the bytes
+    fn validate(&mut self, layout: &'a Layout, offset: usize) -> Result<(), InvalidValue> {
+        match layout {
+            Layout::Bool => {
+                let value = self.bytes[offset];
+                if value > 1 {
+                    return Err(InvalidValue::InvalidBool { offset, value });
+                }
+            }
+            Layout::Char => {
+                let value = u32::from_ne_bytes(self.read(offset));
+                if char::from_u32(value).is_none() {
+                    return Err(InvalidValue::InvalidChar { offset, value });
+                }
+            }
+            Layout::Ref(layout) | Layout::MutRef(layout) => self.pointer(layout, offset)?,
+            Layout::RelPtr(layout) => {
+                self.relative(layout, offset, 1)?;
+            }
+            Layout::FunctionPointer { .. } => {
+                if self.address(offset) == 0 {
+                    return Err(InvalidValue::NullPointer { offset });
+                }
+            }
+            Layout::Array { len, layout } => {
+                let size = size_of_layout(self.defined_types, layout);
+                for i in 0..*len {
+                    self.validate(layout, offset + i * size)?;
+                }
+            }
+            Layout::DefinedType { id } => self.defined_type(*id, offset, false)?,
+            Layout::NonExhaustive(layout) => match &**layout {
+                Layout::DefinedType { id } => self.defined_type(*id, offset, true)?,
+                layout => self.validate(layout, offset)?,
+            },
+            Layout::NonZero(layout) => {
+                if self.is_zero(layout, offset) {
+                    return Err(InvalidValue::Zero { offset });
+                }
+                self.validate(layout, offset)?
+            }
+            Layout::NicheOption(layout) => {
+                // All zeroes is None
+                if !self.is_zero(layout, offset) {
+                    self.validate(layout, offset)?
+                }
+            }
+            Layout::Atomic(layout) | Layout::C { layout, .. } => self.validate(layout, offset)?,
+            // All bit patterns of integers, floats and raw pointers are valid
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    /// `open` is whether the enum may have variants that are not listed (see [`SNonExhaustive`][crate::types::SNonExhaustive])
+    fn defined_type(&mut self, id: usize, offset: usize, open: bool) -> Result<(), InvalidValue> {
+        let ty = &self.defined_types[id];
+        let name = &*ty.name;
+
+        match &ty.ty {
+            TypeType::StructNamed { fields } => {
+                let equivalent = ffi_safe_equivalent(self.defined_types, ty);
+                for field in fields.iter() {
+                    // The pointer of a slice is validated together with its length below
+                    if let Some(Equivalent::Slice(slice)) = &equivalent {
+                        if std::ptr::eq(field, slice.ptr) {
+                            continue;
+                        }
+                    }
+                    self.validate(&field.layout, offset + field.offset)?;
+                }
+
+                match equivalent {
+                    Some(Equivalent::Slice(slice)) => {
+                        self.slice(&slice, offset)?;
+                    }
+                    Some(Equivalent::Str {
+                        offset: inner,
+                        slice,
+                    }) => {
+                        let (start, len) = self.slice(&slice, offset + inner)?;
+                        if len != 0 && std::str::from_utf8(&self.bytes[start..start + len]).is_err()
+                        {
+                            return Err(InvalidValue::InvalidUtf8 { offset });
+                        }
+                    }
+                    Some(Equivalent::Box {
+                        offset: ptr,
+                        layout,
+                    }) => {
+                        self.contents(layout, offset + ptr, 1)?;
+                    }
+                    None => {}
+                }
+            }
+            TypeType::StructUnnamed { fields } => {
+                if ty.known == SOption::Some(KnownType::NonNull) {
+                    if let [field] = &fields[..] {
+                        return match &field.layout {
+                            Layout::MutPtr(layout) => self.pointer(layout, offset + field.offset),
+                            _ => Ok(()),
+                        };
+                    }
+                }
+
+                for field in fields.iter() {
+                    self.validate(&field.layout, offset + field.offset)?;
+                }
+            }
+            TypeType::Enum { variants, layout } => {
+                // SAFETY: the value is within the bytes
+                let discriminant = match (layout, unsafe {
+                    read_discriminant(&ty.repr, self.bytes.as_ptr().add(offset))
+                }) {
+                    (EnumLayout::Unspecified, _) | (_, None) => {
+                        return Err(InvalidValue::UnspecifiedEnumLayout {
+                            name: name.to_owned(),
+                        })
+                    }
+                    (_, Some(discriminant)) => discriminant,
+                };
+                let Some(variant) = variants.iter().find(|v| v.discriminant == discriminant) else {
+                    return match open {
+                        true => Ok(()),
+                        false => Err(InvalidValue::InvalidDiscriminant {
+                            offset,
+                            name: name.to_owned(),
+                            discriminant,
+                        }),
+                    };
+                };
+
+                match &variant.ty {
+                    EnumVariantType::Unit => {}
+                    EnumVariantType::Tuple(fields) => {
+                        for field in fields.iter() {
+                            self.validate(&field.layout, offset + field.offset)?;
+                        }
+                    }
+                    EnumVariantType::Struct(fields) => {
+                        for field in fields.iter() {
+                            self.validate(&field.layout, offset + field.offset)?;
+                        }
+                    }
+                }
+            }
+            TypeType::StructUnit | TypeType::Union { .. } | TypeType::Opaque => {}
+        }
+
+        Ok(())
+    }
+
+    /// Validates the elements of a slice-like type at `offset`, and returns their offset and number
+    fn slice(
+        &mut self,
+        slice: &SliceFields<'a>,
+        offset: usize,
+    ) -> Result<(usize, usize), InvalidValue> {
+        let len = self.address(offset + slice.len_offset);
+        let ptr = offset + slice.ptr.offset;
+        let start = match slice.relative {
+            true => self.relative(slice.layout, ptr, len)?,
+            false => self.contents(slice.layout, ptr, len)?,
+        };
+
+        Ok((start, len))
+    }
+
+    /// Validates `len` values pointed to by the pointer at `offset`, which must be within the bytes.
+    /// Returns the offset of the values.
+    fn contents(
+        &mut self,
+        layout: &'a Layout,
+        offset: usize,
+        len: usize,
+    ) -> Result<usize, InvalidValue> {
+        self.pointer(layout, offset)?;
+
+        let start = self
+            .address(offset)
+            .wrapping_sub(self.bytes.as_ptr() as usize);
+        self.elements(layout, offset, start, len)?;
+
+        Ok(start)
+    }
+
+    /// Validates `len` values pointed to by the [relative pointer][crate::types::RelPtr] at `offset`,
+    /// which must be within the bytes. Returns the offset of the values.
+    fn relative(
+        &mut self,
+        layout: &'a Layout,
+        offset: usize,
+        len: usize,
+    ) -> Result<usize, InvalidValue> {
+        let relative = isize::from_ne_bytes(self.read(offset));
+        let Some(start) = offset.checked_add_signed(relative) else {
+            return Err(InvalidValue::OutOfBounds { offset });
+        };
+        let align = align_of_layout(self.defined_types, layout);
+        if !(self.bytes.as_ptr() as usize)
+            .wrapping_add(start)
+            .is_multiple_of(align)
+        {
+            return Err(InvalidValue::MisalignedPointer { offset, align });
+        }
+        self.elements(layout, offset, start, len)?;
+
+        Ok(start)
+    }
+
+    /// Checks that `len` values at `start`, pointed to by the pointer at `offset`, are within the bytes,
+    /// and adds them to the values to validate
+    fn elements(
+        &mut self,
+        layout: &'a Layout,
+        offset: usize,
+        start: usize,
+        len: usize,
+    ) -> Result<(), InvalidValue> {
+        let size = size_of_layout(self.defined_types, layout);
+        if len == 0 || size == 0 {
+            // Nothing is read through the pointer
+            return Ok(());
+        }
+
+        match size
+            .checked_mul(len)
+            .and_then(|total| start.checked_add(total))
+        {
+            Some(end) if end <= self.bytes.len() => {}
+            _ => return Err(InvalidValue::OutOfBounds { offset }),
+        }
+
+        if self.visited.insert((start, len, layout)) {
+            self.pending.push((start, len, layout));
+        }
+
+        Ok(())
+    }
+
+    /// Checks that the pointer at `offset` is non-null and aligned for the layout
+    fn pointer(&self, layout: &Layout, offset: usize) -> Result<(), InvalidValue> {
+        let address = self.address(offset);
+        if address == 0 {
+            return Err(InvalidValue::NullPointer { offset });
+        }
+        let align = align_of_layout(self.defined_types, layout);
+        if !address.is_multiple_of(align) {
+            return Err(InvalidValue::MisalignedPointer { offset, align });
+        }
+
+        Ok(())
+    }
+
+    fn is_zero(&self, layout: &Layout, offset: usize) -> bool {
+        let size = size_of_layout(self.defined_types, layout);
+
+        self.bytes[offset..offset + size].iter().all(|b| *b == 0)
+    }
+
+    fn address(&self, offset: usize) -> usize {
+        usize::from_ne_bytes(self.read(offset))
+    }
+
+    fn read<const N: usize>(&self, offset: usize) -> [u8; N] {
+        self.bytes[offset..offset + N].try_into().unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{validate, InvalidValue};
+    use crate::{
+        types::{SNonExhaustive, SOption, SStr, SVec},
+        TypeInfo,
+    };
+    use std::{
+        mem::{offset_of, size_of},
+        ptr::{addr_of_mut, NonNull},
+    };
+
+    #[repr(C)]
+    #[derive(TypeInfo)]
+    struct Packet {
+        ready: bool,
+        kind: Kind,
+        next: SOption<NonNull<u32>>,
+        letter: char,
+    }
+
+    #[repr(u16)]
+    #[derive(TypeInfo, Clone, Copy)]
+    #[allow(dead_code)]
+    enum Kind {
+        Ping = 1,
+        Pong = 2,
+    }
+
+    #[repr(C)]
+    #[derive(TypeInfo)]
+    struct Message<'a> {
+        text: SStr<'a>,
+    }
+
+    /// An aligned buffer, big enough for the values in the tests
+    #[repr(C, align(16))]
+    struct Aligned([u8; 64]);
+
+    impl Aligned {
+        fn new<T>(value: T) -> Self {
+            let mut buffer = Aligned([0; 64]);
+            // SAFETY: the buffer is big enough and aligned
+            unsafe { buffer.0.as_mut_ptr().cast::<T>().write(value) };
+
+            buffer
+        }
+    }
+
+    #[test]
+    fn validate_values() {
+        const SIZE: usize = size_of::<Packet>();
+        let layout = Packet::layout();
+        // Written field by field, so that the padding stays initialized
+        let mut valid = Aligned([0; 64]);
+        let packet = valid.0.as_mut_ptr().cast::<Packet>();
+        // SAFETY: the buffer is big enough and aligned
+        unsafe {
+            addr_of_mut!((*packet).ready).write(true);
+            addr_of_mut!((*packet).kind).write(Kind::Pong);
+            addr_of_mut!((*packet).letter).write('a');
+        }
+        valid.0[offset_of!(Packet, next)] = 1; // SOption::None
+        assert_eq!(validate(&valid.0[..SIZE], &layout), Ok(()));
+
+        let mut invalid = Aligned(valid.0);
+        invalid.0[offset_of!(Packet, ready)] = 2;
+        assert_eq!(
+            validate(&invalid.0[..SIZE], &layout),
+            Err(InvalidValue::InvalidBool {
+                offset: 0,
+                value: 2
+            })
+        );
+
+        let mut invalid = Aligned(valid.0);
+        let offset = offset_of!(Packet, kind);
+        invalid.0[offset..offset + 2].copy_from_slice(&3u16.to_ne_bytes());
+        assert!(matches!(
+            validate(&invalid.0[..SIZE], &layout),
+            Err(InvalidValue::InvalidDiscriminant { offset: 2, .. })
+        ));
+
+        // SOption::Some with a null pointer
+        let mut invalid = Aligned(valid.0);
+        let offset = offset_of!(Packet, next);
+        invalid.0[offset..offset + size_of::<SOption<NonNull<u32>>>()].fill(0);
+        assert!(matches!(
+            validate(&invalid.0[..SIZE], &layout),
+            Err(InvalidValue::NullPointer { .. })
+        ));
+
+        let mut invalid = Aligned(valid.0);
+        let offset = offset_of!(Packet, letter);
+        invalid.0[offset..offset + 4].copy_from_slice(&0xD800u32.to_ne_bytes());
+        assert_eq!(
+            validate(&invalid.0[..SIZE], &layout),
+            Err(InvalidValue::InvalidChar {
+                offset,
+                value: 0xD800
+            })
+        );
+
+        assert_eq!(
+            validate(&valid.0[..SIZE - 1], &layout),
+            Err(InvalidValue::TooShort {
+                size: SIZE,
+                len: SIZE - 1
+            })
+        );
+        assert_eq!(
+            validate(&valid.0[1..], &layout),
+            Err(InvalidValue::Misaligned { align: 8 })
+        );
+    }
+
+    #[test]
+    fn validate_strings() {
+        const SIZE: usize = size_of::<Message>();
+        let mut buffer = Aligned([0; 64]);
+        buffer.0[SIZE..SIZE + 5].copy_from_slice(b"hello");
+        let text = SStr::new(std::str::from_utf8(&buffer.0[SIZE..SIZE + 5]).unwrap());
+        let message = Aligned::new(Message { text });
+        buffer.0[..SIZE].copy_from_slice(&message.0[..SIZE]);
+
+        let layout = Message::layout();
+        assert_eq!(validate(&buffer.0, &layout), Ok(()));
+
+        buffer.0[SIZE] = 0xff;
+        assert_eq!(
+            validate(&buffer.0, &layout),
+            Err(InvalidValue::InvalidUtf8 { offset: 0 })
+        );
+
+        // The contents of the string are not in the validated bytes
+        assert_eq!(
+            validate(&buffer.0[..SIZE], &layout),
+            Err(InvalidValue::OutOfBounds { offset: 0 })
+        );
+    }
+
+    #[test]
+    fn unknown_variants() {
+        let buffer = Aligned::new(7u16);
+
+        assert_eq!(
+            validate(&buffer.0, &SNonExhaustive::<Kind>::layout()),
+            Ok(())
+        );
+        assert!(validate(&buffer.0, &Kind::layout()).is_err());
+    }
+
+    #[test]
+    fn all_fields_of_equivalents() {
+        // The allocator of an empty vector isn't used to validate the elements, but it's still checked
+        let mut buffer = Aligned::new(SVec::<u64>::new());
+        let allocator = 3 * size_of::<usize>();
+        buffer.0[allocator..allocator + size_of::<usize>()].fill(0);
+        assert_eq!(
+            validate(&buffer.0, &SVec::<u64>::layout()),
+            Err(InvalidValue::NullPointer { offset: allocator })
+        );
+
+        #[repr(C)]
+        #[derive(TypeInfo)]
+        #[ironties(rename = "SVec")]
+        struct Renamed {
+            ptr: *const u8,
+            len: usize,
+            valid: bool,
+        }
+
+        // Shares the name of `SVec`, but isn't marked as a slice
+        let layout = Renamed::layout();
+        let mut buffer = Aligned::new(Renamed {
+            ptr: std::ptr::null(),
+            len: 0,
+            valid: true,
+        });
+        let offset = offset_of!(Renamed, valid);
+        buffer.0[offset] = 2;
+        assert_eq!(
+            validate(&buffer.0, &layout),
+            Err(InvalidValue::InvalidBool { offset, value: 2 })
+        );
+    }
+}
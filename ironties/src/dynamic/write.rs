@@ -0,0 +1,632 @@
+use super::{
+    align_of_layout, ffi_safe_equivalent, size_of_layout, Equivalent, Fields, SliceFields, Value,
+};
+use crate::{
+    layout::{
+        DefinedType, Discriminant, EnumLayout, EnumVariantType, KnownType, Layout, NamedField,
+        Repr, ReprPrimitive, TypeType, UnnamedField,
+    },
+    types::SOption,
+    LayoutHeader, TypeLayout,
+};
+use std::{
+    alloc::{alloc_zeroed, dealloc, handle_alloc_error},
+    ffi::c_int,
+    fmt::Display,
+    ptr::{write_unaligned, NonNull},
+};
+
+/// Why a [`Value`] couldn't be written
+#[derive(Debug, PartialEq, Clone)]
+pub enum WriteError {
+    /// The layout was produced for a different target (see [`LayoutHeader::same_target`])
+    DifferentTarget,
+    /// The value doesn't fit the layout (a string where an integer is expected, or an integer that's out of range for example)
+    Mismatch { expected: String, found: String },
+    /// A field of the struct or enum variant is not given
+    MissingField { name: String, field: String },
+    /// The struct or enum variant doesn't have a field with this name
+    UnknownField { name: String, field: String },
+    /// The enum doesn't have a variant with this name
+    UnknownVariant { name: String, variant: String },
+    /// The layout of an enum without a stable layout (`#[ironties(unsafe_allow_repr_rust)]`) is not known
+    UnspecifiedEnumLayout { name: String },
+    /// Unions and opaque types, whose contents can't be interpreted, and owned types like [`SVec`][crate::types::SVec]
+    /// or [`SBox`][crate::types::SBox], whose memory would have to come from their allocators
+    Unsupported { name: String },
+}
+
+impl Display for WriteError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WriteError::DifferentTarget => {
+                write!(f, "the layout was produced for a different target")
+            }
+            WriteError::Mismatch { expected, found } => {
+                write!(f, "expected {expected}, found {found}")
+            }
+            WriteError::MissingField { name, field } => {
+                write!(f, "missing field `{field}` of `{name}`")
+            }
+            WriteError::UnknownField { name, field } => {
+                write!(f, "`{name}` has no field `{field}`")
+            }
+            WriteError::UnknownVariant { name, variant } => {
+                write!(f, "the enum `{name}` has no variant `{variant}`")
+            }
+            WriteError::UnspecifiedEnumLayout { name } => {
+                write!(f, "the enum `{name}` doesn't have a stable layout")
+            }
+            WriteError::Unsupported { name } => write!(f, "values of `{name}` can't be written"),
+        }
+    }
+}
+
+impl std::error::Error for WriteError {}
+
+/// A value written according to a [`TypeLayout`], together with the memory that its pointers point to
+/// (the contents of [`SStr`][crate::types::SStr]s and [`SSlice`][crate::types::SSlice]s)
+///
+/// The value is aligned, and its padding is zeroed.
+pub struct ValueBuffer {
+    value: Allocation,
+    // Only kept alive for the pointers in the value.
+    // The allocations don't move when this vector grows, so pointers to them stay valid.
+    _storage: Vec<Allocation>,
+}
+
+impl ValueBuffer {
+    pub fn as_ptr(&self) -> *const u8 {
+        self.value.ptr.as_ptr()
+    }
+    pub fn as_mut_ptr(&mut self) -> *mut u8 {
+        self.value.ptr.as_ptr()
+    }
+    /// The bytes of the value itself, without the memory that its pointers point to
+    pub fn as_bytes(&self) -> &[u8] {
+        // SAFETY: the whole allocation is initialized
+        unsafe { std::slice::from_raw_parts(self.as_ptr(), self.value.layout.size()) }
+    }
+}
+
+impl std::fmt::Debug for ValueBuffer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ValueBuffer")
+            .field("bytes", &self.as_bytes())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Zeroed memory
+struct Allocation {
+    ptr: NonNull<u8>,
+    layout: std::alloc::Layout,
+}
+
+impl Allocation {
+    fn new(size: usize, align: usize) -> Self {
+        let layout = std::alloc::Layout::from_size_align(size, align)
+            .expect("the size and alignment of a layout are always valid");
+        let ptr = match size {
+            // Zero-sized allocations are not allowed, but aligned dangling pointers can be used instead
+            0 => NonNull::new(align as *mut u8).unwrap(),
+            // SAFETY: the size is not zero
+            _ => NonNull::new(unsafe { alloc_zeroed(layout) })
+                .unwrap_or_else(|| handle_alloc_error(layout)),
+        };
+
+        Self { ptr, layout }
+    }
+}
+
+impl Drop for Allocation {
+    fn drop(&mut self) {
+        if self.layout.size() != 0 {
+            // SAFETY: allocated in Allocation::new with the same layout
+            unsafe { dealloc(self.ptr.as_ptr(), self.layout) }
+        }
+    }
+}
+
+impl<'a> Value<'a> {
+    /// Writes the value according to `layout`, which must have been produced for the current target
+    ///
+    /// Struct fields are matched by name, and the names of the structs and enums themselves are ignored.
+    /// Integers are converted between widths as long as they fit. [`Value::Str`] and [`Value::List`] can be written
+    /// as [`SStr`][crate::types::SStr] and [`SSlice`][crate::types::SSlice], whose contents are then stored in the buffer too.
+    pub fn write(&self, layout: &TypeLayout) -> Result<ValueBuffer, WriteError> {
+        if !layout.header.same_target(&LayoutHeader::current()) {
+            return Err(WriteError::DifferentTarget);
+        }
+
+        let mut writer = Writer {
+            defined_types: &layout.defined_types,
+            storage: Vec::new(),
+        };
+        let value = writer.allocate(&layout.layout, 1);
+        // SAFETY: the allocation has the size of the layout
+        unsafe { writer.write(&layout.layout, self, value.ptr.as_ptr())? };
+
+        Ok(ValueBuffer {
+            value,
+            _storage: writer.storage,
+        })
+    }
+}
+
+struct Writer<'l> {
+    defined_types: &'l [DefinedType],
+    storage: Vec<Allocation>,
+}
+
+impl<'l> Writer<'l> {
+    /// Allocates zeroed memory for `len` values of the layout
+    fn allocate(&self, layout: &Layout, len: usize) -> Allocation {
+        Allocation::new(
+            size_of_layout(self.defined_types, layout) * len,
+            align_of_layout(self.defined_types, layout),
+        )
+    }
+
+    /// `ptr` must point to zeroed memory of the size of the layout
+    unsafe fn write(
+        &mut self,
+        layout: &Layout,
+        value: &Value,
+        ptr: *mut u8,
+    ) -> Result<(), WriteError> {
+        let mismatch = || WriteError::Mismatch {
+            expected: format!("{layout:?}"),
+            found: format!("{value:?}"),
+        };
+
+        match (layout, value) {
+            (Layout::Void, Value::Unit) => {}
+            (Layout::Bool, Value::Bool(v)) => write_unaligned(ptr.cast(), *v),
+            (Layout::Char, Value::Char(v)) => write_unaligned(ptr.cast(), *v),
+            (Layout::F32, Value::F32(v)) => write_unaligned(ptr.cast(), *v),
+            (Layout::F64, Value::F64(v)) => write_unaligned(ptr.cast(), *v),
+            (
+                Layout::ConstPtr(_) | Layout::MutPtr(_) | Layout::FunctionPointer { .. },
+                Value::Pointer(address),
+            ) => write_unaligned(ptr.cast(), *address),
+            (Layout::Ref(_) | Layout::MutRef(_), Value::Pointer(address)) if *address != 0 => {
+                write_unaligned(ptr.cast(), *address)
+            }
+            (Layout::RelPtr(_), Value::Pointer(address)) => {
+                write_unaligned(ptr.cast(), address.wrapping_sub(ptr as usize) as isize)
+            }
+            (Layout::Array { len, layout }, Value::Array(values)) if values.len() == *len => {
+                let size = size_of_layout(self.defined_types, layout);
+                for (i, value) in values.iter().enumerate() {
+                    self.write(layout, value, ptr.add(i * size))?;
+                }
+            }
+            (Layout::DefinedType { id }, value) => self.defined_type(*id, value, ptr)?,
+            (Layout::NicheOption(_), Value::Option(None)) => {
+                // None is all zeroes, which the memory already is
+            }
+            (Layout::NicheOption(layout), Value::Option(Some(value))) => {
+                self.write(layout, value, ptr)?
+            }
+            (Layout::NonZero(layout), value) => {
+                self.write(layout, value, ptr)?;
+
+                let size = size_of_layout(self.defined_types, layout);
+                if (0..size).all(|i| *ptr.add(i) == 0) {
+                    return Err(mismatch());
+                }
+            }
+            (
+                Layout::NonExhaustive(layout) | Layout::Atomic(layout) | Layout::C { layout, .. },
+                value,
+            ) => self.write(layout, value, ptr)?,
+            (layout, value) => {
+                let Some(integer) = integer(value) else {
+                    return Err(mismatch());
+                };
+                let fits = match layout {
+                    Layout::U8 => write_integer::<u8>(ptr, integer),
+                    Layout::U16 => write_integer::<u16>(ptr, integer),
+                    Layout::U32 => write_integer::<u32>(ptr, integer),
+                    Layout::U64 => write_integer::<u64>(ptr, integer),
+                    Layout::U128 => write_integer::<u128>(ptr, integer),
+                    Layout::USize => write_integer::<usize>(ptr, integer),
+                    Layout::I8 => write_integer::<i8>(ptr, integer),
+                    Layout::I16 => write_integer::<i16>(ptr, integer),
+                    Layout::I32 => write_integer::<i32>(ptr, integer),
+                    Layout::I64 => write_integer::<i64>(ptr, integer),
+                    Layout::I128 => write_integer::<i128>(ptr, integer),
+                    Layout::ISize => write_integer::<isize>(ptr, integer),
+                    _ => false,
+                };
+                if !fits {
+                    return Err(mismatch());
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    unsafe fn defined_type(
+        &mut self,
+        id: usize,
+        value: &Value,
+        ptr: *mut u8,
+    ) -> Result<(), WriteError> {
+        let ty = &self.defined_types[id];
+        let name = &*ty.name;
+        let equivalent = ffi_safe_equivalent(self.defined_types, ty);
+        let mismatch = || WriteError::Mismatch {
+            expected: format!("a value of `{name}`"),
+            found: format!("{value:?}"),
+        };
+
+        match (&ty.ty, value, equivalent) {
+            (_, Value::Str(s), Some(Equivalent::Str { offset, slice }))
+                if !slice.owned && !slice.relative =>
+            {
+                let bytes: Vec<Value> = s.bytes().map(Value::U8).collect();
+                self.slice(&slice, &bytes, ptr.add(offset))?
+            }
+            (_, Value::List(values), Some(Equivalent::Slice(slice)))
+                if !slice.owned && !slice.relative =>
+            {
+                self.slice(&slice, values, ptr)?
+            }
+            (
+                _,
+                Value::Str(_) | Value::List(_) | Value::Box(_),
+                Some(Equivalent::Str { slice, .. } | Equivalent::Slice(slice)),
+            ) if slice.owned => {
+                return Err(WriteError::Unsupported {
+                    name: name.to_owned(),
+                })
+            }
+            (_, Value::Str(_) | Value::List(_) | Value::Box(_), Some(Equivalent::Box { .. })) => {
+                return Err(WriteError::Unsupported {
+                    name: name.to_owned(),
+                })
+            }
+            (TypeType::StructNamed { fields }, Value::Struct { fields: values, .. }, _) => {
+                self.named_fields(name, fields, values, ptr)?
+            }
+            (TypeType::StructUnnamed { fields }, Value::Struct { fields: values, .. }, _) => {
+                self.unnamed_fields(name, fields, values, ptr)?
+            }
+            (TypeType::StructUnit, Value::Struct { fields, .. }, _) => match fields {
+                Fields::Unit => {}
+                Fields::Named(v) if v.is_empty() => {}
+                Fields::Unnamed(v) if v.is_empty() => {}
+                _ => return Err(mismatch()),
+            },
+            (TypeType::Enum { .. }, Value::Option(option), _)
+                if ty.known == SOption::Some(KnownType::Option) =>
+            {
+                let (variant, fields) = match option {
+                    None => ("None", Fields::Unit),
+                    Some(value) => ("Some", Fields::Unnamed(vec![(**value).clone()])),
+                };
+
+                self.defined_type(
+                    id,
+                    &Value::Enum {
+                        name,
+                        variant,
+                        fields,
+                    },
+                    ptr,
+                )?
+            }
+            (
+                TypeType::Enum { variants, layout },
+                Value::Enum {
+                    variant, fields, ..
+                },
+                _,
+            ) => {
+                if *layout == EnumLayout::Unspecified {
+                    return Err(WriteError::UnspecifiedEnumLayout {
+                        name: name.to_owned(),
+                    });
+                }
+                let Some(v) = variants.iter().find(|v| &*v.name == *variant) else {
+                    return Err(WriteError::UnknownVariant {
+                        name: name.to_owned(),
+                        variant: (*variant).to_owned(),
+                    });
+                };
+
+                if !write_discriminant(&ty.repr, ptr, v.discriminant) {
+                    return Err(WriteError::UnspecifiedEnumLayout {
+                        name: name.to_owned(),
+                    });
+                }
+                let name = format!("{name}::{variant}");
+                match (&v.ty, fields) {
+                    (EnumVariantType::Unit, Fields::Unit) => {}
+                    (EnumVariantType::Tuple(layouts), fields) => {
+                        self.unnamed_fields(&name, layouts, fields, ptr)?
+                    }
+                    (EnumVariantType::Struct(layouts), fields) => {
+                        self.named_fields(&name, layouts, fields, ptr)?
+                    }
+                    _ => return Err(mismatch()),
+                }
+            }
+            (TypeType::Union { .. } | TypeType::Opaque, _, _) => {
+                return Err(WriteError::Unsupported {
+                    name: name.to_owned(),
+                })
+            }
+            _ => return Err(mismatch()),
+        }
+
+        Ok(())
+    }
+
+    /// Writes the elements of a slice-like type to newly allocated memory, and points it to them
+    unsafe fn slice(
+        &mut self,
+        slice: &SliceFields,
+        values: &[Value],
+        ptr: *mut u8,
+    ) -> Result<(), WriteError> {
+        let elements = self.allocate(slice.layout, values.len());
+        let size = size_of_layout(self.defined_types, slice.layout);
+        for (i, value) in values.iter().enumerate() {
+            self.write(slice.layout, value, elements.ptr.as_ptr().add(i * size))?;
+        }
+
+        write_unaligned(ptr.add(slice.ptr.offset).cast(), elements.ptr.as_ptr());
+        write_unaligned(ptr.add(slice.len_offset).cast(), values.len());
+        self.storage.push(elements);
+
+        Ok(())
+    }
+
+    unsafe fn named_fields(
+        &mut self,
+        name: &str,
+        layouts: &[NamedField],
+        fields: &Fields,
+        ptr: *mut u8,
+    ) -> Result<(), WriteError> {
+        let values: &[(&str, Value)] = match fields {
+            Fields::Named(values) => values,
+            Fields::Unit => &[],
+            Fields::Unnamed(_) => {
+                return Err(WriteError::Mismatch {
+                    expected: format!("named fields of `{name}`"),
+                    found: format!("{fields:?}"),
+                })
+            }
+        };
+
+        if let Some((field, _)) = values
+            .iter()
+            .find(|(field, _)| layouts.iter().all(|f| &*f.name != *field))
+        {
+            return Err(WriteError::UnknownField {
+                name: name.to_owned(),
+                field: (*field).to_owned(),
+            });
+        }
+        for layout in layouts {
+            let Some((_, value)) = values.iter().find(|(field, _)| *field == &*layout.name) else {
+                return Err(WriteError::MissingField {
+                    name: name.to_owned(),
+                    field: layout.name.to_string(),
+                });
+            };
+
+            self.write(&layout.layout, value, ptr.add(layout.offset))?;
+        }
+
+        Ok(())
+    }
+
+    unsafe fn unnamed_fields(
+        &mut self,
+        name: &str,
+        layouts: &[UnnamedField],
+        fields: &Fields,
+        ptr: *mut u8,
+    ) -> Result<(), WriteError> {
+        let values: &[Value] = match fields {
+            Fields::Unnamed(values) => values,
+            Fields::Unit => &[],
+            Fields::Named(_) => {
+                return Err(WriteError::Mismatch {
+                    expected: format!("unnamed fields of `{name}`"),
+                    found: format!("{fields:?}"),
+                })
+            }
+        };
+
+        if values.len() > layouts.len() {
+            return Err(WriteError::UnknownField {
+                name: name.to_owned(),
+                field: layouts.len().to_string(),
+            });
+        }
+        for (i, layout) in layouts.iter().enumerate() {
+            let Some(value) = values.get(i) else {
+                return Err(WriteError::MissingField {
+                    name: name.to_owned(),
+                    field: i.to_string(),
+                });
+            };
+
+            self.write(&layout.layout, value, ptr.add(layout.offset))?;
+        }
+
+        Ok(())
+    }
+}
+
+pub(super) fn integer(value: &Value) -> Option<Discriminant> {
+    Some(match *value {
+        Value::U8(v) => v.into(),
+        Value::U16(v) => v.into(),
+        Value::U32(v) => v.into(),
+        Value::U64(v) => v.into(),
+        Value::U128(v) => v.into(),
+        Value::USize(v) => v.into(),
+        Value::I8(v) => v.into(),
+        Value::I16(v) => v.into(),
+        Value::I32(v) => v.into(),
+        Value::I64(v) => v.into(),
+        Value::I128(v) => v.into(),
+        Value::ISize(v) => v.into(),
+        _ => return None,
+    })
+}
+
+/// Returns `false` if the value doesn't fit in `T`
+unsafe fn write_integer<T: TryFrom<u128> + TryFrom<i128>>(
+    ptr: *mut u8,
+    value: Discriminant,
+) -> bool {
+    let value = match value {
+        Discriminant::Unsigned(v) => T::try_from(v).ok(),
+        Discriminant::Signed(v) => T::try_from(v).ok(),
+    };
+
+    match value {
+        Some(value) => {
+            write_unaligned(ptr.cast(), value);
+            true
+        }
+        None => false,
+    }
+}
+
+/// Writes the tag of an enum with the given repr, returns `false` if it doesn't have a stable layout
+unsafe fn write_discriminant(repr: &Repr, ptr: *mut u8, discriminant: Discriminant) -> bool {
+    match repr.primitive {
+        SOption::Some(ReprPrimitive::U8) => write_integer::<u8>(ptr, discriminant),
+        SOption::Some(ReprPrimitive::U16) => write_integer::<u16>(ptr, discriminant),
+        SOption::Some(ReprPrimitive::U32) => write_integer::<u32>(ptr, discriminant),
+        SOption::Some(ReprPrimitive::U64) => write_integer::<u64>(ptr, discriminant),
+        SOption::Some(ReprPrimitive::U128) => write_integer::<u128>(ptr, discriminant),
+        SOption::Some(ReprPrimitive::USize) => write_integer::<usize>(ptr, discriminant),
+        SOption::Some(ReprPrimitive::I8) => write_integer::<i8>(ptr, discriminant),
+        SOption::Some(ReprPrimitive::I16) => write_integer::<i16>(ptr, discriminant),
+        SOption::Some(ReprPrimitive::I32) => write_integer::<i32>(ptr, discriminant),
+        SOption::Some(ReprPrimitive::I64) => write_integer::<i64>(ptr, discriminant),
+        SOption::Some(ReprPrimitive::I128) => write_integer::<i128>(ptr, discriminant),
+        SOption::Some(ReprPrimitive::ISize) => write_integer::<isize>(ptr, discriminant),
+        SOption::None if repr.c => write_integer::<c_int>(ptr, discriminant),
+        SOption::None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::WriteError;
+    use crate::{
+        dynamic::{Fields, Value},
+        types::{SOption, SSlice, SStr},
+        TypeInfo,
+    };
+
+    #[repr(C)]
+    #[derive(TypeInfo, Debug, PartialEq)]
+    struct Args<'a> {
+        flag: bool,
+        count: u64,
+        name: SStr<'a>,
+        points: SSlice<'a, [i16; 2]>,
+        shape: SOption<Shape>,
+    }
+
+    #[repr(C)]
+    #[derive(TypeInfo, Debug, PartialEq, Clone, Copy)]
+    #[allow(dead_code)]
+    enum Shape {
+        Dot,
+        Circle(f32),
+    }
+
+    fn args(count: Value<'static>) -> Value<'static> {
+        Value::Struct {
+            name: "Args",
+            fields: Fields::Named(vec![
+                (
+                    "shape",
+                    Value::Option(Some(Box::new(Value::Enum {
+                        name: "Shape",
+                        variant: "Circle",
+                        fields: Fields::Unnamed(vec![Value::F32(1.5)]),
+                    }))),
+                ),
+                ("name", Value::Str("plugin".to_owned())),
+                ("count", count),
+                ("flag", Value::Bool(true)),
+                (
+                    "points",
+                    Value::List(vec![Value::Array(vec![Value::I16(1), Value::I16(-2)])]),
+                ),
+            ]),
+        }
+    }
+
+    #[test]
+    fn write_values() {
+        let layout = Args::layout();
+        let buffer = args(Value::U8(3)).write(&layout).unwrap();
+
+        // Padding after the bool
+        assert_eq!(buffer.as_bytes()[1..8], [0; 7]);
+
+        // SAFETY: the buffer was written according to the layout of Args
+        let written = unsafe { &*buffer.as_ptr().cast::<Args>() };
+        assert_eq!(
+            *written,
+            Args {
+                flag: true,
+                count: 3,
+                name: SStr::new("plugin"),
+                points: SSlice::new(&[[1, -2]]),
+                shape: SOption::Some(Shape::Circle(1.5)),
+            }
+        );
+    }
+
+    #[test]
+    fn write_errors() {
+        let layout = Args::layout();
+
+        assert!(matches!(
+            args(Value::I8(-1)).write(&layout),
+            Err(WriteError::Mismatch { .. })
+        ));
+        assert_eq!(
+            Value::Struct {
+                name: "Args",
+                fields: Fields::Named(vec![("flag", Value::Bool(true))]),
+            }
+            .write(&layout)
+            .unwrap_err(),
+            WriteError::MissingField {
+                name: "Args".to_owned(),
+                field: "count".to_owned(),
+            }
+        );
+        assert_eq!(
+            Value::Enum {
+                name: "Shape",
+                variant: "Square",
+                fields: Fields::Unit,
+            }
+            .write(&Shape::layout())
+            .unwrap_err(),
+            WriteError::UnknownVariant {
+                name: "Shape".to_owned(),
+                variant: "Square".to_owned(),
+            }
+        );
+    }
+}
@@ -0,0 +1,1020 @@
+//! A compact binary encoding of [`TypeLayout`]s, for storing them alongside data.
+
+use crate::{
+    layout::{
+        CType, DefinedType, Discriminant, EnumLayout, EnumVariant, EnumVariantType, KnownType,
+        Layout, NamedField, Repr, ReprPrimitive, TypeFlags, TypeType, UnnamedField,
+    },
+    types::{SBox, SOption, SStr, SVec},
+    Endianness, LayoutHeader, TypeLayout, LAYOUT_FORMAT_VERSION,
+};
+use std::{
+    collections::{HashMap, HashSet},
+    ffi::c_int,
+    fmt::Display,
+    mem::size_of,
+    sync::{Mutex, OnceLock},
+};
+
+/// Why a [`TypeLayout`] couldn't be decoded
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum DecodeError {
+    /// The layout was encoded with a different [`LAYOUT_FORMAT_VERSION`]
+    FormatVersion(u32),
+    /// The bytes end before the layout does
+    UnexpectedEnd,
+    /// The bytes are not an encoded layout, or the layout is not consistent
+    /// (a field outside of its type, or a type containing itself for example)
+    Invalid,
+    /// The names in the layout take up more than [`MAX_INTERNED_LEN`] bytes
+    TooManyNames,
+}
+
+impl Display for DecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DecodeError::FormatVersion(version) => write!(
+                f,
+                "the layout has format version {version}, but {LAYOUT_FORMAT_VERSION} is expected"
+            ),
+            DecodeError::UnexpectedEnd => write!(f, "the encoded layout is truncated"),
+            DecodeError::Invalid => write!(f, "the bytes are not an encoded layout"),
+            DecodeError::TooManyNames => write!(
+                f,
+                "the names in the layout take up more than {MAX_INTERNED_LEN} bytes"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+/// The maximum total length of the names in a decoded layout (see [`TypeLayout::from_bytes`])
+pub const MAX_INTERNED_LEN: usize = 16 << 20;
+
+impl TypeLayout {
+    /// Encodes the layout in a compact binary format, which can be decoded with [`TypeLayout::from_bytes`]
+    ///
+    /// The encoding starts with the [format version][LayoutHeader::format_version] as a little-endian `u32`.
+    /// Equal layouts always have equal encodings.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut encoder = Encoder(Vec::new());
+        encoder.header(&self.header);
+        encoder.usize(self.defined_types.len());
+        for ty in self.defined_types.iter() {
+            encoder.defined_type(ty);
+        }
+        encoder.layout(&self.layout);
+
+        encoder.0
+    }
+    /// Decodes a layout encoded with [`TypeLayout::to_bytes`], which may have been produced for a different target
+    ///
+    /// The layout is checked to be consistent: the fields and the enum tags of defined types must lie within them,
+    /// sizes must not overflow, and types can't contain themselves other than through pointers.
+    /// Fields can't be nested more than 128 levels deep either, and zero-sized values can't be made of more than
+    /// 65536 fields and elements. The layouts of pointed-to values are checked the same way.
+    ///
+    /// Names in decoded layouts are interned and live for the rest of the program, so they are only interned
+    /// once the layout passed the checks, and their total length is limited to [`MAX_INTERNED_LEN`] bytes.
+    /// Every distinct name is only kept once, but decoding layouts with ever new names still uses up memory.
+    pub fn from_bytes(bytes: &[u8]) -> Result<TypeLayout, DecodeError> {
+        // The names are only interned in a second pass, so that rejected layouts don't leak anything
+        let layout = Self::decode(bytes, false)?;
+        if !check(&layout) {
+            return Err(DecodeError::Invalid);
+        }
+
+        Self::decode(bytes, true)
+    }
+
+    /// Decodes a layout, with empty names unless `intern` is set
+    fn decode(bytes: &[u8], intern: bool) -> Result<TypeLayout, DecodeError> {
+        let mut decoder = Decoder {
+            bytes,
+            depth: 0,
+            max_id: None,
+            intern,
+            names_len: 0,
+        };
+        let header = decoder.header()?;
+        let defined_types = decoder.list(Decoder::defined_type)?;
+        let layout = decoder.layout()?;
+
+        if !decoder.bytes.is_empty() || decoder.max_id.is_some_and(|id| id >= defined_types.len()) {
+            return Err(DecodeError::Invalid);
+        }
+
+        Ok(TypeLayout {
+            header,
+            defined_types,
+            layout,
+        })
+    }
+}
+
+struct Encoder(Vec<u8>);
+
+impl Encoder {
+    fn u8(&mut self, value: u8) {
+        self.0.push(value);
+    }
+    fn bool(&mut self, value: bool) {
+        self.u8(value as u8);
+    }
+    fn u64(&mut self, value: u64) {
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+    fn usize(&mut self, value: usize) {
+        self.u64(value as u64);
+    }
+    fn u128(&mut self, value: u128) {
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+    fn str(&mut self, value: &str) {
+        self.usize(value.len());
+        self.0.extend_from_slice(value.as_bytes());
+    }
+    fn option(&mut self, value: &SOption<usize>) {
+        match value {
+            SOption::Some(value) => {
+                self.bool(true);
+                self.usize(*value);
+            }
+            SOption::None => self.bool(false),
+        }
+    }
+
+    fn header(&mut self, header: &LayoutHeader) {
+        self.0
+            .extend_from_slice(&header.format_version.to_le_bytes());
+        self.u8(header.pointer_width);
+        self.u8(header.endianness as u8);
+        self.str(&header.target);
+        self.str(&header.rustc_version);
+    }
+    fn defined_type(&mut self, ty: &DefinedType) {
+        self.str(&ty.name);
+
+        self.bool(ty.repr.c);
+        self.bool(ty.repr.transparent);
+        match ty.repr.primitive {
+            SOption::Some(primitive) => {
+                self.bool(true);
+                self.u8(primitive as u8);
+            }
+            SOption::None => self.bool(false),
+        }
+        self.option(&ty.repr.packed);
+        self.option(&ty.repr.align);
+
+        self.bool(ty.flags.send);
+        self.bool(ty.flags.sync);
+        self.bool(ty.flags.copy);
+        self.bool(ty.flags.needs_drop);
+
+        match ty.known {
+            SOption::Some(known) => {
+                self.bool(true);
+                self.u8(known as u8);
+            }
+            SOption::None => self.bool(false),
+        }
+
+        self.usize(ty.size);
+        self.usize(ty.align);
+
+        match &ty.ty {
+            TypeType::StructNamed { fields } => {
+                self.u8(0);
+                self.named_fields(fields);
+            }
+            TypeType::StructUnnamed { fields } => {
+                self.u8(1);
+                self.unnamed_fields(fields);
+            }
+            TypeType::StructUnit => self.u8(2),
+            TypeType::Enum { variants, layout } => {
+                self.u8(3);
+                self.usize(variants.len());
+                for variant in variants.iter() {
+                    self.variant(variant);
+                }
+                match layout {
+                    EnumLayout::Inline { tag_size } => {
+                        self.u8(0);
+                        self.usize(*tag_size);
+                    }
+                    EnumLayout::Separate { tag_size } => {
+                        self.u8(1);
+                        self.usize(*tag_size);
+                    }
+                    EnumLayout::Unspecified => self.u8(2),
+                }
+            }
+            TypeType::Union { fields } => {
+                self.u8(4);
+                self.named_fields(fields);
+            }
+            TypeType::Opaque => self.u8(5),
+        }
+    }
+    fn named_fields(&mut self, fields: &[NamedField]) {
+        self.usize(fields.len());
+        for field in fields {
+            self.str(&field.name);
+            self.layout(&field.layout);
+            self.usize(field.offset);
+        }
+    }
+    fn unnamed_fields(&mut self, fields: &[UnnamedField]) {
+        self.usize(fields.len());
+        for field in fields {
+            self.layout(&field.layout);
+            self.usize(field.offset);
+        }
+    }
+    fn variant(&mut self, variant: &EnumVariant) {
+        self.str(&variant.name);
+        match &variant.ty {
+            EnumVariantType::Unit => self.u8(0),
+            EnumVariantType::Tuple(fields) => {
+                self.u8(1);
+                self.unnamed_fields(fields);
+            }
+            EnumVariantType::Struct(fields) => {
+                self.u8(2);
+                self.named_fields(fields);
+            }
+        }
+        match variant.discriminant {
+            Discriminant::Unsigned(value) => {
+                self.u8(0);
+                self.u128(value);
+            }
+            Discriminant::Signed(value) => {
+                self.u8(1);
+                self.u128(value as u128);
+            }
+        }
+        self.usize(variant.payload_offset);
+    }
+    fn layout(&mut self, layout: &Layout) {
+        match layout {
+            Layout::Void => self.u8(0),
+            Layout::U8 => self.u8(1),
+            Layout::U16 => self.u8(2),
+            Layout::U32 => self.u8(3),
+            Layout::U64 => self.u8(4),
+            Layout::U128 => self.u8(5),
+            Layout::I8 => self.u8(6),
+            Layout::I16 => self.u8(7),
+            Layout::I32 => self.u8(8),
+            Layout::I64 => self.u8(9),
+            Layout::I128 => self.u8(10),
+            Layout::USize => self.u8(11),
+            Layout::ISize => self.u8(12),
+            Layout::Bool => self.u8(13),
+            Layout::F32 => self.u8(14),
+            Layout::F64 => self.u8(15),
+            Layout::Char => self.u8(16),
+            Layout::ConstPtr(layout) => {
+                self.u8(17);
+                self.layout(layout);
+            }
+            Layout::MutPtr(layout) => {
+                self.u8(18);
+                self.layout(layout);
+            }
+            Layout::Ref(layout) => {
+                self.u8(19);
+                self.layout(layout);
+            }
+            Layout::MutRef(layout) => {
+                self.u8(20);
+                self.layout(layout);
+            }
+            Layout::Array { len, layout } => {
+                self.u8(21);
+                self.usize(*len);
+                self.layout(layout);
+            }
+            Layout::FunctionPointer {
+                is_unsafe,
+                abi,
+                args,
+                return_ty,
+            } => {
+                self.u8(22);
+                self.bool(*is_unsafe);
+                self.str(abi);
+                self.usize(args.len());
+                for arg in args.iter() {
+                    self.layout(arg);
+                }
+                self.layout(return_ty);
+            }
+            Layout::DefinedType { id } => {
+                self.u8(23);
+                self.usize(*id);
+            }
+            Layout::NonExhaustive(layout) => {
+                self.u8(24);
+                self.layout(layout);
+            }
+            Layout::Atomic(layout) => {
+                self.u8(25);
+                self.layout(layout);
+            }
+            Layout::NonZero(layout) => {
+                self.u8(26);
+                self.layout(layout);
+            }
+            Layout::NicheOption(layout) => {
+                self.u8(27);
+                self.layout(layout);
+            }
+            Layout::C { ty, layout } => {
+                self.u8(28);
+                self.u8(*ty as u8);
+                self.layout(layout);
+            }
+            Layout::RelPtr(layout) => {
+                self.u8(29);
+                self.layout(layout);
+            }
+        }
+    }
+}
+
+struct Decoder<'b> {
+    bytes: &'b [u8],
+    /// The nesting depth of layouts, limited so that malicious inputs can't overflow the stack
+    depth: usize,
+    /// The greatest id of a defined type referred to, checked after all defined types are decoded
+    max_id: Option<usize>,
+    /// Whether to intern the names, instead of leaving them empty
+    intern: bool,
+    /// The total length of the names decoded so far
+    names_len: usize,
+}
+
+impl<'b> Decoder<'b> {
+    const MAX_DEPTH: usize = 512;
+
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], DecodeError> {
+        let Some((bytes, rest)) = self.bytes.split_first_chunk() else {
+            return Err(DecodeError::UnexpectedEnd);
+        };
+        self.bytes = rest;
+
+        Ok(*bytes)
+    }
+    fn u8(&mut self) -> Result<u8, DecodeError> {
+        Ok(self.take::<1>()?[0])
+    }
+    fn bool(&mut self) -> Result<bool, DecodeError> {
+        match self.u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(DecodeError::Invalid),
+        }
+    }
+    fn u64(&mut self) -> Result<u64, DecodeError> {
+        Ok(u64::from_le_bytes(self.take()?))
+    }
+    fn usize(&mut self) -> Result<usize, DecodeError> {
+        self.u64()?.try_into().map_err(|_| DecodeError::Invalid)
+    }
+    fn u128(&mut self) -> Result<u128, DecodeError> {
+        Ok(u128::from_le_bytes(self.take()?))
+    }
+    fn str(&mut self) -> Result<SStr<'static>, DecodeError> {
+        let len = self.usize()?;
+        if self.bytes.len() < len {
+            return Err(DecodeError::UnexpectedEnd);
+        }
+        let (bytes, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+
+        let s = std::str::from_utf8(bytes).map_err(|_| DecodeError::Invalid)?;
+        self.names_len += s.len();
+        if self.names_len > MAX_INTERNED_LEN {
+            return Err(DecodeError::TooManyNames);
+        }
+
+        Ok(SStr::new(match self.intern {
+            true => intern(s),
+            false => "",
+        }))
+    }
+    fn option(&mut self) -> Result<SOption<usize>, DecodeError> {
+        Ok(match self.bool()? {
+            true => SOption::Some(self.usize()?),
+            false => SOption::None,
+        })
+    }
+    fn list<T>(
+        &mut self,
+        mut element: impl FnMut(&mut Self) -> Result<T, DecodeError>,
+    ) -> Result<SVec<T>, DecodeError> {
+        let len = self.usize()?;
+        // Every element takes at least a byte, so this doesn't allocate more than the input could hold
+        let mut elements = Vec::with_capacity(len.min(self.bytes.len()));
+        for _ in 0..len {
+            elements.push(element(self)?);
+        }
+
+        Ok(SVec::from_vec(elements))
+    }
+
+    fn header(&mut self) -> Result<LayoutHeader, DecodeError> {
+        let format_version = u32::from_le_bytes(self.take()?);
+        if format_version != LAYOUT_FORMAT_VERSION {
+            return Err(DecodeError::FormatVersion(format_version));
+        }
+
+        Ok(LayoutHeader {
+            format_version,
+            pointer_width: self.u8()?,
+            endianness: match self.u8()? {
+                0 => Endianness::Little,
+                1 => Endianness::Big,
+                _ => return Err(DecodeError::Invalid),
+            },
+            target: self.str()?,
+            rustc_version: self.str()?,
+        })
+    }
+    fn defined_type(&mut self) -> Result<DefinedType, DecodeError> {
+        Ok(DefinedType {
+            name: self.str()?,
+            repr: Repr {
+                c: self.bool()?,
+                transparent: self.bool()?,
+                primitive: match self.bool()? {
+                    true => SOption::Some(repr_primitive(self.u8()?)?),
+                    false => SOption::None,
+                },
+                packed: self.option()?,
+                align: self.option()?,
+            },
+            flags: TypeFlags {
+                send: self.bool()?,
+                sync: self.bool()?,
+                copy: self.bool()?,
+                needs_drop: self.bool()?,
+            },
+            known: match self.bool()? {
+                true => SOption::Some(known_type(self.u8()?)?),
+                false => SOption::None,
+            },
+            size: self.usize()?,
+            align: self.usize()?,
+            ty: match self.u8()? {
+                0 => TypeType::StructNamed {
+                    fields: self.list(Self::named_field)?,
+                },
+                1 => TypeType::StructUnnamed {
+                    fields: self.list(Self::unnamed_field)?,
+                },
+                2 => TypeType::StructUnit,
+                3 => TypeType::Enum {
+                    variants: self.list(Self::variant)?,
+                    layout: match self.u8()? {
+                        0 => EnumLayout::Inline {
+                            tag_size: self.usize()?,
+                        },
+                        1 => EnumLayout::Separate {
+                            tag_size: self.usize()?,
+                        },
+                        2 => EnumLayout::Unspecified,
+                        _ => return Err(DecodeError::Invalid),
+                    },
+                },
+                4 => TypeType::Union {
+                    fields: self.list(Self::named_field)?,
+                },
+                5 => TypeType::Opaque,
+                _ => return Err(DecodeError::Invalid),
+            },
+        })
+    }
+    fn named_field(&mut self) -> Result<NamedField, DecodeError> {
+        Ok(NamedField {
+            name: self.str()?,
+            layout: self.layout()?,
+            offset: self.usize()?,
+        })
+    }
+    fn unnamed_field(&mut self) -> Result<UnnamedField, DecodeError> {
+        Ok(UnnamedField {
+            layout: self.layout()?,
+            offset: self.usize()?,
+        })
+    }
+    fn variant(&mut self) -> Result<EnumVariant, DecodeError> {
+        Ok(EnumVariant {
+            name: self.str()?,
+            ty: match self.u8()? {
+                0 => EnumVariantType::Unit,
+                1 => EnumVariantType::Tuple(self.list(Self::unnamed_field)?),
+                2 => EnumVariantType::Struct(self.list(Self::named_field)?),
+                _ => return Err(DecodeError::Invalid),
+            },
+            discriminant: match self.u8()? {
+                0 => Discriminant::Unsigned(self.u128()?),
+                1 => Discriminant::Signed(self.u128()? as i128),
+                _ => return Err(DecodeError::Invalid),
+            },
+            payload_offset: self.usize()?,
+        })
+    }
+    fn layout(&mut self) -> Result<Layout, DecodeError> {
+        if self.depth == Self::MAX_DEPTH {
+            return Err(DecodeError::Invalid);
+        }
+        self.depth += 1;
+        let layout = self.layout_inner();
+        self.depth -= 1;
+
+        layout
+    }
+    fn layout_inner(&mut self) -> Result<Layout, DecodeError> {
+        let inner = |this: &mut Self| this.layout().map(SBox::new);
+
+        Ok(match self.u8()? {
+            0 => Layout::Void,
+            1 => Layout::U8,
+            2 => Layout::U16,
+            3 => Layout::U32,
+            4 => Layout::U64,
+            5 => Layout::U128,
+            6 => Layout::I8,
+            7 => Layout::I16,
+            8 => Layout::I32,
+            9 => Layout::I64,
+            10 => Layout::I128,
+            11 => Layout::USize,
+            12 => Layout::ISize,
+            13 => Layout::Bool,
+            14 => Layout::F32,
+            15 => Layout::F64,
+            16 => Layout::Char,
+            17 => Layout::ConstPtr(inner(self)?),
+            18 => Layout::MutPtr(inner(self)?),
+            19 => Layout::Ref(inner(self)?),
+            20 => Layout::MutRef(inner(self)?),
+            21 => Layout::Array {
+                len: self.usize()?,
+                layout: inner(self)?,
+            },
+            22 => Layout::FunctionPointer {
+                is_unsafe: self.bool()?,
+                abi: self.str()?,
+                args: self.list(Self::layout)?,
+                return_ty: inner(self)?,
+            },
+            23 => {
+                let id = self.usize()?;
+                self.max_id = self.max_id.max(Some(id));
+
+                Layout::DefinedType { id }
+            }
+            24 => Layout::NonExhaustive(inner(self)?),
+            25 => Layout::Atomic(inner(self)?),
+            26 => Layout::NonZero(inner(self)?),
+            27 => Layout::NicheOption(inner(self)?),
+            28 => Layout::C {
+                ty: c_type(self.u8()?)?,
+                layout: inner(self)?,
+            },
+            29 => Layout::RelPtr(inner(self)?),
+            _ => return Err(DecodeError::Invalid),
+        })
+    }
+}
+
+/// Checks that a decoded layout is consistent, so that sizes computed from it don't overflow,
+/// and values read with it don't reach outside of their types or recurse forever
+fn check(layout: &TypeLayout) -> bool {
+    let pointer_size = match layout.header.pointer_width {
+        width @ (16 | 32 | 64) => usize::from(width / 8),
+        _ => return false,
+    };
+    let mut checker = Checker {
+        defined_types: &layout.defined_types,
+        pointer_size,
+        shapes: HashMap::new(),
+        targets: Vec::new(),
+    };
+
+    if !(0..layout.defined_types.len()).all(|id| checker.defined_type(id, 0).is_some())
+        || checker.layout(&layout.layout, 0).is_none()
+    {
+        return false;
+    }
+
+    // Pointed-to values are read separately, so their layouts are checked on their own once the heights
+    // of all defined types are known, which also allows types to point to themselves
+    while let Some(target) = checker.targets.pop() {
+        if checker.layout(target, 0).is_none() {
+            return false;
+        }
+    }
+
+    true
+}
+
+/// The size of a value, how deeply its fields are nested, and how many values it is made of
+/// (itself, its fields, their fields and so on)
+type Shape = (usize, usize, usize);
+
+struct Checker<'a> {
+    defined_types: &'a [DefinedType],
+    /// The size of pointers on the target of the layout
+    pointer_size: usize,
+    /// The shapes of values of checked defined types, or `None` while a type is being checked
+    shapes: HashMap<usize, Option<Shape>>,
+    /// The layouts of pointed-to values, which are checked last
+    targets: Vec<&'a Layout>,
+}
+
+impl<'a> Checker<'a> {
+    /// How deeply fields can be nested, so that code reading values with the layout can't overflow the stack.
+    /// Lower than [`Decoder::MAX_DEPTH`], since reading a value takes more stack than decoding a layout.
+    const MAX_DEPTH: usize = 128;
+    /// How many values a zero-sized value can be made of (the length of an array of `()`s for example).
+    /// Larger values can be made of [`Self::MAX_DEPTH`] more values per byte, so that reading values with
+    /// the layout takes time proportional to their size.
+    const MAX_ZERO_SIZED_VALUES: usize = 1 << 16;
+
+    /// Returns the shape of values of the layout. `depth` is how deeply the layout itself is nested.
+    fn layout(&mut self, layout: &'a Layout, depth: usize) -> Option<Shape> {
+        if depth > Self::MAX_DEPTH {
+            return None;
+        }
+
+        let size = match layout {
+            Layout::Void => 0,
+            Layout::U8 | Layout::I8 | Layout::Bool => 1,
+            Layout::U16 | Layout::I16 => 2,
+            Layout::U32 | Layout::I32 | Layout::F32 | Layout::Char => 4,
+            Layout::U64 | Layout::I64 | Layout::F64 => 8,
+            Layout::U128 | Layout::I128 => 16,
+            Layout::USize | Layout::ISize => self.pointer_size,
+            Layout::ConstPtr(target)
+            | Layout::MutPtr(target)
+            | Layout::Ref(target)
+            | Layout::MutRef(target)
+            | Layout::RelPtr(target) => {
+                self.targets.push(target);
+                self.pointer_size
+            }
+            Layout::FunctionPointer {
+                args, return_ty, ..
+            } => {
+                self.targets.extend(args.iter());
+                self.targets.push(return_ty);
+                self.pointer_size
+            }
+            Layout::Array { len, layout } => {
+                let (size, height, count) = self.layout(layout, depth + 1)?;
+                return Self::shape(
+                    size.checked_mul(*len)?,
+                    height + 1,
+                    count.checked_mul(*len)?,
+                );
+            }
+            Layout::DefinedType { id } => return self.defined_type(*id, depth),
+            Layout::NonExhaustive(layout)
+            | Layout::Atomic(layout)
+            | Layout::NonZero(layout)
+            | Layout::NicheOption(layout)
+            | Layout::C { layout, .. } => {
+                let (size, height, count) = self.layout(layout, depth + 1)?;
+                return Self::shape(size, height + 1, count);
+            }
+        };
+
+        Some((size, 1, 1))
+    }
+
+    /// Checks that a value isn't made of too many values for its size
+    fn shape(size: usize, height: usize, count: usize) -> Option<Shape> {
+        let count = count.checked_add(1)?;
+        let max = size
+            .saturating_mul(Self::MAX_DEPTH)
+            .saturating_add(Self::MAX_ZERO_SIZED_VALUES);
+
+        (size <= isize::MAX as usize && count <= max).then_some((size, height, count))
+    }
+
+    fn defined_type(&mut self, id: usize, depth: usize) -> Option<Shape> {
+        let ty = &self.defined_types[id];
+        let shape = match self.shapes.get(&id) {
+            Some(Some(shape)) => *shape,
+            // The type contains itself
+            Some(None) => return None,
+            None => {
+                self.shapes.insert(id, None);
+                let shape = self.type_shape(ty, depth)?;
+                self.shapes.insert(id, Some(shape));
+                shape
+            }
+        };
+
+        (depth + shape.1 <= Self::MAX_DEPTH).then_some(shape)
+    }
+
+    /// Checks the size, the alignment and the fields of the type
+    fn type_shape(&mut self, ty: &'a DefinedType, depth: usize) -> Option<Shape> {
+        if !ty.align.is_power_of_two()
+            || !ty.size.is_multiple_of(ty.align)
+            || ty.size > isize::MAX as usize
+        {
+            return None;
+        }
+
+        // The fields of each variant, only one of which is read
+        let variants: Vec<Vec<(&Layout, usize)>> = match &ty.ty {
+            TypeType::StructNamed { fields } => {
+                vec![fields.iter().map(|f| (&f.layout, f.offset)).collect()]
+            }
+            TypeType::StructUnnamed { fields } => {
+                vec![fields.iter().map(|f| (&f.layout, f.offset)).collect()]
+            }
+            // Only one field of a union is valid at a time
+            TypeType::Union { fields } => {
+                fields.iter().map(|f| vec![(&f.layout, f.offset)]).collect()
+            }
+            TypeType::Enum { variants, .. } => {
+                let tag = match ty.repr.primitive {
+                    SOption::Some(ReprPrimitive::U8 | ReprPrimitive::I8) => 1,
+                    SOption::Some(ReprPrimitive::U16 | ReprPrimitive::I16) => 2,
+                    SOption::Some(ReprPrimitive::U32 | ReprPrimitive::I32) => 4,
+                    SOption::Some(ReprPrimitive::U64 | ReprPrimitive::I64) => 8,
+                    SOption::Some(ReprPrimitive::U128 | ReprPrimitive::I128) => 16,
+                    SOption::Some(ReprPrimitive::USize | ReprPrimitive::ISize) => self.pointer_size,
+                    SOption::None if ty.repr.c => size_of::<c_int>(),
+                    SOption::None => 0,
+                };
+                if tag > ty.size {
+                    return None;
+                }
+
+                variants
+                    .iter()
+                    .map(|v| match &v.ty {
+                        EnumVariantType::Unit => Vec::new(),
+                        EnumVariantType::Tuple(fields) => {
+                            fields.iter().map(|f| (&f.layout, f.offset)).collect()
+                        }
+                        EnumVariantType::Struct(fields) => {
+                            fields.iter().map(|f| (&f.layout, f.offset)).collect()
+                        }
+                    })
+                    .collect()
+            }
+            TypeType::StructUnit | TypeType::Opaque => Vec::new(),
+        };
+
+        let (mut height, mut count) = (1, 0);
+        for fields in variants {
+            let mut variant_count = 0usize;
+            for (layout, offset) in fields {
+                let (size, field_height, field_count) = self.layout(layout, depth + 1)?;
+                if offset.checked_add(size)? > ty.size {
+                    return None;
+                }
+                height = height.max(field_height + 1);
+                variant_count = variant_count.checked_add(field_count)?;
+            }
+            count = count.max(variant_count);
+        }
+
+        Self::shape(ty.size, height, count)
+    }
+}
+
+fn repr_primitive(tag: u8) -> Result<ReprPrimitive, DecodeError> {
+    use ReprPrimitive::*;
+
+    [
+        U8, U16, U32, U64, U128, USize, I8, I16, I32, I64, I128, ISize,
+    ]
+    .into_iter()
+    .find(|p| *p as u8 == tag)
+    .ok_or(DecodeError::Invalid)
+}
+
+fn known_type(tag: u8) -> Result<KnownType, DecodeError> {
+    use KnownType::*;
+
+    [Slice, Str, Box, Option, NonNull]
+        .into_iter()
+        .find(|k| *k as u8 == tag)
+        .ok_or(DecodeError::Invalid)
+}
+
+fn c_type(tag: u8) -> Result<CType, DecodeError> {
+    use CType::*;
+
+    [
+        Char, SChar, UChar, Short, UShort, Int, UInt, Long, ULong, LongLong, ULongLong, SizeT,
+        SSizeT,
+    ]
+    .into_iter()
+    .find(|ty| *ty as u8 == tag)
+    .ok_or(DecodeError::Invalid)
+}
+
+/// The names interned by [`intern`]
+static NAMES: OnceLock<Mutex<HashSet<&'static str>>> = OnceLock::new();
+
+/// Names in layouts are `&'static str`s, so decoded names are leaked, but only once per distinct name.
+fn intern(s: &str) -> &'static str {
+    let names = &mut *NAMES.get_or_init(Default::default).lock().unwrap();
+    if let Some(name) = names.get(s) {
+        return name;
+    }
+
+    let name: &'static str = Box::leak(s.into());
+    names.insert(name);
+    name
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DecodeError, MAX_INTERNED_LEN, NAMES};
+    use crate::{
+        layout::{Layout, TypeType},
+        types::{SBox, SNonExhaustive, SOption, SStr, SVec},
+        TypeInfo, TypeLayout,
+    };
+
+    #[repr(C)]
+    #[derive(TypeInfo)]
+    struct Node<'a> {
+        name: SStr<'a>,
+        children: SVec<Node<'a>>,
+        callback: SOption<extern "C" fn(u32) -> bool>,
+        state: SNonExhaustive<State>,
+    }
+
+    #[repr(i8)]
+    #[derive(TypeInfo)]
+    #[allow(dead_code)]
+    enum State {
+        Idle = -1,
+        Busy { jobs: [u16; 2] },
+    }
+
+    #[test]
+    fn round_trip() {
+        let layout = Node::layout();
+        let bytes = layout.to_bytes();
+
+        assert_eq!(TypeLayout::from_bytes(&bytes), Ok(layout));
+        assert_eq!(
+            TypeLayout::from_bytes(&bytes[..bytes.len() - 1]),
+            Err(DecodeError::UnexpectedEnd)
+        );
+        assert_eq!(
+            TypeLayout::from_bytes(&[bytes.as_slice(), &[0]].concat()),
+            Err(DecodeError::Invalid)
+        );
+
+        let mut old = bytes.clone();
+        old[..4].copy_from_slice(&1u32.to_le_bytes());
+        assert_eq!(
+            TypeLayout::from_bytes(&old),
+            Err(DecodeError::FormatVersion(1))
+        );
+    }
+
+    #[test]
+    fn inconsistent_layouts() {
+        #[repr(C)]
+        #[derive(TypeInfo)]
+        struct Pair {
+            a: u32,
+            b: [u16; 2],
+        }
+
+        let decode = |change: fn(&mut TypeLayout, usize)| {
+            let mut layout = Pair::layout();
+            let Layout::DefinedType { id } = layout.layout else {
+                unreachable!()
+            };
+            change(&mut layout, id);
+            TypeLayout::from_bytes(&layout.to_bytes())
+        };
+        fn field(layout: &mut TypeLayout, id: usize, i: usize) -> &mut Layout {
+            let TypeType::StructNamed { fields } = &mut layout.defined_types[id].ty else {
+                unreachable!()
+            };
+            &mut fields[i].layout
+        }
+
+        assert_eq!(decode(|_, _| {}), Ok(Pair::layout()));
+        // The second field is outside of the type
+        assert_eq!(
+            decode(|layout, id| layout.defined_types[id].size = 4),
+            Err(DecodeError::Invalid)
+        );
+        assert_eq!(
+            decode(|layout, id| layout.defined_types[id].align = 3),
+            Err(DecodeError::Invalid)
+        );
+        // The size of the array overflows
+        assert_eq!(
+            decode(|layout, id| *field(layout, id, 1) = Layout::Array {
+                len: usize::MAX,
+                layout: SBox::new(Layout::U16),
+            }),
+            Err(DecodeError::Invalid)
+        );
+        // The type contains itself
+        assert_eq!(
+            decode(|layout, id| *field(layout, id, 1) = Layout::DefinedType { id }),
+            Err(DecodeError::Invalid)
+        );
+        // Nested too deeply for values to be read without overflowing the stack
+        assert_eq!(
+            decode(|layout, id| {
+                let mut nested = Layout::Array {
+                    len: 2,
+                    layout: SBox::new(Layout::U16),
+                };
+                for _ in 0..200 {
+                    nested = Layout::Array {
+                        len: 1,
+                        layout: SBox::new(nested),
+                    };
+                }
+                *field(layout, id, 1) = nested;
+            }),
+            Err(DecodeError::Invalid)
+        );
+        // Unless it's through a pointer, which fits in the 8 bytes of the type
+        assert!(decode(|layout, id| *field(layout, id, 0) =
+            Layout::MutPtr(SBox::new(Layout::DefinedType { id })))
+        .is_ok());
+        // The pointed-to values are checked too
+        assert_eq!(
+            decode(
+                |layout, id| *field(layout, id, 0) = Layout::RelPtr(SBox::new(Layout::Array {
+                    len: usize::MAX,
+                    layout: SBox::new(Layout::U16),
+                }))
+            ),
+            Err(DecodeError::Invalid)
+        );
+        // Too many zero-sized elements to read in a reasonable time
+        assert_eq!(
+            decode(|layout, id| *field(layout, id, 1) = Layout::Array {
+                len: usize::MAX,
+                layout: SBox::new(Layout::Void),
+            }),
+            Err(DecodeError::Invalid)
+        );
+        assert!(decode(|layout, id| *field(layout, id, 1) = Layout::Array {
+            len: 16,
+            layout: SBox::new(Layout::Void),
+        })
+        .is_ok());
+    }
+
+    #[test]
+    fn long_names() {
+        let mut layout = State::layout();
+        let Layout::DefinedType { id } = layout.layout else {
+            unreachable!()
+        };
+        layout.defined_types[id].name = SStr::new("a".repeat(MAX_INTERNED_LEN + 1).leak());
+
+        assert_eq!(
+            TypeLayout::from_bytes(&layout.to_bytes()),
+            Err(DecodeError::TooManyNames)
+        );
+        assert!(TypeLayout::from_bytes(&State::layout().to_bytes()).is_ok());
+    }
+
+    #[test]
+    fn rejected_names_not_interned() {
+        let mut layout = State::layout();
+        let Layout::DefinedType { id } = layout.layout else {
+            unreachable!()
+        };
+        layout.defined_types[id].name = SStr::new("RejectedState");
+        layout.defined_types[id].size = 0;
+
+        assert_eq!(
+            TypeLayout::from_bytes(&layout.to_bytes()),
+            Err(DecodeError::Invalid)
+        );
+        let names = NAMES.get_or_init(Default::default).lock().unwrap();
+        assert!(!names.contains("RejectedState"));
+    }
+}
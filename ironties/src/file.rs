@@ -0,0 +1,283 @@
+//! Zero-copy files of values, checked against the layouts of their types when opened.
+//!
+//! A file starts with a header containing the [encoded][TypeLayout::to_bytes] layout of the stored type,
+//! followed by the value itself, aligned and with its padding zeroed:
+//!
+//! | Bytes | Contents |
+//! | --- | --- |
+//! | 8 | `IRONTIES` |
+//! | 8 | The length of the encoded layout, little-endian |
+//! | 8 | The offset of the value from the start of the file, little-endian |
+//! | | The encoded layout, and zeroes up to the value |
+//! | | The value |
+//!
+//! Values can't contain pointers, since they would be meaningless once the file is mapped elsewhere.
+//! [Relative pointers][crate::types::RelPtr] can be used instead, as long as they point within the value.
+//! Unions and opaque types are rejected too, since their contents can't be checked.
+
+use crate::{
+    dynamic::{unchecked_type, validate, Copier, InvalidValue},
+    encode::DecodeError,
+    TypeInfo, TypeLayout,
+};
+use memmap2::{MmapMut, MmapOptions};
+use std::{fmt::Display, fs::File, io::Write, marker::PhantomData, ops::Deref, path::Path};
+
+const MAGIC: [u8; 8] = *b"IRONTIES";
+const HEADER_SIZE: usize = 24;
+
+/// Why a file couldn't be written or opened
+#[derive(Debug)]
+pub enum FileError {
+    Io(std::io::Error),
+    /// The file doesn't start with a valid header
+    InvalidHeader,
+    /// The layout in the header couldn't be decoded
+    Layout(DecodeError),
+    /// The file contains a value of a different type
+    LayoutMismatch,
+    /// The type contains pointers, unions or opaque types (`name` is the type containing them, or the type itself)
+    Unsupported {
+        name: String,
+    },
+    /// The value in the file is not a valid value of the type
+    InvalidValue(InvalidValue),
+}
+
+impl Display for FileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FileError::Io(err) => Display::fmt(err, f),
+            FileError::InvalidHeader => write!(f, "the file doesn't have a valid header"),
+            FileError::Layout(err) => write!(f, "invalid layout in the header: {err}"),
+            FileError::LayoutMismatch => write!(f, "the file contains a value of a different type"),
+            FileError::Unsupported { name } => write!(
+                f,
+                "`{name}` contains pointers, unions or opaque types, which can't be stored in files"
+            ),
+            FileError::InvalidValue(err) => write!(f, "invalid value in the file: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for FileError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            FileError::Io(err) => Some(err),
+            FileError::Layout(err) => Some(err),
+            FileError::InvalidValue(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for FileError {
+    fn from(value: std::io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+/// Writes the value to a new file (or truncates an existing one), to be opened with [`MappedFile::open`]
+pub fn write<T: TypeInfo>(path: impl AsRef<Path>, value: &T) -> Result<(), FileError> {
+    let layout = T::layout_ref();
+    check_storable(layout)?;
+
+    let encoded = layout.to_bytes();
+    let offset = (HEADER_SIZE + encoded.len()).next_multiple_of(align_of::<T>());
+
+    let mut bytes = vec![0; offset + size_of::<T>()];
+    bytes[..8].copy_from_slice(&MAGIC);
+    bytes[8..16].copy_from_slice(&(encoded.len() as u64).to_le_bytes());
+    bytes[16..24].copy_from_slice(&(offset as u64).to_le_bytes());
+    bytes[HEADER_SIZE..HEADER_SIZE + encoded.len()].copy_from_slice(&encoded);
+
+    // SAFETY: the layout describes T, and the buffer has room for it
+    unsafe {
+        Copier {
+            defined_types: &layout.defined_types,
+        }
+        .copy(
+            &layout.layout,
+            (value as *const T).cast(),
+            bytes[offset..].as_mut_ptr(),
+        )
+    };
+
+    File::create(path)?.write_all(&bytes)?;
+
+    Ok(())
+}
+
+/// A file written with [`write()`], mapped into memory
+///
+/// The mapping is private, so changes to the value through interior mutability are not written to the file.
+pub struct MappedFile<T> {
+    mmap: MmapMut,
+    offset: usize,
+    _phantom: PhantomData<T>,
+}
+
+impl<T: TypeInfo> MappedFile<T> {
+    /// Maps the file into memory, and checks that it contains a valid value of `T`
+    ///
+    /// # Safety
+    ///
+    /// The file must not be modified while it's mapped (see [`memmap2::Mmap`]).
+    pub unsafe fn open(path: impl AsRef<Path>) -> Result<Self, FileError> {
+        let mmap = MmapOptions::new().map_copy(&File::open(path)?)?;
+
+        if mmap.len() < HEADER_SIZE || mmap[..8] != MAGIC {
+            return Err(FileError::InvalidHeader);
+        }
+        let layout_len = u64::from_le_bytes(mmap[8..16].try_into().unwrap());
+        let offset = u64::from_le_bytes(mmap[16..24].try_into().unwrap());
+        let (Ok(layout_len), Ok(offset)) = (usize::try_from(layout_len), usize::try_from(offset))
+        else {
+            return Err(FileError::InvalidHeader);
+        };
+        if HEADER_SIZE
+            .checked_add(layout_len)
+            .is_none_or(|end| end > offset)
+            || offset > mmap.len()
+        {
+            return Err(FileError::InvalidHeader);
+        }
+
+        let layout = TypeLayout::from_bytes(&mmap[HEADER_SIZE..HEADER_SIZE + layout_len])
+            .map_err(FileError::Layout)?;
+        if !T::layout_ref().is_compatible_with(&layout) {
+            return Err(FileError::LayoutMismatch);
+        }
+        check_storable(T::layout_ref())?;
+        validate(&mmap[offset..], T::layout_ref()).map_err(FileError::InvalidValue)?;
+
+        Ok(Self {
+            mmap,
+            offset,
+            _phantom: PhantomData,
+        })
+    }
+    pub fn get(&self) -> &T {
+        // SAFETY: the value was validated when the file was opened, and it is aligned
+        unsafe { &*self.mmap.as_ptr().add(self.offset).cast() }
+    }
+}
+
+impl<T: TypeInfo> Deref for MappedFile<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        self.get()
+    }
+}
+
+/// Checks that the values of the layout can be stored in files
+fn check_storable(layout: &TypeLayout) -> Result<(), FileError> {
+    match unchecked_type(layout, "the value") {
+        Some(name) => Err(FileError::Unsupported {
+            name: name.to_owned(),
+        }),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{write, FileError, MappedFile};
+    use crate::{dynamic::InvalidValue, types::SVec, TypeInfo};
+    use std::path::PathBuf;
+
+    #[repr(C)]
+    #[derive(TypeInfo, Debug, PartialEq, Clone, Copy)]
+    struct Record {
+        id: u32,
+        active: bool,
+        score: f64,
+    }
+
+    #[repr(C)]
+    #[derive(TypeInfo, Debug, PartialEq)]
+    struct Records {
+        len: u8,
+        ids: [u32; 4],
+        records: [Record; 4],
+        kind: Kind,
+    }
+
+    #[repr(u8)]
+    #[derive(TypeInfo, Debug, PartialEq, Clone, Copy)]
+    #[allow(dead_code)]
+    enum Kind {
+        Cache,
+        Index { version: u16 },
+    }
+
+    fn path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("ironties-{}-{name}", std::process::id()))
+    }
+
+    fn records() -> Records {
+        let record = |id| Record {
+            id,
+            active: id % 2 == 0,
+            score: id as f64 / 2.0,
+        };
+
+        Records {
+            len: 4,
+            ids: [1, 2, 3, 4],
+            records: [record(1), record(2), record(3), record(4)],
+            kind: Kind::Index { version: 3 },
+        }
+    }
+
+    #[test]
+    fn write_and_open() {
+        let path = path("records");
+        write(&path, &records()).unwrap();
+
+        // SAFETY: the file is not modified while it's mapped
+        let file = unsafe { MappedFile::<Records>::open(&path) }.unwrap();
+        assert_eq!(*file, records());
+
+        // SAFETY: the same
+        let err = unsafe { MappedFile::<Record>::open(&path) }.err().unwrap();
+        assert!(matches!(err, FileError::LayoutMismatch));
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn invalid_files() {
+        let path = path("invalid");
+        write(&path, &records()).unwrap();
+
+        let mut bytes = std::fs::read(&path).unwrap();
+        let offset = u64::from_le_bytes(bytes[16..24].try_into().unwrap()) as usize;
+        let active =
+            offset + std::mem::offset_of!(Records, records) + std::mem::offset_of!(Record, active);
+        bytes[active] = 2;
+        std::fs::write(&path, &bytes).unwrap();
+
+        // SAFETY: the file is not modified while it's mapped
+        let err = unsafe { MappedFile::<Records>::open(&path) }.err().unwrap();
+        assert!(matches!(
+            err,
+            FileError::InvalidValue(InvalidValue::InvalidBool { value: 2, .. })
+        ));
+
+        std::fs::write(&path, b"not a file").unwrap();
+        // SAFETY: the same
+        let err = unsafe { MappedFile::<Records>::open(&path) }.err().unwrap();
+        assert!(matches!(err, FileError::InvalidHeader));
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn pointers() {
+        let err = write(path("pointers"), &SVec::from_vec(vec![1u8])).unwrap_err();
+
+        assert!(matches!(err, FileError::Unsupported { name } if name == "SVec"));
+    }
+}
@@ -0,0 +1,65 @@
+use crate::{types::SStr, TypeInfo};
+
+/// The version of the format of [`TypeLayout`][crate::TypeLayout].
+///
+/// Incremented every time the structure of [`TypeLayout`][crate::TypeLayout] or any of the types it contains changes.
+pub const LAYOUT_FORMAT_VERSION: u32 = 11;
+
+/// Describes what a [`TypeLayout`][crate::TypeLayout] was produced for
+///
+/// The same Rust type can have a different layout on different targets (`usize` for example),
+/// so layouts are only [compatible][crate::TypeLayout::is_compatible_with] if they were produced for the same target.
+///
+/// Headers compare equal if they are for the [same target][Self::same_target], even if they were produced by different compilers.
+#[repr(C)]
+#[derive(TypeInfo, Debug, Clone)]
+pub struct LayoutHeader {
+    /// Always the first field, so it can be checked before interpreting the rest of the layout.
+    pub format_version: u32,
+    /// The width of pointers in bits
+    pub pointer_width: u8,
+    pub endianness: Endianness,
+    /// The target triple (`x86_64-unknown-linux-gnu` for example)
+    pub target: SStr<'static>,
+    /// The output of `rustc --version`
+    pub rustc_version: SStr<'static>,
+}
+
+#[repr(u8)]
+#[derive(TypeInfo, Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Endianness {
+    Little,
+    Big,
+}
+
+impl LayoutHeader {
+    /// The header for layouts produced by the current compilation
+    pub const fn current() -> Self {
+        Self {
+            format_version: LAYOUT_FORMAT_VERSION,
+            pointer_width: usize::BITS as u8,
+            endianness: if cfg!(target_endian = "little") {
+                Endianness::Little
+            } else {
+                Endianness::Big
+            },
+            target: SStr::new(env!("IRONTIES_TARGET")),
+            rustc_version: SStr::new(env!("IRONTIES_RUSTC_VERSION")),
+        }
+    }
+    /// Checks whether both layouts can be compared, that is, they are of the same format and target.
+    ///
+    /// The compiler version is not taken into account, since it does not affect the layouts of FFI-safe types.
+    pub fn same_target(&self, other: &LayoutHeader) -> bool {
+        self.format_version == other.format_version
+            && self.pointer_width == other.pointer_width
+            && self.endianness == other.endianness
+            && self.target == other.target
+    }
+}
+
+impl PartialEq for LayoutHeader {
+    fn eq(&self, other: &Self) -> bool {
+        self.same_target(other)
+    }
+}
@@ -5,15 +5,73 @@
 //! procedural macro.
 
 use crate::{
-    types::{SBox, SStr, SVec},
+    _TypeInfoImpl,
+    types::{SBox, SOption, SStr, SVec},
     TypeInfo,
 };
+use std::{
+    any::type_name,
+    collections::HashMap,
+    marker::PhantomData,
+    ops::{Index, IndexMut},
+};
+
+/// A list of all defined types, indexed by their [`TypeUid`]s
+#[derive(Default)]
+pub struct DefinedTypes {
+    types: Vec<DefinedType>,
+    /// Generic types share the same [`TypeUid`], so the full type name is also a part of the key.
+    ids: HashMap<(TypeUid, &'static str), usize>,
+}
+
+impl DefinedTypes {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Returns the id of `T` if it is already defined
+    pub fn id_of<T: _TypeInfoImpl + ?Sized>(&self) -> Option<usize> {
+        self.ids.get(&(T::_UID, type_name::<T>())).copied()
+    }
+    /// Adds the definition of `T` and returns its id
+    pub fn define<T: _TypeInfoImpl + ?Sized>(&mut self, ty: DefinedType) -> usize {
+        let id = self.types.len();
+        self.types.push(ty);
+        self.ids.insert((T::_UID, type_name::<T>()), id);
 
-/// A list of all defined types with their [`TypeUid`]s
-pub type DefinedTypes = Vec<(TypeUid, DefinedType)>;
+        id
+    }
+    pub fn into_vec(self) -> Vec<DefinedType> {
+        self.types
+    }
+}
+
+/// The layout of a field. Requires [`TypeInfo`] rather than [`_TypeInfoImpl`], so that fields of types
+/// without a layout get the diagnostic of [`TypeInfo`].
+pub fn field_layout<T: TypeInfo>(defined_types: DefinedTypes) -> FullLayout {
+    T::_layout_impl(defined_types)
+}
+
+/// The fingerprint of a field, see [`field_layout`]
+pub const fn field_fingerprint<T: TypeInfo>() -> u128 {
+    T::_FINGERPRINT
+}
+
+impl Index<usize> for DefinedTypes {
+    type Output = DefinedType;
+
+    fn index(&self, id: usize) -> &Self::Output {
+        &self.types[id]
+    }
+}
+
+impl IndexMut<usize> for DefinedTypes {
+    fn index_mut(&mut self, id: usize) -> &mut Self::Output {
+        &mut self.types[id]
+    }
+}
 
 /// Unique type ID
-#[derive(PartialEq, Debug, Clone, Copy)]
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy)]
 pub struct TypeUid {
     /// The Rust source path to the type (`my_crate::path::to::MyType`)
     pub rustpath: &'static str,
@@ -37,9 +95,152 @@ pub struct FullLayout {
 #[derive(TypeInfo, Debug, PartialEq, Clone)]
 pub struct DefinedType {
     pub name: SStr<'static>,
+    pub repr: Repr,
+    pub flags: TypeFlags,
+    /// Set for the types of this crate and std which are read as the values they stand for,
+    /// regardless of their names
+    pub known: SOption<KnownType>,
+    pub size: usize,
+    pub align: usize,
     pub ty: TypeType,
 }
 
+/// The `#[repr(..)]` of a defined type
+#[repr(C)]
+#[derive(TypeInfo, Debug, PartialEq, Clone)]
+pub struct Repr {
+    /// `#[repr(C)]`
+    pub c: bool,
+    /// `#[repr(transparent)]`
+    pub transparent: bool,
+    /// The type of the discriminant of an enum (`#[repr(u8)]` for example)
+    pub primitive: SOption<ReprPrimitive>,
+    /// `#[repr(packed(N))]`
+    pub packed: SOption<usize>,
+    /// `#[repr(align(N))]`
+    pub align: SOption<usize>,
+}
+
+impl Repr {
+    /// The default representation, when there is no `#[repr(..)]` attribute
+    pub const RUST: Self = Self {
+        c: false,
+        transparent: false,
+        primitive: SOption::None,
+        packed: SOption::None,
+        align: SOption::None,
+    };
+    /// `#[repr(transparent)]`
+    pub const TRANSPARENT: Self = Self {
+        transparent: true,
+        ..Self::RUST
+    };
+}
+
+/// Properties of a defined type that don't show up in its memory layout,
+/// but still decide whether values can be safely passed between two versions of it
+///
+/// For generic types, they are detected for each instantiation (`SVec<u8>` is `Send`, but `SVec<Rc<u8>>` isn't).
+#[repr(C)]
+#[derive(TypeInfo, Debug, PartialEq, Eq, Clone, Copy)]
+pub struct TypeFlags {
+    pub send: bool,
+    pub sync: bool,
+    pub copy: bool,
+    /// Whether the type has drop glue (see [`std::mem::needs_drop`])
+    pub needs_drop: bool,
+}
+
+impl TypeFlags {
+    /// The flags of `T`
+    pub const fn of<T>() -> Self {
+        Self {
+            send: <Probe<T> as IsSend>::VALUE,
+            sync: <Probe<T> as IsSync>::VALUE,
+            copy: <Probe<T> as IsCopy>::VALUE,
+            needs_drop: std::mem::needs_drop::<T>(),
+        }
+    }
+}
+
+/// Checks whether a type implements `Send`, `Sync` or `Copy` through specialization, which unlike
+/// trait bounds is resolved after monomorphization, so it also works for generic types.
+struct Probe<T: ?Sized>(PhantomData<T>);
+
+trait IsSend {
+    const VALUE: bool;
+}
+impl<T: ?Sized> IsSend for Probe<T> {
+    default const VALUE: bool = false;
+}
+impl<T: ?Sized + Send> IsSend for Probe<T> {
+    const VALUE: bool = true;
+}
+
+trait IsSync {
+    const VALUE: bool;
+}
+impl<T: ?Sized> IsSync for Probe<T> {
+    default const VALUE: bool = false;
+}
+impl<T: ?Sized + Sync> IsSync for Probe<T> {
+    const VALUE: bool = true;
+}
+
+trait IsCopy {
+    const VALUE: bool;
+}
+impl<T: ?Sized> IsCopy for Probe<T> {
+    default const VALUE: bool = false;
+}
+impl<T: Copy> IsCopy for Probe<T> {
+    const VALUE: bool = true;
+}
+
+/// What a type of this crate or std stands for in [`dynamic`][crate::dynamic]
+///
+/// Derived types can't be marked as one of these, so a type that shares the name and fields of `SVec`
+/// is still read as a plain struct.
+#[derive(TypeInfo, Debug, PartialEq, Eq, Clone, Copy)]
+#[repr(u8)]
+pub enum KnownType {
+    /// `SSlice`, `SMutSlice`, `SVec`, `SBoxSlice` and `RelSlice`
+    Slice,
+    /// `SStr`, `SMutStr`, `SString`, `SBoxStr` and `RelStr`
+    Str,
+    /// `SBox`
+    Box,
+    /// `SOption`
+    Option,
+    /// `NonNull`
+    NonNull,
+}
+
+impl KnownType {
+    /// Only callable from this crate, which is what keeps derived types from being marked
+    pub(crate) const fn mark(self) -> SOption<Self> {
+        SOption::Some(self)
+    }
+}
+
+/// The primitive representation of an enum
+#[derive(TypeInfo, Debug, PartialEq, Eq, Clone, Copy)]
+#[repr(u8)]
+pub enum ReprPrimitive {
+    U8,
+    U16,
+    U32,
+    U64,
+    U128,
+    USize,
+    I8,
+    I16,
+    I32,
+    I64,
+    I128,
+    ISize,
+}
+
 /// The type of a type (`struct`, `enum`, etc)
 #[derive(TypeInfo, Debug, PartialEq, Clone)]
 #[repr(u8)]
@@ -48,16 +249,36 @@ pub enum TypeType {
         fields: SVec<NamedField>,
     },
     StructUnnamed {
-        fields: SVec<Layout>,
+        fields: SVec<UnnamedField>,
     },
     StructUnit,
     Enum {
         variants: SVec<EnumVariant>,
-        repr: SStr<'static>,
+        layout: EnumLayout,
     },
     Union {
         fields: SVec<NamedField>,
     },
+    /// A type of which only the size and alignment are known (`#[ironties(opaque)]`)
+    Opaque,
+}
+
+/// How the tag and the fields of an enum are laid out in memory
+///
+/// In both stable layouts, the fields of each variant are laid out like in a `#[repr(C)]` struct,
+/// starting at the [`payload_offset`][EnumVariant::payload_offset] of the variant.
+#[derive(TypeInfo, Debug, PartialEq, Clone)]
+#[repr(u8)]
+pub enum EnumLayout {
+    /// `#[repr(u8)]` and other primitive representations:
+    /// every variant is a `#[repr(C)]` struct which starts with the tag.
+    Inline { tag_size: usize },
+    /// `#[repr(C)]` and `#[repr(C, u8)]`: the tag is followed by a `#[repr(C)]` union of all variants,
+    /// so all variants have the same payload offset.
+    Separate { tag_size: usize },
+    /// The layout is not specified (`#[ironties(unsafe_allow_repr_rust)]`),
+    /// so the payload offsets and the offsets of the fields of variants are meaningless
+    Unspecified,
 }
 
 /// The layout of a single segment
@@ -69,10 +290,12 @@ pub enum Layout {
     U16,
     U32,
     U64,
+    U128,
     I8,
     I16,
     I32,
     I64,
+    I128,
     USize,
     ISize,
     Bool,
@@ -96,6 +319,55 @@ pub enum Layout {
     DefinedType {
         id: usize, // id in the defined_types vec
     },
+    /// An enum which may have more variants than are listed in its definition (see [`SNonExhaustive`][crate::types::SNonExhaustive])
+    NonExhaustive(SBox<Layout>),
+    /// An atomic integer or pointer (`AtomicU32` for example)
+    Atomic(SBox<Layout>),
+    /// An integer which is never zero (`NonZeroU32` for example)
+    NonZero(SBox<Layout>),
+    /// An `Option` of a type which can't be zero (references, function pointers, `NonNull`, `NonZero*`),
+    /// where `None` is represented by zero
+    NicheOption(SBox<Layout>),
+    /// A C type whose size or signedness depends on the target, with its layout on the current target
+    /// (see the [C types][crate::types::CInt] in [`types`][crate::types])
+    C {
+        ty: CType,
+        layout: SBox<Layout>,
+    },
+    /// A pointer stored as an offset from its own address (see [`RelPtr`][crate::types::RelPtr])
+    RelPtr(SBox<Layout>),
+}
+
+/// A C platform type
+#[derive(TypeInfo, Debug, PartialEq, Eq, Clone, Copy)]
+#[repr(u8)]
+pub enum CType {
+    /// `char`
+    Char,
+    /// `signed char`
+    SChar,
+    /// `unsigned char`
+    UChar,
+    /// `short`
+    Short,
+    /// `unsigned short`
+    UShort,
+    /// `int`
+    Int,
+    /// `unsigned int`
+    UInt,
+    /// `long`
+    Long,
+    /// `unsigned long`
+    ULong,
+    /// `long long`
+    LongLong,
+    /// `unsigned long long`
+    ULongLong,
+    /// `size_t`
+    SizeT,
+    /// `ssize_t`
+    SSizeT,
 }
 
 /// A field's name and layout
@@ -104,6 +376,17 @@ pub enum Layout {
 pub struct NamedField {
     pub name: SStr<'static>,
     pub layout: Layout,
+    /// The offset from the start of the type (for fields of enum variants, from the start of the enum)
+    pub offset: usize,
+}
+
+/// The layout of a field of a tuple struct or tuple variant
+#[repr(C)]
+#[derive(TypeInfo, Debug, PartialEq, Clone)]
+pub struct UnnamedField {
+    pub layout: Layout,
+    /// The offset from the start of the type (for fields of enum variants, from the start of the enum)
+    pub offset: usize,
 }
 
 #[repr(C)]
@@ -111,13 +394,426 @@ pub struct NamedField {
 pub struct EnumVariant {
     pub name: SStr<'static>,
     pub ty: EnumVariantType,
-    pub discriminant: i64,
+    pub discriminant: Discriminant,
+    /// The offset of the first field from the start of the enum, see [`EnumLayout`]
+    pub payload_offset: usize,
 }
 
+/// The value of an enum discriminant
+///
+/// The value is evaluated in the type of the discriminant (see [`Repr::primitive`]), which is `isize`
+/// if the enum doesn't have a primitive representation.
+#[derive(TypeInfo, Debug, PartialEq, Eq, Clone, Copy)]
+#[repr(u8)]
+pub enum Discriminant {
+    Unsigned(u128),
+    Signed(i128),
+}
+
+macro_rules! discriminant_from {
+    ($variant:ident: $($ty:ty),*) => {
+        $(
+            impl From<$ty> for Discriminant {
+                fn from(value: $ty) -> Self {
+                    Self::$variant(value as _)
+                }
+            }
+        )*
+    };
+}
+discriminant_from!(Unsigned: u8, u16, u32, u64, u128, usize);
+discriminant_from!(Signed: i8, i16, i32, i64, i128, isize);
+
 #[repr(C)]
 #[derive(TypeInfo, Debug, PartialEq, Clone)]
 pub enum EnumVariantType {
     Unit,
-    Tuple(SVec<Layout>),
+    Tuple(SVec<UnnamedField>),
     Struct(SVec<NamedField>),
 }
+
+/// Builds 128-bit structural fingerprints of types in const contexts (FNV-1a)
+///
+/// Every piece of data is written with its length, so that different sequences can't produce the same input.
+#[derive(Debug, Clone, Copy)]
+pub struct Fingerprinter(u128);
+
+impl Fingerprinter {
+    const PRIME: u128 = 0x0000000001000000000000000000013B;
+    const OFFSET_BASIS: u128 = 0x6c62272e07bb014262b821756295c58d;
+
+    pub const fn new() -> Self {
+        Self(Self::OFFSET_BASIS)
+    }
+    pub const fn bytes(self, bytes: &[u8]) -> Self {
+        let mut hash = self.u64(bytes.len() as u64).0;
+        let mut i = 0;
+        while i < bytes.len() {
+            hash ^= bytes[i] as u128;
+            hash = hash.wrapping_mul(Self::PRIME);
+            i += 1;
+        }
+
+        Self(hash)
+    }
+    pub const fn str(self, s: &str) -> Self {
+        self.bytes(s.as_bytes())
+    }
+    /// For types which might not implement [`TypeInfo`], and so have no fingerprint of their own
+    pub const fn type_name<T: ?Sized>(self) -> Self {
+        self.str(std::any::type_name::<T>())
+    }
+    pub const fn u64(self, value: u64) -> Self {
+        let bytes = value.to_le_bytes();
+        let mut hash = self.0;
+        let mut i = 0;
+        while i < bytes.len() {
+            hash ^= bytes[i] as u128;
+            hash = hash.wrapping_mul(Self::PRIME);
+            i += 1;
+        }
+
+        Self(hash)
+    }
+    pub const fn i64(self, value: i64) -> Self {
+        self.u64(value as u64)
+    }
+    pub const fn bool(self, value: bool) -> Self {
+        self.u64(value as u64)
+    }
+    pub const fn u128(self, value: u128) -> Self {
+        self.u64(value as u64).u64((value >> 64) as u64)
+    }
+    pub const fn repr(self, repr: &Repr) -> Self {
+        let mut this = self.bool(repr.c).bool(repr.transparent);
+        this = match repr.primitive {
+            SOption::Some(primitive) => this.bool(true).u64(primitive as u64),
+            SOption::None => this.bool(false),
+        };
+        this = match repr.packed {
+            SOption::Some(packed) => this.bool(true).u64(packed as u64),
+            SOption::None => this.bool(false),
+        };
+        match repr.align {
+            SOption::Some(align) => this.bool(true).u64(align as u64),
+            SOption::None => this.bool(false),
+        }
+    }
+    pub const fn flags(self, flags: &TypeFlags) -> Self {
+        self.bool(flags.send)
+            .bool(flags.sync)
+            .bool(flags.copy)
+            .bool(flags.needs_drop)
+    }
+    pub const fn known(self, known: &SOption<KnownType>) -> Self {
+        match known {
+            SOption::Some(known) => self.bool(true).u64(*known as u64),
+            SOption::None => self.bool(false),
+        }
+    }
+    pub const fn finish(self) -> u128 {
+        self.0
+    }
+}
+
+impl Default for Fingerprinter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        fingerprint,
+        layout::{Discriminant, EnumLayout, Repr, ReprPrimitive, TypeType},
+        types::{SBox, SOption, SVec},
+        TypeInfo, TypeLayout,
+    };
+
+    #[test]
+    fn reprs() {
+        mod a {
+            #[repr(C, align(8))]
+            #[derive(crate::TypeInfo)]
+            pub struct Aligned(pub u8);
+
+            #[repr(C, u8)]
+            #[derive(crate::TypeInfo)]
+            pub enum Tagged {
+                A(u32),
+            }
+        }
+        mod b {
+            #[repr(C, packed(2))]
+            #[derive(crate::TypeInfo)]
+            pub struct Aligned(pub u8);
+
+            #[repr(u8)]
+            #[derive(crate::TypeInfo)]
+            pub enum Tagged {
+                A(u32),
+            }
+        }
+
+        fn repr<T: TypeInfo>() -> Repr {
+            T::layout().defined_type().repr.clone()
+        }
+
+        assert_eq!(
+            repr::<a::Aligned>(),
+            Repr {
+                c: true,
+                align: SOption::Some(8),
+                ..Repr::RUST
+            }
+        );
+        assert_eq!(
+            repr::<b::Aligned>(),
+            Repr {
+                c: true,
+                packed: SOption::Some(2),
+                ..Repr::RUST
+            }
+        );
+        assert_eq!(
+            repr::<a::Tagged>(),
+            Repr {
+                c: true,
+                primitive: SOption::Some(ReprPrimitive::U8),
+                ..Repr::RUST
+            }
+        );
+        assert_eq!(repr::<std::mem::ManuallyDrop<u8>>(), Repr::TRANSPARENT);
+        let (a::Tagged::A(x), b::Tagged::A(y)) = (a::Tagged::A(1), b::Tagged::A(1));
+        assert_eq!(x, y);
+
+        assert!(!a::Aligned::layout().is_compatible_with(&b::Aligned::layout()));
+        assert!(!a::Tagged::layout().is_compatible_with(&b::Tagged::layout()));
+        const _: () = assert!(fingerprint::<a::Aligned>() != fingerprint::<b::Aligned>());
+        const _: () = assert!(fingerprint::<a::Tagged>() != fingerprint::<b::Tagged>());
+    }
+
+    #[test]
+    fn enum_layouts() {
+        #[allow(dead_code)]
+        #[repr(u8)]
+        #[derive(crate::TypeInfo)]
+        enum Inline {
+            A(u8, u32),
+            B(u64),
+        }
+
+        #[allow(dead_code)]
+        #[repr(C, u8)]
+        #[derive(crate::TypeInfo)]
+        enum Separate {
+            A(u8, u32),
+            B(u64),
+        }
+
+        fn enum_layout<T: TypeInfo>() -> (EnumLayout, Vec<usize>) {
+            let layout = T::layout();
+            let TypeType::Enum { variants, layout } = &layout.defined_type().ty else {
+                panic!("expected an enum");
+            };
+            (
+                layout.clone(),
+                variants.iter().map(|v| v.payload_offset).collect(),
+            )
+        }
+
+        fn offset<T, F>(value: &T, field: &F) -> usize {
+            field as *const F as usize - value as *const T as usize
+        }
+
+        let (a, b) = (Inline::A(1, 2), Inline::B(3));
+        let (Inline::A(a_field, _), Inline::B(b_field)) = (&a, &b) else {
+            unreachable!()
+        };
+        assert_eq!(
+            enum_layout::<Inline>(),
+            (
+                EnumLayout::Inline { tag_size: 1 },
+                vec![offset(&a, a_field), offset(&b, b_field)]
+            )
+        );
+
+        let (a, b) = (Separate::A(1, 2), Separate::B(3));
+        let (Separate::A(a_field, _), Separate::B(b_field)) = (&a, &b) else {
+            unreachable!()
+        };
+        assert_eq!(
+            enum_layout::<Separate>(),
+            (
+                EnumLayout::Separate { tag_size: 1 },
+                vec![offset(&a, a_field), offset(&b, b_field)]
+            )
+        );
+    }
+
+    #[test]
+    fn wide_discriminants() {
+        #[allow(dead_code)]
+        #[repr(u64)]
+        #[derive(crate::TypeInfo)]
+        enum Wide {
+            A = u64::MAX - 1,
+            B,
+        }
+
+        #[allow(dead_code)]
+        #[repr(i128)]
+        #[derive(crate::TypeInfo)]
+        enum Huge {
+            A = -1,
+            B(u8) = i128::MAX,
+        }
+
+        fn discriminants<T: TypeInfo>() -> Vec<Discriminant> {
+            let layout = T::layout();
+            let TypeType::Enum { variants, .. } = &layout.defined_type().ty else {
+                panic!("expected an enum");
+            };
+            variants.iter().map(|v| v.discriminant).collect()
+        }
+
+        assert_eq!(
+            discriminants::<Wide>(),
+            [
+                Discriminant::Unsigned(u64::MAX as u128 - 1),
+                Discriminant::Unsigned(u64::MAX as u128)
+            ]
+        );
+        assert_eq!(
+            discriminants::<Huge>(),
+            [Discriminant::Signed(-1), Discriminant::Signed(i128::MAX)]
+        );
+    }
+
+    #[test]
+    fn type_flags() {
+        use crate::layout::TypeFlags;
+        use std::{cell::Cell, marker::PhantomData};
+
+        mod v1 {
+            use crate::TypeInfo;
+
+            #[repr(C)]
+            #[derive(TypeInfo, Clone, Copy)]
+            pub struct Handle {
+                pub id: u32,
+            }
+        }
+
+        mod v2 {
+            use crate::TypeInfo;
+
+            #[repr(C)]
+            #[derive(TypeInfo)]
+            pub struct Handle {
+                pub id: u32,
+            }
+
+            impl Drop for Handle {
+                fn drop(&mut self) {}
+            }
+        }
+
+        #[repr(C)]
+        #[derive(TypeInfo)]
+        struct NotSync {
+            id: u32,
+            _phantom: PhantomData<Cell<u8>>,
+        }
+
+        #[repr(C)]
+        #[derive(TypeInfo, Clone, Copy)]
+        #[ironties(send, sync, copy)]
+        struct Pair<T> {
+            a: T,
+            b: T,
+        }
+
+        // The flags of generic types depend on their type parameters
+        #[repr(C)]
+        #[derive(TypeInfo, Clone, Copy)]
+        struct Undeclared<T> {
+            a: T,
+        }
+
+        let flags = |layout: TypeLayout| layout.defined_type().flags;
+
+        assert_eq!(
+            flags(v1::Handle::layout()),
+            TypeFlags {
+                send: true,
+                sync: true,
+                copy: true,
+                needs_drop: false,
+            }
+        );
+        assert_eq!(
+            flags(v2::Handle::layout()),
+            TypeFlags {
+                send: true,
+                sync: true,
+                copy: false,
+                needs_drop: true,
+            }
+        );
+        assert_eq!(
+            flags(NotSync::layout()),
+            TypeFlags {
+                send: true,
+                sync: false,
+                copy: false,
+                needs_drop: false,
+            }
+        );
+        assert!(flags(<SBox<u8>>::layout()).needs_drop);
+        assert_eq!(
+            flags(<Pair<u32>>::layout()),
+            TypeFlags {
+                send: true,
+                sync: true,
+                copy: true,
+                needs_drop: false,
+            }
+        );
+        assert_eq!(
+            flags(<Undeclared<u32>>::layout()),
+            TypeFlags {
+                send: true,
+                sync: true,
+                copy: true,
+                needs_drop: false,
+            }
+        );
+        assert_eq!(
+            flags(<Undeclared<*const u32>>::layout()),
+            TypeFlags {
+                send: false,
+                sync: false,
+                copy: true,
+                needs_drop: false,
+            }
+        );
+        assert_eq!(
+            flags(<SVec<u8>>::layout()),
+            TypeFlags {
+                send: true,
+                sync: true,
+                copy: false,
+                needs_drop: true,
+            }
+        );
+        assert!(flags(<PhantomData<u8>>::layout()).send);
+        assert!(!flags(<PhantomData<Cell<u8>>>::layout()).sync);
+        assert!(!flags(<Cell<u8>>::layout()).sync);
+
+        assert!(!v1::Handle::layout().is_compatible_with(&v2::Handle::layout()));
+        assert!(!v2::Handle::layout().extends(&v1::Handle::layout()));
+        const _: () = assert!(fingerprint::<v1::Handle>() != fingerprint::<v2::Handle>());
+    }
+}
@@ -1,16 +1,37 @@
 #![feature(allocator_api)]
+#![feature(const_type_name)]
+#![feature(specialization)]
+#![allow(incomplete_features)]
 
 extern crate self as ironties;
 
+mod compat;
+pub mod dynamic;
+mod encode;
+#[cfg(feature = "mmap")]
+pub mod file;
+mod header;
 #[doc(hidden)]
 pub mod layout;
 mod other_impls;
 mod primitive_impls;
+#[cfg(all(unix, feature = "shm"))]
+pub mod shm;
+#[cfg(unix)]
+pub mod socket;
 pub mod types;
 
+pub use encode::{DecodeError, MAX_INTERNED_LEN};
+pub use header::{Endianness, LayoutHeader, LAYOUT_FORMAT_VERSION};
 pub use ironties_derive::TypeInfo;
+pub use other_impls::NicheOptimized;
 
-use layout::{DefinedType, DefinedTypes, FullLayout, Layout, TypeUid};
+use layout::{DefinedType, DefinedTypes, Fingerprinter, FullLayout, Layout, TypeUid};
+use std::{
+    any::type_name,
+    collections::HashMap,
+    sync::{OnceLock, RwLock},
+};
 use types::SVec;
 
 /// Implementation detail. Use the [`TypeInfo`] trait.
@@ -19,6 +40,16 @@ pub unsafe trait _TypeInfoImpl {
     #[doc(hidden)]
     const _UID: TypeUid;
 
+    /// Structural fingerprint of the type, see [`fingerprint`]
+    #[doc(hidden)]
+    const _FINGERPRINT: u128;
+
+    /// Fingerprint which only identifies the type by its name and generic parameters,
+    /// without going into its contents. Used for types behind pointers and in function signatures,
+    /// since recursive types would otherwise make the fingerprint infinite.
+    #[doc(hidden)]
+    const _SHALLOW_FINGERPRINT: u128 = Self::_FINGERPRINT;
+
     #[doc(hidden)]
     fn _layout_impl(defined_types: DefinedTypes) -> FullLayout;
 }
@@ -31,36 +62,159 @@ pub unsafe trait _TypeInfoImpl {
 ///
 /// Use the [`TypeInfo::layout`] method on any type which implements [`TypeInfo`] to get it's [`TypeLayout`]
 ///
+/// Layouts produced for different targets (see [`LayoutHeader`]) never compare equal,
+/// even if their types are the same. The versions of the compilers that produced them are not compared.
+///
 /// # Limitations
 ///
 /// As of yet, [`TypeLayout`] does not encode lifetime information
 #[repr(C)]
 #[derive(TypeInfo, Debug, PartialEq, Clone)]
 pub struct TypeLayout {
+    header: LayoutHeader,
     defined_types: SVec<DefinedType>,
     layout: Layout,
 }
 
+impl TypeLayout {
+    /// Information about the target and compiler that this layout was produced for
+    pub fn header(&self) -> &LayoutHeader {
+        &self.header
+    }
+}
+
+#[cfg(test)]
+impl TypeLayout {
+    /// The defined type that the layout is of
+    pub(crate) fn defined_type(&self) -> &DefinedType {
+        let Layout::DefinedType { id } = self.layout else {
+            panic!("expected a defined type");
+        };
+        &self.defined_types[id]
+    }
+}
+
 /// Allows to construct a [`TypeLayout`] of the type
+///
+/// Deriving requires the type to have a stable layout, so `#[repr(C)]` (or `#[repr(transparent)]`) can't be left out:
+///
+/// ```compile_fail
+/// # use ironties::TypeInfo;
+/// #[derive(TypeInfo)]
+/// struct Point {
+///     x: u32,
+///     y: u32,
+/// }
+/// ```
+///
+/// All fields must implement `TypeInfo` too, so standard library types have to be replaced with
+/// their FFI-safe equivalents from [`types`]:
+///
+/// ```compile_fail
+/// # use ironties::TypeInfo;
+/// #[repr(C)]
+/// #[derive(TypeInfo)]
+/// struct Message {
+///     text: String,
+/// }
+/// ```
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` does not have an FFI-safe layout",
+    label = "`{Self}` does not implement `TypeInfo`",
+    note = "use the FFI-safe equivalents from `ironties::types` instead of standard library types, \
+        for example `SVec<T>` instead of `Vec<T>`, `SBox<T>` instead of `Box<T>`, `SString` instead of `String`, \
+        `SStr` instead of `&str` and `SOption<T>` instead of `Option<T>`",
+    note = "your own types can implement `TypeInfo` with `#[derive(TypeInfo)]`"
+)]
 pub trait TypeInfo: _TypeInfoImpl {
+    /// Returns the [`TypeLayout`] of the type.
+    ///
+    /// The layout is only built on the first call, see [`TypeInfo::layout_ref`].
     fn layout() -> TypeLayout {
-        let layout_impl = <Self as _TypeInfoImpl>::_layout_impl(Vec::new());
-
-        TypeLayout {
-            layout: layout_impl.layout,
-            defined_types: SVec::from_vec(
-                layout_impl
-                    .defined_types
-                    .into_iter()
-                    .map(|(_uid, ty)| ty)
-                    .collect(),
-            ),
+        Self::layout_ref().clone()
+    }
+    /// Returns a reference to the [`TypeLayout`] of the type.
+    ///
+    /// The layout is built on the first call and cached for the rest of the program,
+    /// so all subsequent calls are cheap.
+    fn layout_ref() -> &'static TypeLayout {
+        /// Only needed because `TypeLayout` contains raw pointers. It is never mutated after being cached.
+        struct CachedLayout(&'static TypeLayout);
+        unsafe impl Send for CachedLayout {}
+        unsafe impl Sync for CachedLayout {}
+
+        static LAYOUTS: OnceLock<RwLock<HashMap<(TypeUid, &'static str), CachedLayout>>> =
+            OnceLock::new();
+
+        let key = (Self::_UID, type_name::<Self>());
+        let layouts = LAYOUTS.get_or_init(Default::default);
+
+        if let Some(layout) = layouts.read().unwrap().get(&key) {
+            return layout.0;
         }
+
+        let FullLayout {
+            layout,
+            defined_types,
+        } = Self::_layout_impl(DefinedTypes::new());
+        let layout = TypeLayout {
+            header: LayoutHeader::current(),
+            layout,
+            defined_types: SVec::from_vec(defined_types.into_vec()),
+        };
+
+        layouts
+            .write()
+            .unwrap()
+            .entry(key)
+            .or_insert_with(|| CachedLayout(Box::leak(Box::new(layout))))
+            .0
     }
 }
 
 impl<T: _TypeInfoImpl> TypeInfo for T {}
 
+/// Implemented by mirrors of types from other crates, see `#[ironties(remote = "..")]` on [`TypeInfo`][derive@TypeInfo]
+///
+/// # Safety
+///
+/// The layout of `Self` must be the same as the layout of `R`.
+pub unsafe trait RemoteTypeInfo<R>: TypeInfo {}
+
+/// Returns a 128-bit structural fingerprint of the type, which can be computed and compared at compile time
+///
+/// Two types have the same fingerprint if their layouts would be equal, and the layouts are of the same target
+/// (see [`LayoutHeader::same_target`]):
+///
+/// ```
+/// # use ironties::{TypeInfo, fingerprint};
+/// #[repr(C)]
+/// #[derive(TypeInfo)]
+/// struct Point {
+///     x: f32,
+///     y: f32,
+/// }
+///
+/// const _: () = assert!(fingerprint::<Point>() != fingerprint::<[f32; 2]>());
+/// const _: () = assert!(fingerprint::<Point>() == fingerprint::<Point>());
+/// ```
+///
+/// # Limitations
+///
+/// Types behind pointers and in function pointer signatures are only identified by their names and generic parameters,
+/// so changes inside of them do not change the fingerprint. Use [`TypeInfo::layout`] for a full comparison.
+pub const fn fingerprint<T: TypeInfo>() -> u128 {
+    let header = LayoutHeader::current();
+
+    Fingerprinter::new()
+        .u64(header.format_version as u64)
+        .u64(header.pointer_width as u64)
+        .u64(header.endianness as u64)
+        .str(env!("IRONTIES_TARGET"))
+        .u128(T::_FINGERPRINT)
+        .finish()
+}
+
 #[rustfmt::skip]
 macro_rules! id {
     ($($name:tt)+) => {
@@ -76,7 +230,12 @@ pub(crate) use id;
 
 #[cfg(test)]
 mod tests {
-    use crate::{TypeInfo, TypeLayout};
+    use crate::{
+        fingerprint,
+        layout::{Layout, TypeType},
+        types::{SStr, SVec},
+        TypeInfo, TypeLayout,
+    };
 
     #[test]
     fn type_layout_ffi_safe() {
@@ -88,4 +247,167 @@ mod tests {
     fn type_layout_self_eq_test() {
         assert_eq!(TypeLayout::layout(), TypeLayout::layout());
     }
+
+    #[test]
+    fn fingerprints() {
+        mod a {
+            #[repr(C)]
+            #[derive(crate::TypeInfo)]
+            pub struct Node {
+                pub value: u32,
+                pub next: *const Node,
+            }
+        }
+        mod b {
+            #[repr(C)]
+            #[derive(crate::TypeInfo)]
+            pub struct Node {
+                pub value: u32,
+                pub next: *const Node,
+            }
+        }
+        mod c {
+            #[repr(C)]
+            #[derive(crate::TypeInfo)]
+            pub struct Node {
+                pub value: u64,
+                pub next: *const Node,
+            }
+        }
+
+        const _: () = assert!(fingerprint::<a::Node>() == fingerprint::<b::Node>());
+        const _: () = assert!(fingerprint::<a::Node>() != fingerprint::<c::Node>());
+        const _: () = assert!(fingerprint::<TypeLayout>() == fingerprint::<TypeLayout>());
+        const _: () = assert!(fingerprint::<u32>() != fingerprint::<i32>());
+        const _: () = assert!(fingerprint::<[u8; 2]>() != fingerprint::<[u8; 3]>());
+    }
+
+    #[test]
+    fn cached_layouts() {
+        #[repr(C)]
+        #[derive(crate::TypeInfo)]
+        struct Generics {
+            a: SVec<u8>,
+            b: SVec<u16>,
+            c: SVec<u8>,
+        }
+
+        assert!(std::ptr::eq(Generics::layout_ref(), Generics::layout_ref()));
+        assert_eq!(*Generics::layout_ref(), Generics::layout());
+
+        let layout = Generics::layout();
+        let TypeType::StructNamed { fields } = &layout.defined_type().ty else {
+            panic!("expected a struct with named fields");
+        };
+        assert_ne!(fields[0].layout, fields[1].layout);
+        assert_eq!(fields[0].layout, fields[2].layout);
+    }
+
+    #[test]
+    fn attributes() {
+        mod a {
+            #[repr(C)]
+            #[derive(crate::TypeInfo)]
+            #[ironties(rename = "Point", crate = "crate")]
+            pub struct OldPoint {
+                pub x: u32,
+            }
+
+            pub trait Handle {
+                type Raw;
+            }
+
+            #[repr(C)]
+            #[derive(crate::TypeInfo)]
+            #[ironties(bound = "T::Raw: crate::TypeInfo")]
+            pub struct Handles<T: Handle> {
+                pub raw: T::Raw,
+            }
+
+            pub struct File;
+            impl Handle for File {
+                type Raw = u32;
+            }
+            pub struct Socket;
+            impl Handle for Socket {
+                type Raw = u64;
+            }
+        }
+        mod b {
+            #[repr(C)]
+            #[derive(crate::TypeInfo)]
+            pub struct Point {
+                pub x: u32,
+            }
+
+            #[allow(dead_code)]
+            #[derive(crate::TypeInfo)]
+            #[ironties(opaque)]
+            pub struct Opaque {
+                pub name: String,
+            }
+        }
+
+        assert!(a::OldPoint::layout().is_compatible_with(&b::Point::layout()));
+        const _: () = assert!(fingerprint::<a::OldPoint>() == fingerprint::<b::Point>());
+
+        let layout = <a::Handles<a::File>>::layout();
+        let TypeType::StructNamed { fields } = &layout.defined_type().ty else {
+            panic!("expected a struct with named fields");
+        };
+        assert_eq!(fields[0].layout, Layout::U32);
+        // The type parameters don't implement `TypeInfo`, but still tell pointers apart
+        const _: () = assert!(
+            fingerprint::<*const a::Handles<a::File>>()
+                != fingerprint::<*const a::Handles<a::Socket>>()
+        );
+
+        let layout = b::Opaque::layout();
+        let ty = layout.defined_type();
+        assert_eq!(ty.ty, TypeType::Opaque);
+        assert_eq!(
+            (ty.size, ty.align),
+            (size_of::<String>(), align_of::<String>())
+        );
+    }
+
+    #[test]
+    fn remote() {
+        mod foreign {
+            #[repr(C)]
+            pub struct Timespec {
+                pub tv_sec: i64,
+                pub tv_nsec: i64,
+            }
+        }
+
+        #[allow(dead_code)]
+        #[repr(C)]
+        #[derive(crate::TypeInfo)]
+        #[ironties(remote = "foreign::Timespec")]
+        struct TimespecDef {
+            tv_sec: i64,
+            tv_nsec: i64,
+        }
+
+        #[repr(C)]
+        #[derive(crate::TypeInfo)]
+        struct Event {
+            #[ironties(with = "TimespecDef")]
+            time: foreign::Timespec,
+        }
+
+        let layout = Event::layout();
+        let TypeType::StructNamed { fields } = &layout.defined_type().ty else {
+            panic!("expected a struct with named fields");
+        };
+        let Layout::DefinedType { id } = fields[0].layout else {
+            panic!("expected a defined type");
+        };
+        assert_eq!(layout.defined_types[id].name, SStr::new("Timespec"));
+        assert_eq!(
+            *TimespecDef::layout().defined_type(),
+            layout.defined_types[id]
+        );
+    }
 }
@@ -1,48 +1,85 @@
 use crate::{
-    layout::{DefinedType, DefinedTypes, FullLayout, Layout, TypeType},
-    types::{FfiSafeEquivalent, SStr, SVec},
-    TypeUid, _TypeInfoImpl, id,
+    _TypeInfoImpl, id,
+    layout::{
+        DefinedType, DefinedTypes, Fingerprinter, FullLayout, KnownType, Layout, NamedField, Repr,
+        ReprPrimitive, TypeFlags, TypeType, UnnamedField,
+    },
+    types::{FfiSafeEquivalent, SBox, SOption, SStr, SVec},
+    TypeInfo, TypeUid,
+};
+use std::{
+    mem::{ManuallyDrop, MaybeUninit},
+    num::{
+        NonZeroI128, NonZeroI16, NonZeroI32, NonZeroI64, NonZeroI8, NonZeroIsize, NonZeroU128,
+        NonZeroU16, NonZeroU32, NonZeroU64, NonZeroU8, NonZeroUsize,
+    },
+    ptr::NonNull,
 };
 
 trait All {}
 impl<T: ?Sized> All for T {}
 
+/// For `#[repr(transparent)]` wrappers of their generic parameters
 macro_rules! impl_opaque {
     ($path:path where $($generic:ident [$($bound:tt),*]),* ) => {
+        impl_opaque! {$path where $($generic [$($bound),*]),* ; _FINGERPRINT, |layout| layout, SOption::None}
+    };
+    // For `NonNull`, which only points to its generic parameter
+    ($path:path where $($generic:ident [$($bound:tt),*]),* ; indirect) => {
+        impl_opaque! {$path where $($generic [$($bound),*]),* ; _SHALLOW_FINGERPRINT, |layout| Layout::MutPtr(SBox::new(layout)), KnownType::NonNull.mark()}
+    };
+    ($path:path where $($generic:ident [$($bound:tt),*]),* ; $fingerprint:ident, $field:expr, $known:expr) => {
         unsafe impl<$($generic : $($bound +)* All),*> _TypeInfoImpl for $path {
             const _UID: TypeUid = id!($path);
+            const _FINGERPRINT: u128 = Fingerprinter::new()
+                .str("StructUnnamed")
+                .str(stringify!($path))
+                .repr(&Repr::TRANSPARENT)
+                .flags(&TypeFlags::of::<Self>())
+                .known(&$known)
+                $(.u128($generic::$fingerprint))*
+                .finish();
+            const _SHALLOW_FINGERPRINT: u128 = Fingerprinter::new()
+                .str("DefinedType")
+                .str(stringify!($path))
+                $(.u128($generic::_SHALLOW_FINGERPRINT))*
+                .finish();
 
             fn _layout_impl(defined_types: DefinedTypes) -> FullLayout {
-                let mut layouts = Vec::new();
+                let mut fields = Vec::new();
 
                 $(
                     let FullLayout {
                         layout,
                         mut defined_types,
                     } = $generic::_layout_impl(defined_types);
-                    layouts.push(layout);
+                    // Transparent wrappers only have one non-zero-sized field, at the start
+                    fields.push(UnnamedField {
+                        layout: ($field)(layout),
+                        offset: 0,
+                    });
                 )*
 
-                match defined_types.iter().position(|t| t.0 == Self::_UID) {
+                match defined_types.id_of::<Self>() {
                     Some(pos) => FullLayout {
                         layout: Layout::DefinedType { id: pos },
                         defined_types,
                     },
                     None => {
-                        defined_types.push((
-                            Self::_UID,
-                            DefinedType {
-                                name: SStr::from_normal(stringify!($path)),
-                                ty: TypeType::StructUnnamed {
-                                    fields: SVec::from_vec(layouts),
-                                },
+                        let my_type_id = defined_types.define::<Self>(DefinedType {
+                            name: SStr::from_normal(stringify!($path)),
+                            repr: Repr::TRANSPARENT,
+                            flags: TypeFlags::of::<Self>(),
+                            known: $known,
+                            size: size_of::<Self>(),
+                            align: align_of::<Self>(),
+                            ty: TypeType::StructUnnamed {
+                                fields: SVec::from_vec(fields),
                             },
-                        ));
+                        });
 
                         FullLayout {
-                            layout: Layout::DefinedType {
-                                id: defined_types.len() - 1,
-                            },
+                            layout: Layout::DefinedType { id: my_type_id },
                             defined_types,
                         }
                     }
@@ -52,27 +89,212 @@ macro_rules! impl_opaque {
     };
 }
 
-impl_opaque! {::std::mem::ManuallyDrop<T> where T [_TypeInfoImpl]}
-impl_opaque! {::std::ptr::NonNull<T> where T [_TypeInfoImpl]}
+impl_opaque! {::std::mem::ManuallyDrop<T> where T [TypeInfo]}
+impl_opaque! {::std::ptr::NonNull<T> where T [TypeInfo]; indirect}
+impl_opaque! {::std::cell::Cell<T> where T [TypeInfo]}
+impl_opaque! {::std::cell::UnsafeCell<T> where T [TypeInfo]}
+impl_opaque! {::std::num::Wrapping<T> where T [TypeInfo]}
+impl_opaque! {::std::num::Saturating<T> where T [TypeInfo]}
+impl_opaque! {::std::pin::Pin<P> where P [TypeInfo]}
+
+/// Types which can't be zero, so that `Option<T>` is guaranteed to have the same layout as `T`,
+/// with `None` represented as zero.
+///
+/// # Safety
+///
+/// The type must be eligible for the [null pointer optimization](https://doc.rust-lang.org/std/option/index.html#representation).
+#[diagnostic::on_unimplemented(
+    message = "`Option<{Self}>` does not have a stable layout",
+    label = "`Option<{Self}>` does not implement `TypeInfo`",
+    note = "use `ironties::types::SOption` instead"
+)]
+pub unsafe trait NicheOptimized {}
+
+unsafe impl<T: ?Sized> NicheOptimized for &T {}
+unsafe impl<T: ?Sized> NicheOptimized for &mut T {}
+unsafe impl<T: ?Sized> NicheOptimized for NonNull<T> {}
+
+macro_rules! niche_optimized {
+    ($($ty:ty),*) => {
+        $( unsafe impl NicheOptimized for $ty {} )*
+    };
+}
+niche_optimized!(
+    NonZeroU8,
+    NonZeroU16,
+    NonZeroU32,
+    NonZeroU64,
+    NonZeroU128,
+    NonZeroUsize,
+    NonZeroI8,
+    NonZeroI16,
+    NonZeroI32,
+    NonZeroI64,
+    NonZeroI128,
+    NonZeroIsize
+);
+
+unsafe impl<T: TypeInfo + NicheOptimized> _TypeInfoImpl for Option<T> {
+    const _UID: TypeUid = id!(Option<T>);
+    const _FINGERPRINT: u128 = Fingerprinter::new()
+        .str("NicheOption")
+        .u128(T::_FINGERPRINT)
+        .finish();
+    const _SHALLOW_FINGERPRINT: u128 = Fingerprinter::new()
+        .str("NicheOption")
+        .u128(T::_SHALLOW_FINGERPRINT)
+        .finish();
+
+    fn _layout_impl(defined_types: DefinedTypes) -> FullLayout {
+        let FullLayout {
+            layout,
+            defined_types,
+        } = T::_layout_impl(defined_types);
+
+        FullLayout {
+            layout: Layout::NicheOption(SBox::new(layout)),
+            defined_types,
+        }
+    }
+}
+
+/// `MaybeUninit<T>` is a `#[repr(transparent)]` union of `()` and `ManuallyDrop<T>`
+unsafe impl<T: TypeInfo> _TypeInfoImpl for MaybeUninit<T> {
+    const _UID: TypeUid = id!(::std::mem::MaybeUninit<T>);
+    const _FINGERPRINT: u128 = Fingerprinter::new()
+        .str("Union")
+        .str("::std::mem::MaybeUninit")
+        .repr(&Repr::TRANSPARENT)
+        .flags(&TypeFlags::of::<Self>())
+        .u64(2)
+        .str("uninit")
+        .u128(<()>::_FINGERPRINT)
+        .str("value")
+        .u128(<ManuallyDrop<T>>::_FINGERPRINT)
+        .finish();
+    const _SHALLOW_FINGERPRINT: u128 = Fingerprinter::new()
+        .str("DefinedType")
+        .str("::std::mem::MaybeUninit")
+        .u128(T::_SHALLOW_FINGERPRINT)
+        .finish();
+
+    fn _layout_impl(defined_types: DefinedTypes) -> FullLayout {
+        let FullLayout {
+            layout: uninit,
+            defined_types,
+        } = <()>::_layout_impl(defined_types);
+        let FullLayout {
+            layout: value,
+            mut defined_types,
+        } = <ManuallyDrop<T>>::_layout_impl(defined_types);
+
+        match defined_types.id_of::<Self>() {
+            Some(pos) => FullLayout {
+                layout: Layout::DefinedType { id: pos },
+                defined_types,
+            },
+            None => {
+                let my_type_id = defined_types.define::<Self>(DefinedType {
+                    name: SStr::from_normal("::std::mem::MaybeUninit"),
+                    repr: Repr::TRANSPARENT,
+                    flags: TypeFlags::of::<Self>(),
+                    known: SOption::None,
+                    size: size_of::<Self>(),
+                    align: align_of::<Self>(),
+                    ty: TypeType::Union {
+                        fields: SVec::from_vec(vec![
+                            NamedField {
+                                name: SStr::from_normal("uninit"),
+                                layout: uninit,
+                                offset: 0,
+                            },
+                            NamedField {
+                                name: SStr::from_normal("value"),
+                                layout: value,
+                                offset: 0,
+                            },
+                        ]),
+                    },
+                });
+
+                FullLayout {
+                    layout: Layout::DefinedType { id: my_type_id },
+                    defined_types,
+                }
+            }
+        }
+    }
+}
+
+/// `c_void` is a `#[repr(u8)]` enum with hidden variants, which is only meant to be used behind pointers.
+/// Unlike `()`, it takes up a byte, so it's recorded as an opaque type.
+unsafe impl _TypeInfoImpl for ::std::ffi::c_void {
+    const _UID: TypeUid = id!(::std::ffi::c_void);
+    const _FINGERPRINT: u128 = Fingerprinter::new()
+        .str("Opaque")
+        .str("::std::ffi::c_void")
+        .repr(&C_VOID_REPR)
+        .flags(&TypeFlags::of::<Self>())
+        .u64(size_of::<Self>() as u64)
+        .u64(align_of::<Self>() as u64)
+        .finish();
+
+    fn _layout_impl(mut defined_types: DefinedTypes) -> FullLayout {
+        match defined_types.id_of::<Self>() {
+            Some(pos) => FullLayout {
+                layout: Layout::DefinedType { id: pos },
+                defined_types,
+            },
+            None => {
+                let my_type_id = defined_types.define::<Self>(DefinedType {
+                    name: SStr::from_normal("::std::ffi::c_void"),
+                    repr: C_VOID_REPR,
+                    flags: TypeFlags::of::<Self>(),
+                    known: SOption::None,
+                    size: size_of::<Self>(),
+                    align: align_of::<Self>(),
+                    ty: TypeType::Opaque,
+                });
+
+                FullLayout {
+                    layout: Layout::DefinedType { id: my_type_id },
+                    defined_types,
+                }
+            }
+        }
+    }
+}
+
+const C_VOID_REPR: Repr = Repr {
+    primitive: SOption::Some(ReprPrimitive::U8),
+    ..Repr::RUST
+};
 
 unsafe impl<T: ?Sized> _TypeInfoImpl for std::marker::PhantomData<T> {
     const _UID: TypeUid = id!(::std::marker::PhantomData);
+    const _FINGERPRINT: u128 = Fingerprinter::new()
+        .str("StructUnit")
+        .str("::std::marker::PhantomData")
+        .repr(&Repr::RUST)
+        .flags(&TypeFlags::of::<Self>())
+        .finish();
 
     fn _layout_impl(mut defined_types: DefinedTypes) -> FullLayout {
-        match defined_types.iter().position(|t| t.0 == Self::_UID) {
+        match defined_types.id_of::<Self>() {
             Some(pos) => FullLayout {
                 layout: Layout::DefinedType { id: pos },
                 defined_types,
             },
             None => {
-                defined_types.push((
-                    Self::_UID,
-                    DefinedType {
-                        name: SStr::from_normal("::std::marker::PhantomData"),
-                        ty: TypeType::StructUnit,
-                    },
-                ));
-                let my_type_id = defined_types.len() - 1;
+                let my_type_id = defined_types.define::<Self>(DefinedType {
+                    name: SStr::from_normal("::std::marker::PhantomData"),
+                    repr: Repr::RUST,
+                    flags: TypeFlags::of::<Self>(),
+                    known: SOption::None,
+                    size: size_of::<Self>(),
+                    align: align_of::<Self>(),
+                    ty: TypeType::StructUnit,
+                });
 
                 FullLayout {
                     layout: Layout::DefinedType { id: my_type_id },
@@ -82,3 +304,61 @@ unsafe impl<T: ?Sized> _TypeInfoImpl for std::marker::PhantomData<T> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        fingerprint,
+        layout::{Layout, Repr, TypeType},
+        types::SBox,
+        TypeInfo,
+    };
+
+    #[test]
+    fn std_types() {
+        use std::{
+            cell::Cell,
+            ffi::c_void,
+            mem::MaybeUninit,
+            num::NonZeroU32,
+            sync::atomic::{AtomicPtr, AtomicU32},
+        };
+
+        let nonzero = Layout::NonZero(SBox::new(Layout::U32));
+        assert_eq!(
+            AtomicU32::layout().layout,
+            Layout::Atomic(SBox::new(Layout::U32))
+        );
+        assert_eq!(NonZeroU32::layout().layout, nonzero);
+        assert_eq!(
+            <Option<NonZeroU32>>::layout().layout,
+            Layout::NicheOption(SBox::new(nonzero))
+        );
+        assert_eq!(
+            <AtomicPtr<u8>>::layout().layout,
+            Layout::Atomic(SBox::new(Layout::MutPtr(SBox::new(Layout::U8))))
+        );
+        assert_eq!(
+            <Option<&u8>>::layout().layout,
+            Layout::NicheOption(SBox::new(Layout::Ref(SBox::new(Layout::U8))))
+        );
+        assert!(matches!(
+            <Option<extern "C" fn()>>::layout().layout,
+            Layout::NicheOption(_)
+        ));
+
+        let layout = <MaybeUninit<Cell<u32>>>::layout();
+        assert_eq!(layout.defined_type().repr, Repr::TRANSPARENT);
+        assert!(matches!(layout.defined_type().ty, TypeType::Union { .. }));
+
+        // Unlike `()`, `c_void` takes up a byte
+        let layout = c_void::layout();
+        let ty = layout.defined_type();
+        assert_eq!((&ty.ty, ty.size, ty.align), (&TypeType::Opaque, 1, 1));
+        assert!(!<*const c_void>::layout().is_compatible_with(&<*const ()>::layout()));
+
+        const _: () = assert!(fingerprint::<AtomicU32>() != fingerprint::<u32>());
+        const _: () = assert!(fingerprint::<NonZeroU32>() != fingerprint::<u32>());
+        const _: () = assert!(fingerprint::<Option<NonZeroU32>>() != fingerprint::<NonZeroU32>());
+    }
+}
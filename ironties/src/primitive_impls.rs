@@ -1,9 +1,9 @@
 use crate::{
-    layout::{DefinedTypes, FullLayout, Layout},
+    _TypeInfoImpl, id,
+    layout::{DefinedTypes, Fingerprinter, FullLayout, Layout},
     types::SBox,
-    TypeUid, _TypeInfoImpl, id,
+    TypeInfo, TypeUid,
 };
-
 mod functions;
 
 macro_rules! impl_primitives {
@@ -11,6 +11,7 @@ macro_rules! impl_primitives {
     	$(
     		unsafe impl $crate::_TypeInfoImpl for $name {
                 const _UID: TypeUid = id!($name);
+                const _FINGERPRINT: u128 = Fingerprinter::new().str(stringify!($layout)).finish();
 
                 fn _layout_impl(defined_types: DefinedTypes) -> FullLayout {
                     FullLayout {
@@ -27,11 +28,13 @@ impl_primitives!(
     u16 = U16,
     u32 = U32,
     u64 = U64,
+    u128 = U128,
     usize = USize,
     i8 = I8,
     i16 = I16,
     i32 = I32,
     i64 = I64,
+    i128 = I128,
     isize = ISize,
     f32 = F32,
     f64 = F64,
@@ -40,8 +43,100 @@ impl_primitives!(
     () = Void
 );
 
-unsafe impl<T: _TypeInfoImpl> _TypeInfoImpl for *const T {
+/// For types that wrap a primitive and have the same size
+macro_rules! impl_wrapped_primitives {
+    ( $variant:ident: $( $name:ty = $inner:ty),* ) => {
+        $(
+            unsafe impl _TypeInfoImpl for $name {
+                const _UID: TypeUid = id!($name);
+                const _FINGERPRINT: u128 = Fingerprinter::new()
+                    .str(stringify!($variant))
+                    .u128(<$inner>::_FINGERPRINT)
+                    .finish();
+
+                fn _layout_impl(defined_types: DefinedTypes) -> FullLayout {
+                    let FullLayout {
+                        layout,
+                        defined_types,
+                    } = <$inner>::_layout_impl(defined_types);
+
+                    FullLayout {
+                        layout: Layout::$variant(SBox::new(layout)),
+                        defined_types,
+                    }
+                }
+            }
+        )*
+    };
+}
+impl_wrapped_primitives!(NonZero:
+    ::std::num::NonZeroU8 = u8,
+    ::std::num::NonZeroU16 = u16,
+    ::std::num::NonZeroU32 = u32,
+    ::std::num::NonZeroU64 = u64,
+    ::std::num::NonZeroU128 = u128,
+    ::std::num::NonZeroUsize = usize,
+    ::std::num::NonZeroI8 = i8,
+    ::std::num::NonZeroI16 = i16,
+    ::std::num::NonZeroI32 = i32,
+    ::std::num::NonZeroI64 = i64,
+    ::std::num::NonZeroI128 = i128,
+    ::std::num::NonZeroIsize = isize
+);
+#[cfg(target_has_atomic = "8")]
+impl_wrapped_primitives!(Atomic:
+    ::std::sync::atomic::AtomicU8 = u8,
+    ::std::sync::atomic::AtomicI8 = i8,
+    ::std::sync::atomic::AtomicBool = bool
+);
+#[cfg(target_has_atomic = "16")]
+impl_wrapped_primitives!(Atomic:
+    ::std::sync::atomic::AtomicU16 = u16,
+    ::std::sync::atomic::AtomicI16 = i16
+);
+#[cfg(target_has_atomic = "32")]
+impl_wrapped_primitives!(Atomic:
+    ::std::sync::atomic::AtomicU32 = u32,
+    ::std::sync::atomic::AtomicI32 = i32
+);
+#[cfg(target_has_atomic = "64")]
+impl_wrapped_primitives!(Atomic:
+    ::std::sync::atomic::AtomicU64 = u64,
+    ::std::sync::atomic::AtomicI64 = i64
+);
+#[cfg(target_has_atomic = "ptr")]
+impl_wrapped_primitives!(Atomic:
+    ::std::sync::atomic::AtomicUsize = usize,
+    ::std::sync::atomic::AtomicIsize = isize
+);
+
+#[cfg(target_has_atomic = "ptr")]
+unsafe impl<T: TypeInfo> _TypeInfoImpl for ::std::sync::atomic::AtomicPtr<T> {
+    const _UID: TypeUid = id!(::std::sync::atomic::AtomicPtr<T>);
+    const _FINGERPRINT: u128 = Fingerprinter::new()
+        .str("Atomic")
+        .u128(<*mut T>::_FINGERPRINT)
+        .finish();
+
+    fn _layout_impl(defined_types: DefinedTypes) -> FullLayout {
+        let FullLayout {
+            layout,
+            defined_types,
+        } = <*mut T>::_layout_impl(defined_types);
+
+        FullLayout {
+            layout: Layout::Atomic(SBox::new(layout)),
+            defined_types,
+        }
+    }
+}
+
+unsafe impl<T: TypeInfo> _TypeInfoImpl for *const T {
     const _UID: TypeUid = id!(*const T);
+    const _FINGERPRINT: u128 = Fingerprinter::new()
+        .str("ConstPtr")
+        .u128(T::_SHALLOW_FINGERPRINT)
+        .finish();
 
     fn _layout_impl(defined_types: DefinedTypes) -> FullLayout {
         let FullLayout {
@@ -56,8 +151,12 @@ unsafe impl<T: _TypeInfoImpl> _TypeInfoImpl for *const T {
     }
 }
 
-unsafe impl<T: _TypeInfoImpl> _TypeInfoImpl for *mut T {
+unsafe impl<T: TypeInfo> _TypeInfoImpl for *mut T {
     const _UID: TypeUid = id!(*mut T);
+    const _FINGERPRINT: u128 = Fingerprinter::new()
+        .str("MutPtr")
+        .u128(T::_SHALLOW_FINGERPRINT)
+        .finish();
 
     fn _layout_impl(defined_types: DefinedTypes) -> FullLayout {
         let FullLayout {
@@ -72,8 +171,12 @@ unsafe impl<T: _TypeInfoImpl> _TypeInfoImpl for *mut T {
     }
 }
 
-unsafe impl<'a, T: _TypeInfoImpl> _TypeInfoImpl for &'a T {
+unsafe impl<T: TypeInfo> _TypeInfoImpl for &T {
     const _UID: TypeUid = id!(&T);
+    const _FINGERPRINT: u128 = Fingerprinter::new()
+        .str("Ref")
+        .u128(T::_SHALLOW_FINGERPRINT)
+        .finish();
 
     fn _layout_impl(defined_types: DefinedTypes) -> FullLayout {
         let FullLayout {
@@ -88,8 +191,12 @@ unsafe impl<'a, T: _TypeInfoImpl> _TypeInfoImpl for &'a T {
     }
 }
 
-unsafe impl<'a, T: _TypeInfoImpl> _TypeInfoImpl for &'a mut T {
+unsafe impl<T: TypeInfo> _TypeInfoImpl for &mut T {
     const _UID: TypeUid = id!(&mut T);
+    const _FINGERPRINT: u128 = Fingerprinter::new()
+        .str("MutRef")
+        .u128(T::_SHALLOW_FINGERPRINT)
+        .finish();
 
     fn _layout_impl(defined_types: DefinedTypes) -> FullLayout {
         let FullLayout {
@@ -104,8 +211,18 @@ unsafe impl<'a, T: _TypeInfoImpl> _TypeInfoImpl for &'a mut T {
     }
 }
 
-unsafe impl<const N: usize, T: _TypeInfoImpl> _TypeInfoImpl for [T; N] {
+unsafe impl<const N: usize, T: TypeInfo> _TypeInfoImpl for [T; N] {
     const _UID: TypeUid = id!([T; N]);
+    const _FINGERPRINT: u128 = Fingerprinter::new()
+        .str("Array")
+        .u64(N as u64)
+        .u128(T::_FINGERPRINT)
+        .finish();
+    const _SHALLOW_FINGERPRINT: u128 = Fingerprinter::new()
+        .str("Array")
+        .u64(N as u64)
+        .u128(T::_SHALLOW_FINGERPRINT)
+        .finish();
 
     fn _layout_impl(defined_types: DefinedTypes) -> FullLayout {
         let FullLayout {
@@ -1,8 +1,9 @@
 use super::id;
 use crate::{
-    layout::{DefinedTypes, FullLayout, Layout},
+    _TypeInfoImpl,
+    layout::{DefinedTypes, Fingerprinter, FullLayout, Layout},
     types::{FfiSafeEquivalent, SBox, SStr, SVec},
-    TypeUid, _TypeInfoImpl,
+    NicheOptimized, TypeInfo, TypeUid,
 };
 
 /// Reverse the order of a sequence of ident tokens.
@@ -18,8 +19,16 @@ macro_rules! rev_args {
 /// Impl `CallableOnce` given set of arguments.
 macro_rules! impl_with_args {
     ($($arg:ident)*) => {
-        unsafe impl<R: _TypeInfoImpl $(, $arg : _TypeInfoImpl)*> _TypeInfoImpl for unsafe extern fn($($arg),*) -> R {
-            const _UID: TypeUid = id!(unsafe extern fn($($arg),*) -> R);
+        unsafe impl<R: TypeInfo $(, $arg : TypeInfo)*> _TypeInfoImpl for unsafe extern "C" fn($($arg),*) -> R {
+            const _UID: TypeUid = id!(unsafe extern "C" fn($($arg),*) -> R);
+            const _FINGERPRINT: u128 = Fingerprinter::new()
+                .str("FunctionPointer")
+                .bool(true)
+                .str("C")
+                .u64(<[&str]>::len(&[$(stringify!($arg)),*]) as u64)
+                $(.u128($arg::_SHALLOW_FINGERPRINT))*
+                .u128(R::_SHALLOW_FINGERPRINT)
+                .finish();
 
             fn _layout_impl(defined_types: DefinedTypes) -> FullLayout {
                 let FullLayout {
@@ -51,8 +60,16 @@ macro_rules! impl_with_args {
             }
         }
 
-        unsafe impl<R: _TypeInfoImpl $(, $arg : _TypeInfoImpl)*> _TypeInfoImpl for unsafe fn($($arg),*) -> R {
+        unsafe impl<R: TypeInfo $(, $arg : TypeInfo)*> _TypeInfoImpl for unsafe fn($($arg),*) -> R {
             const _UID: TypeUid = id!(unsafe fn($($arg),*) -> R);
+            const _FINGERPRINT: u128 = Fingerprinter::new()
+                .str("FunctionPointer")
+                .bool(true)
+                .str("Rust")
+                .u64(<[&str]>::len(&[$(stringify!($arg)),*]) as u64)
+                $(.u128($arg::_SHALLOW_FINGERPRINT))*
+                .u128(R::_SHALLOW_FINGERPRINT)
+                .finish();
 
             fn _layout_impl(defined_types: DefinedTypes) -> FullLayout {
                 let FullLayout {
@@ -84,8 +101,16 @@ macro_rules! impl_with_args {
             }
         }
 
-        unsafe impl<R: _TypeInfoImpl $(, $arg : _TypeInfoImpl)*> _TypeInfoImpl for extern fn($($arg),*) -> R {
-            const _UID: TypeUid = id!(extern fn($($arg),*) -> R);
+        unsafe impl<R: TypeInfo $(, $arg : TypeInfo)*> _TypeInfoImpl for extern "C" fn($($arg),*) -> R {
+            const _UID: TypeUid = id!(extern "C" fn($($arg),*) -> R);
+            const _FINGERPRINT: u128 = Fingerprinter::new()
+                .str("FunctionPointer")
+                .bool(true)
+                .str("C")
+                .u64(<[&str]>::len(&[$(stringify!($arg)),*]) as u64)
+                $(.u128($arg::_SHALLOW_FINGERPRINT))*
+                .u128(R::_SHALLOW_FINGERPRINT)
+                .finish();
 
             fn _layout_impl(defined_types: DefinedTypes) -> FullLayout {
                 let FullLayout {
@@ -117,8 +142,16 @@ macro_rules! impl_with_args {
             }
         }
 
-        unsafe impl<R: _TypeInfoImpl $(, $arg : _TypeInfoImpl)*> _TypeInfoImpl for fn($($arg),*) -> R {
+        unsafe impl<R: TypeInfo $(, $arg : TypeInfo)*> _TypeInfoImpl for fn($($arg),*) -> R {
             const _UID: TypeUid = id!(fn($($arg),*) -> R);
+            const _FINGERPRINT: u128 = Fingerprinter::new()
+                .str("FunctionPointer")
+                .bool(false)
+                .str("Rust")
+                .u64(<[&str]>::len(&[$(stringify!($arg)),*]) as u64)
+                $(.u128($arg::_SHALLOW_FINGERPRINT))*
+                .u128(R::_SHALLOW_FINGERPRINT)
+                .finish();
 
             fn _layout_impl(defined_types: DefinedTypes) -> FullLayout {
                 let FullLayout {
@@ -149,6 +182,11 @@ macro_rules! impl_with_args {
                 }
             }
         }
+
+        unsafe impl<R $(, $arg)*> NicheOptimized for unsafe extern "C" fn($($arg),*) -> R {}
+        unsafe impl<R $(, $arg)*> NicheOptimized for unsafe fn($($arg),*) -> R {}
+        unsafe impl<R $(, $arg)*> NicheOptimized for extern "C" fn($($arg),*) -> R {}
+        unsafe impl<R $(, $arg)*> NicheOptimized for fn($($arg),*) -> R {}
     };
 }
 
@@ -0,0 +1,571 @@
+//! Single-producer single-consumer channels between processes, over POSIX shared memory.
+//!
+//! The shared memory object starts with a header containing the [encoded][TypeLayout::to_bytes] layout
+//! of the message type, so that a process attaching with a different type gets an error instead of garbage.
+//! It is followed by a lock-free ring buffer of messages.
+//!
+//! Messages are copied into the ring buffer without their padding, and [validated][crate::dynamic::validate]
+//! when they are received, so writing invalid values into the channel can't cause undefined behaviour.
+//! Like in [files][crate::file], messages can't contain pointers (other than [relative][crate::types::RelPtr]
+//! ones, pointing within the message), unions or opaque types.
+//!
+//! The process on the other side still has to be trusted: it can resize the shared memory object
+//! (which POSIX shared memory has no way to prevent), and then accessing the mapping is undefined behaviour,
+//! or crashes the process with `SIGBUS`. This is why [`Sender::create`], [`Sender::open`] and the like are `unsafe`.
+//!
+//! The channel never blocks: [`Sender::try_send`] fails if the ring buffer is full,
+//! and [`Receiver::try_recv`] returns `None` if it's empty.
+
+use crate::{
+    compat::Mismatch,
+    dynamic::{unchecked_type, validate, Copier, InvalidValue},
+    encode::DecodeError,
+    TypeInfo, TypeLayout,
+};
+use memmap2::MmapRaw;
+use std::{
+    ffi::CString,
+    fmt::Display,
+    fs::File,
+    marker::PhantomData,
+    mem::{align_of, size_of, MaybeUninit},
+    os::fd::FromRawFd,
+    ptr::{copy_nonoverlapping, write_bytes},
+    sync::atomic::{AtomicBool, AtomicU64, AtomicU8, Ordering},
+};
+
+const MAGIC: u64 = u64::from_le_bytes(*b"IRONSHM\0");
+
+/// Why a channel couldn't be created or attached to, or a message couldn't be received
+#[derive(Debug)]
+pub enum ShmError {
+    Io(std::io::Error),
+    /// The shared memory object doesn't start with a valid header, or is still being created
+    InvalidHeader,
+    /// The layout in the header couldn't be decoded
+    Layout(DecodeError),
+    /// The channel carries messages of a different type
+    LayoutMismatch {
+        /// The layout of the message type of this side of the channel
+        expected: Box<TypeLayout>,
+        /// The layout in the header
+        found: Box<TypeLayout>,
+    },
+    /// The type contains pointers, unions or opaque types (`name` is the type containing them, or the type itself)
+    Unsupported {
+        name: String,
+    },
+    /// Another sender or receiver is already attached to the channel
+    AlreadyAttached,
+    /// A received message is not a valid value of the type
+    InvalidValue(InvalidValue),
+}
+
+impl Display for ShmError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ShmError::Io(err) => Display::fmt(err, f),
+            ShmError::InvalidHeader => {
+                write!(f, "the shared memory doesn't have a valid channel header")
+            }
+            ShmError::Layout(err) => write!(f, "invalid layout in the header: {err}"),
+            ShmError::LayoutMismatch { expected, found } => write!(
+                f,
+                "the channel carries messages of a different type: {}",
+                Mismatch { expected, found }
+            ),
+            ShmError::Unsupported { name } => write!(
+                f,
+                "`{name}` contains pointers, unions or opaque types, which can't be sent through shared memory"
+            ),
+            ShmError::AlreadyAttached => write!(f, "the other end of the channel is already attached"),
+            ShmError::InvalidValue(err) => write!(f, "invalid message received: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for ShmError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ShmError::Io(err) => Some(err),
+            ShmError::Layout(err) => Some(err),
+            ShmError::InvalidValue(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for ShmError {
+    fn from(value: std::io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+/// The start of the shared memory object
+///
+/// All fields are atomic, since the process on the other side may modify them at any time.
+#[repr(C)]
+struct Header {
+    /// Written last, after the rest of the header and the layout
+    magic: AtomicU64,
+    layout_len: AtomicU64,
+    capacity: AtomicU64,
+    /// The offset of the ring buffer from the start of the shared memory
+    slots_offset: AtomicU64,
+    sender: AtomicBool,
+    receiver: AtomicBool,
+    /// The number of messages received so far
+    head: CacheLine,
+    /// The number of messages sent so far
+    tail: CacheLine,
+}
+
+/// Keeps the indices used by different processes from sharing a cache line
+#[repr(C, align(64))]
+struct CacheLine(AtomicU64);
+
+/// A mapped channel, attached to as one of its ends
+struct Channel<T> {
+    mmap: MmapRaw,
+    slots_offset: usize,
+    capacity: u64,
+    /// Set if the channel was created by this side, to unlink it when dropped
+    name: Option<CString>,
+    /// Whether this side is the sender
+    sender: bool,
+    _phantom: PhantomData<T>,
+}
+
+impl<T: TypeInfo> Channel<T> {
+    unsafe fn create(name: &str, capacity: usize, sender: bool) -> Result<Self, ShmError> {
+        assert!(capacity > 0, "the capacity of a channel can't be zero");
+
+        let layout = T::layout_ref();
+        check_supported(layout)?;
+
+        let encoded = layout.to_bytes();
+        let slots_offset = (size_of::<Header>() + encoded.len()).next_multiple_of(align_of::<T>());
+        let Some(total) = size_of::<T>()
+            .checked_mul(capacity)
+            .and_then(|size| size.checked_add(slots_offset))
+        else {
+            panic!("the capacity of the channel is too large");
+        };
+
+        let name = c_name(name)?;
+        let file = shm_open(&name, libc::O_CREAT | libc::O_EXCL | libc::O_RDWR)?;
+        let mmap = match file
+            .set_len(total as u64)
+            .and_then(|()| MmapRaw::map_raw(&file))
+        {
+            Ok(mmap) => mmap,
+            Err(err) => {
+                libc::shm_unlink(name.as_ptr());
+                return Err(err.into());
+            }
+        };
+        let channel = Self {
+            mmap,
+            slots_offset,
+            capacity: capacity as u64,
+            name: Some(name),
+            sender,
+            _phantom: PhantomData,
+        };
+
+        // The memory is zeroed by `set_len`, so the indices start at zero
+        let header = channel.header();
+        header
+            .layout_len
+            .store(encoded.len() as u64, Ordering::Relaxed);
+        header.capacity.store(capacity as u64, Ordering::Relaxed);
+        header
+            .slots_offset
+            .store(slots_offset as u64, Ordering::Relaxed);
+        channel.role().store(true, Ordering::Relaxed);
+        copy_nonoverlapping(
+            encoded.as_ptr(),
+            channel.mmap.as_mut_ptr().add(size_of::<Header>()),
+            encoded.len(),
+        );
+        header.magic.store(MAGIC, Ordering::Release);
+
+        Ok(channel)
+    }
+    unsafe fn open(name: &str, sender: bool) -> Result<Self, ShmError> {
+        let file = shm_open(&c_name(name)?, libc::O_RDWR)?;
+        let mmap = MmapRaw::map_raw(&file)?;
+        if mmap.len() < size_of::<Header>() {
+            return Err(ShmError::InvalidHeader);
+        }
+
+        // SAFETY: the mapping is large enough for the header, and page-aligned
+        let header = &*mmap.as_ptr().cast::<Header>();
+        if header.magic.load(Ordering::Acquire) != MAGIC {
+            return Err(ShmError::InvalidHeader);
+        }
+        let (Ok(layout_len), Ok(capacity), Ok(slots_offset)) = (
+            usize::try_from(header.layout_len.load(Ordering::Relaxed)),
+            usize::try_from(header.capacity.load(Ordering::Relaxed)),
+            usize::try_from(header.slots_offset.load(Ordering::Relaxed)),
+        ) else {
+            return Err(ShmError::InvalidHeader);
+        };
+        if size_of::<Header>()
+            .checked_add(layout_len)
+            .is_none_or(|end| end > slots_offset || end > mmap.len())
+        {
+            return Err(ShmError::InvalidHeader);
+        }
+
+        // Copied out, so that it can't change while being decoded
+        let mut encoded = vec![0; layout_len];
+        copy_shared(
+            mmap.as_ptr().add(size_of::<Header>()),
+            encoded.as_mut_ptr(),
+            layout_len,
+        );
+        let found = TypeLayout::from_bytes(&encoded).map_err(ShmError::Layout)?;
+        let expected = T::layout_ref();
+        if !expected.is_compatible_with(&found) {
+            return Err(ShmError::LayoutMismatch {
+                expected: Box::new(expected.clone()),
+                found: Box::new(found),
+            });
+        }
+
+        // The ring buffer can only be checked once it's known to be of `T`
+        if capacity == 0
+            || !slots_offset.is_multiple_of(align_of::<T>())
+            || size_of::<T>()
+                .checked_mul(capacity)
+                .and_then(|size| size.checked_add(slots_offset))
+                .is_none_or(|end| end > mmap.len())
+        {
+            return Err(ShmError::InvalidHeader);
+        }
+        check_supported(expected)?;
+
+        let role = match sender {
+            true => &header.sender,
+            false => &header.receiver,
+        };
+        if role
+            .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            return Err(ShmError::AlreadyAttached);
+        }
+
+        Ok(Self {
+            mmap,
+            slots_offset,
+            capacity: capacity as u64,
+            name: None,
+            sender,
+            _phantom: PhantomData,
+        })
+    }
+    fn slot(&self, index: u64) -> *mut u8 {
+        let slot = (index % self.capacity) as usize;
+        // SAFETY: the slot is within the mapping, which was checked when attaching
+        unsafe {
+            self.mmap
+                .as_mut_ptr()
+                .add(self.slots_offset + slot * size_of::<T>())
+        }
+    }
+}
+
+impl<T> Channel<T> {
+    fn header(&self) -> &Header {
+        // SAFETY: the mapping is large enough for the header, and page-aligned
+        unsafe { &*self.mmap.as_ptr().cast() }
+    }
+    /// Whether this side of the channel is attached
+    fn role(&self) -> &AtomicBool {
+        match self.sender {
+            true => &self.header().sender,
+            false => &self.header().receiver,
+        }
+    }
+}
+
+impl<T> Drop for Channel<T> {
+    fn drop(&mut self) {
+        self.role().store(false, Ordering::Release);
+
+        if let Some(name) = &self.name {
+            // SAFETY: the name is a valid C string. Processes that are attached keep their mappings
+            unsafe { libc::shm_unlink(name.as_ptr()) };
+        }
+    }
+}
+
+/// The sending end of a channel
+pub struct Sender<T> {
+    channel: Channel<T>,
+}
+
+/// The receiving end of a channel
+pub struct Receiver<T> {
+    channel: Channel<T>,
+}
+
+impl<T: TypeInfo> Sender<T> {
+    /// Creates a new channel with room for `capacity` messages, and attaches to it as the sender.
+    ///
+    /// `name` is the name of the shared memory object, which must not exist yet (`/my-channel` for example).
+    /// The object is removed when the sender is dropped, but processes that are attached to it can keep using it.
+    ///
+    /// # Safety
+    ///
+    /// The shared memory object must not be resized while it's mapped (see [`memmap2::MmapRaw`]).
+    ///
+    /// # Panics
+    ///
+    /// If `capacity` is zero, or the ring buffer would take more than `usize::MAX` bytes.
+    pub unsafe fn create(name: &str, capacity: usize) -> Result<Self, ShmError> {
+        Channel::create(name, capacity, true).map(|channel| Self { channel })
+    }
+    /// Attaches to an existing channel as the sender, checking that it carries messages of `T`
+    ///
+    /// # Safety
+    ///
+    /// The shared memory object must not be resized while it's mapped (see [`memmap2::MmapRaw`]).
+    pub unsafe fn open(name: &str) -> Result<Self, ShmError> {
+        Channel::open(name, true).map(|channel| Self { channel })
+    }
+    /// Sends a message, or returns it back if the channel is full
+    pub fn try_send(&mut self, value: T) -> Result<(), T> {
+        let header = self.channel.header();
+        let tail = header.tail.0.load(Ordering::Relaxed);
+        let head = header.head.0.load(Ordering::Acquire);
+        if tail.wrapping_sub(head) >= self.channel.capacity {
+            return Err(value);
+        }
+
+        let layout = T::layout_ref();
+        let slot = self.channel.slot(tail);
+        // SAFETY: the slot is within the mapping, and the receiver doesn't read it until the tail is incremented.
+        // It's cleared first, since the padding isn't copied, and would otherwise keep the bytes of an earlier message
+        unsafe {
+            write_bytes(slot, 0, size_of::<T>());
+            Copier {
+                defined_types: &layout.defined_types,
+            }
+            .copy(&layout.layout, (&value as *const T).cast(), slot)
+        };
+        // The value has been moved into the channel
+        std::mem::forget(value);
+        header.tail.0.store(tail.wrapping_add(1), Ordering::Release);
+
+        Ok(())
+    }
+}
+
+impl<T: TypeInfo> Receiver<T> {
+    /// Creates a new channel with room for `capacity` messages, and attaches to it as the receiver.
+    ///
+    /// See [`Sender::create`].
+    ///
+    /// # Safety
+    ///
+    /// The shared memory object must not be resized while it's mapped (see [`memmap2::MmapRaw`]).
+    ///
+    /// # Panics
+    ///
+    /// If `capacity` is zero, or the ring buffer would take more than `usize::MAX` bytes.
+    pub unsafe fn create(name: &str, capacity: usize) -> Result<Self, ShmError> {
+        Channel::create(name, capacity, false).map(|channel| Self { channel })
+    }
+    /// Attaches to an existing channel as the receiver, checking that it carries messages of `T`
+    ///
+    /// # Safety
+    ///
+    /// The shared memory object must not be resized while it's mapped (see [`memmap2::MmapRaw`]).
+    pub unsafe fn open(name: &str) -> Result<Self, ShmError> {
+        Channel::open(name, false).map(|channel| Self { channel })
+    }
+    /// Receives a message, if there are any.
+    ///
+    /// If the message is not a valid value of `T`, it is skipped and an error is returned.
+    pub fn try_recv(&mut self) -> Result<Option<T>, ShmError> {
+        let header = self.channel.header();
+        let head = header.head.0.load(Ordering::Relaxed);
+        let tail = header.tail.0.load(Ordering::Acquire);
+        if head == tail {
+            return Ok(None);
+        }
+
+        // Copied out first, so that the sender can't change it after it's validated
+        let mut value = MaybeUninit::<T>::uninit();
+        // SAFETY: the slot is within the mapping, and the sender doesn't write to it until the head is incremented
+        unsafe {
+            copy_shared(
+                self.channel.slot(head),
+                value.as_mut_ptr().cast::<u8>(),
+                size_of::<T>(),
+            )
+        };
+        header.head.0.store(head.wrapping_add(1), Ordering::Release);
+
+        // SAFETY: all bytes of the slot are initialized, since the shared memory starts zeroed
+        let bytes =
+            unsafe { std::slice::from_raw_parts(value.as_ptr().cast::<u8>(), size_of::<T>()) };
+        validate(bytes, T::layout_ref()).map_err(ShmError::InvalidValue)?;
+
+        // SAFETY: the value was validated
+        Ok(Some(unsafe { value.assume_init() }))
+    }
+}
+
+fn check_supported(layout: &TypeLayout) -> Result<(), ShmError> {
+    match unchecked_type(layout, "the message") {
+        Some(name) => Err(ShmError::Unsupported {
+            name: name.to_owned(),
+        }),
+        None => Ok(()),
+    }
+}
+
+fn c_name(name: &str) -> Result<CString, ShmError> {
+    CString::new(name).map_err(|err| ShmError::Io(std::io::Error::other(err)))
+}
+
+unsafe fn shm_open(name: &CString, flags: libc::c_int) -> Result<File, ShmError> {
+    let fd = libc::shm_open(name.as_ptr(), flags, 0o600 as libc::c_uint);
+    if fd == -1 {
+        return Err(std::io::Error::last_os_error().into());
+    }
+
+    Ok(File::from_raw_fd(fd))
+}
+
+/// Copies `len` bytes out of the shared memory, which the process on the other side may be writing to at the same time.
+/// They are loaded as atomics, so that this only results in unexpected values, which are validated afterwards.
+///
+/// # Safety
+///
+/// `src` must be valid for reads and `dst` for writes of `len` bytes.
+unsafe fn copy_shared(src: *const u8, dst: *mut u8, len: usize) {
+    for i in 0..len {
+        let byte = AtomicU8::from_ptr(src.add(i).cast_mut());
+        dst.add(i).write(byte.load(Ordering::Relaxed));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Receiver, Sender, ShmError};
+    use crate::{dynamic::InvalidValue, types::SVec, TypeInfo};
+
+    #[repr(u8)]
+    #[derive(TypeInfo, Debug, PartialEq, Clone, Copy)]
+    enum Kind {
+        Ping,
+        Data(u16),
+    }
+
+    #[repr(C)]
+    #[derive(TypeInfo, Debug, PartialEq, Clone, Copy)]
+    struct Message {
+        id: u32,
+        kind: Kind,
+    }
+
+    fn name(test: &str) -> String {
+        format!("/ironties-test-{}-{test}", std::process::id())
+    }
+
+    #[test]
+    fn send_and_receive() {
+        let name = name("send");
+        let mut sender = unsafe { Sender::<Message>::create(&name, 2) }.unwrap();
+        let mut receiver = unsafe { Receiver::<Message>::open(&name) }.unwrap();
+
+        let messages = [
+            Message {
+                id: 1,
+                kind: Kind::Ping,
+            },
+            Message {
+                id: 2,
+                kind: Kind::Data(300),
+            },
+            Message {
+                id: 3,
+                kind: Kind::Ping,
+            },
+        ];
+
+        assert!(receiver.try_recv().unwrap().is_none());
+        assert_eq!(sender.try_send(messages[0]), Ok(()));
+        assert_eq!(sender.try_send(messages[1]), Ok(()));
+        // Full
+        assert_eq!(sender.try_send(messages[2]), Err(messages[2]));
+
+        assert_eq!(receiver.try_recv().unwrap(), Some(messages[0]));
+        assert_eq!(sender.try_send(messages[2]), Ok(()));
+        assert_eq!(receiver.try_recv().unwrap(), Some(messages[1]));
+        assert_eq!(receiver.try_recv().unwrap(), Some(messages[2]));
+        assert!(receiver.try_recv().unwrap().is_none());
+
+        assert!(matches!(
+            unsafe { Receiver::<Message>::open(&name) },
+            Err(ShmError::AlreadyAttached)
+        ));
+        drop(receiver);
+        assert!(unsafe { Receiver::<Message>::open(&name) }.is_ok());
+    }
+
+    #[test]
+    fn layout_mismatch() {
+        let name = name("mismatch");
+        let _receiver = unsafe { Receiver::<Message>::create(&name, 1) }.unwrap();
+
+        let err = unsafe { Sender::<u32>::open(&name) }.err().unwrap();
+        assert!(matches!(err, ShmError::LayoutMismatch { .. }));
+        assert_eq!(
+            err.to_string(),
+            "the channel carries messages of a different type: \
+            `Message` (size 8, align 4) is not compatible with `U32`"
+        );
+
+        assert!(matches!(
+            unsafe { Sender::<SVec<u8>>::open(&name) },
+            Err(ShmError::LayoutMismatch { .. })
+        ));
+        assert!(matches!(
+            unsafe { Sender::<SVec<u8>>::create(&self::name("pointers"), 1) },
+            Err(ShmError::Unsupported { .. })
+        ));
+    }
+
+    #[test]
+    fn invalid_message() {
+        let name = name("invalid");
+        let mut sender = unsafe { Sender::<Message>::create(&name, 1) }.unwrap();
+        let mut receiver = unsafe { Receiver::<Message>::open(&name) }.unwrap();
+
+        let message = Message {
+            id: 1,
+            kind: Kind::Data(5),
+        };
+        sender.try_send(message).unwrap();
+        // The tag of `kind`, as a misbehaving sender might write it
+        unsafe { sender.channel.slot(0).add(4).write(7) };
+        assert!(matches!(
+            receiver.try_recv(),
+            Err(ShmError::InvalidValue(InvalidValue::InvalidDiscriminant {
+                offset: 4,
+                ..
+            }))
+        ));
+
+        // The invalid message is skipped
+        assert!(receiver.try_recv().unwrap().is_none());
+        sender.try_send(message).unwrap();
+        assert_eq!(receiver.try_recv().unwrap(), Some(message));
+    }
+}
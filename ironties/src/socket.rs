@@ -0,0 +1,370 @@
+//! Messages between processes over Unix domain sockets, with the layouts of the messages checked when connecting.
+//!
+//! When a [`Connection`] is established, both peers send each other the [encoded][TypeLayout::to_bytes] layouts
+//! of the messages they send and receive, and refuse to proceed if they don't match:
+//!
+//! | Bytes | Contents |
+//! | --- | --- |
+//! | 8 | `IRONSOCK` |
+//! | 8 | The length of the encoded layout of sent messages, little-endian |
+//! | | The encoded layout of sent messages |
+//! | 8 | The length of the encoded layout of received messages, little-endian |
+//! | | The encoded layout of received messages |
+//!
+//! After that, messages are sent as their raw bytes, without padding and without any serialization,
+//! and [validated][crate::dynamic::validate] when they are received. Messages can't contain pointers
+//! (other than [relative][crate::types::RelPtr] ones, pointing within the message), unions or opaque types.
+
+use crate::{
+    compat::Mismatch,
+    dynamic::{unchecked_type, validate, Copier, InvalidValue},
+    encode::DecodeError,
+    TypeInfo, TypeLayout,
+};
+use std::{
+    fmt::Display,
+    io::{ErrorKind, Read, Write},
+    marker::PhantomData,
+    mem::{size_of, MaybeUninit},
+    net::Shutdown,
+    os::unix::net::UnixStream,
+    path::Path,
+    thread,
+};
+
+const MAGIC: [u8; 8] = *b"IRONSOCK";
+/// Encoded layouts longer than this are rejected, so that a peer can't make us allocate arbitrary amounts of memory
+const MAX_LAYOUT_LEN: usize = 1 << 20;
+
+/// Why a connection couldn't be established, or a message couldn't be sent or received
+#[derive(Debug)]
+pub enum SocketError {
+    Io(std::io::Error),
+    /// The peer didn't start the connection with a valid handshake
+    InvalidHandshake,
+    /// A layout in the handshake couldn't be decoded
+    Layout(DecodeError),
+    /// The peer sends, or expects to receive, messages of a different type
+    LayoutMismatch {
+        /// Whether the mismatch is in the messages sent by this side, or the ones received by it
+        sent: bool,
+        /// The layout of the messages of this side
+        expected: Box<TypeLayout>,
+        /// The layout of the messages of the peer
+        found: Box<TypeLayout>,
+    },
+    /// The type contains pointers, unions or opaque types (`name` is the type containing them, or the type itself)
+    Unsupported {
+        name: String,
+    },
+    /// A received message is not a valid value of the type
+    InvalidValue(InvalidValue),
+}
+
+impl Display for SocketError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SocketError::Io(err) => Display::fmt(err, f),
+            SocketError::InvalidHandshake => write!(f, "the peer didn't send a valid handshake"),
+            SocketError::Layout(err) => write!(f, "invalid layout in the handshake: {err}"),
+            SocketError::LayoutMismatch {
+                sent,
+                expected,
+                found,
+            } => write!(
+                f,
+                "the peer {} messages of a different type: {}",
+                if *sent { "expects to receive" } else { "sends" },
+                Mismatch { expected, found }
+            ),
+            SocketError::Unsupported { name } => write!(
+                f,
+                "`{name}` contains pointers, unions or opaque types, which can't be sent through sockets"
+            ),
+            SocketError::InvalidValue(err) => write!(f, "invalid message received: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for SocketError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SocketError::Io(err) => Some(err),
+            SocketError::Layout(err) => Some(err),
+            SocketError::InvalidValue(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for SocketError {
+    fn from(value: std::io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+/// A connection that sends messages of `S` and receives messages of `R`
+///
+/// For requests and responses, the client would use `Connection<Request, Response>`,
+/// and the server `Connection<Response, Request>`.
+pub struct Connection<S, R> {
+    stream: UnixStream,
+    _phantom: PhantomData<fn(S) -> R>,
+}
+
+impl<S: TypeInfo, R: TypeInfo> Connection<S, R> {
+    /// Connects to the socket at `path`, and performs the handshake
+    pub fn connect(path: impl AsRef<Path>) -> Result<Self, SocketError> {
+        Self::new(UnixStream::connect(path)?)
+    }
+    /// Performs the handshake over a connected stream ([`UnixListener::accept`][std::os::unix::net::UnixListener::accept]
+    /// or [`UnixStream::pair`] for example).
+    ///
+    /// Blocks until the peer sends its handshake.
+    pub fn new(mut stream: UnixStream) -> Result<Self, SocketError> {
+        let (sent, received) = (S::layout_ref(), R::layout_ref());
+        check_supported(sent)?;
+        check_supported(received)?;
+
+        let mut handshake = MAGIC.to_vec();
+        for layout in [sent, received] {
+            let encoded = layout.to_bytes();
+            handshake.extend_from_slice(&(encoded.len() as u64).to_le_bytes());
+            handshake.extend_from_slice(&encoded);
+        }
+
+        // Both peers send their handshakes before reading the other one, which would block forever if neither
+        // fits in the buffer of the socket, so it's written from another thread while the peer's is read
+        let mut writer = stream.try_clone()?;
+        let (written, read) = thread::scope(|scope| {
+            let writing = scope.spawn(move || writer.write_all(&handshake));
+            let read = read_handshake(&mut stream);
+            if read.is_err() {
+                // The peer may not read the rest of the handshake, so the write is interrupted instead of waited for
+                let _ = stream.shutdown(Shutdown::Both);
+            }
+
+            (writing.join().unwrap(), read)
+        });
+        let (peer_sent, peer_received) = read?;
+        written?;
+
+        if !received.is_compatible_with(&peer_sent) {
+            return Err(SocketError::LayoutMismatch {
+                sent: false,
+                expected: Box::new(received.clone()),
+                found: Box::new(peer_sent),
+            });
+        }
+        if !sent.is_compatible_with(&peer_received) {
+            return Err(SocketError::LayoutMismatch {
+                sent: true,
+                expected: Box::new(sent.clone()),
+                found: Box::new(peer_received),
+            });
+        }
+
+        Ok(Self {
+            stream,
+            _phantom: PhantomData,
+        })
+    }
+    pub fn send(&mut self, message: &S) -> Result<(), SocketError> {
+        let layout = S::layout_ref();
+        let mut bytes = vec![0; size_of::<S>()];
+        // SAFETY: the layout describes S, was checked when connecting, and the buffer has room for it
+        unsafe {
+            Copier {
+                defined_types: &layout.defined_types,
+            }
+            .copy(
+                &layout.layout,
+                (message as *const S).cast(),
+                bytes.as_mut_ptr(),
+            )
+        };
+
+        self.stream.write_all(&bytes)?;
+
+        Ok(())
+    }
+    /// Receives a message, blocking until one arrives, or returns `None` if the peer closed the connection.
+    ///
+    /// If the message is not a valid value of `R`, it is skipped and an error is returned.
+    pub fn recv(&mut self) -> Result<Option<R>, SocketError> {
+        let mut value = MaybeUninit::<R>::zeroed();
+        // SAFETY: the value is zeroed, so all of its bytes are initialized
+        let bytes = unsafe {
+            std::slice::from_raw_parts_mut(value.as_mut_ptr().cast::<u8>(), size_of::<R>())
+        };
+
+        let mut read = 0;
+        while read < bytes.len() {
+            match self.stream.read(&mut bytes[read..]) {
+                Ok(0) if read == 0 => return Ok(None),
+                Ok(0) => return Err(std::io::Error::from(ErrorKind::UnexpectedEof).into()),
+                Ok(n) => read += n,
+                Err(err) if err.kind() == ErrorKind::Interrupted => {}
+                Err(err) => return Err(err.into()),
+            }
+        }
+        validate(bytes, R::layout_ref()).map_err(SocketError::InvalidValue)?;
+
+        // SAFETY: the value was validated
+        Ok(Some(unsafe { value.assume_init() }))
+    }
+    /// The underlying stream, for setting timeouts or shutting down the connection
+    pub fn stream(&self) -> &UnixStream {
+        &self.stream
+    }
+}
+
+/// Reads the layouts of the messages sent and received by the peer
+fn read_handshake(stream: &mut UnixStream) -> Result<(TypeLayout, TypeLayout), SocketError> {
+    let mut magic = [0; 8];
+    stream.read_exact(&mut magic)?;
+    if magic != MAGIC {
+        return Err(SocketError::InvalidHandshake);
+    }
+
+    Ok((read_layout(stream)?, read_layout(stream)?))
+}
+
+fn read_layout(stream: &mut UnixStream) -> Result<TypeLayout, SocketError> {
+    let mut len = [0; 8];
+    stream.read_exact(&mut len)?;
+    let len = match usize::try_from(u64::from_le_bytes(len)) {
+        Ok(len) if len <= MAX_LAYOUT_LEN => len,
+        _ => return Err(SocketError::InvalidHandshake),
+    };
+
+    let mut encoded = vec![0; len];
+    stream.read_exact(&mut encoded)?;
+
+    TypeLayout::from_bytes(&encoded).map_err(SocketError::Layout)
+}
+
+fn check_supported(layout: &TypeLayout) -> Result<(), SocketError> {
+    match unchecked_type(layout, "the message") {
+        Some(name) => Err(SocketError::Unsupported {
+            name: name.to_owned(),
+        }),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Connection, SocketError};
+    use crate::{dynamic::InvalidValue, types::SStr, TypeInfo};
+    use std::{io::Write, mem::size_of, os::unix::net::UnixStream, thread};
+
+    #[repr(u8)]
+    #[derive(TypeInfo, Debug, PartialEq, Clone, Copy)]
+    enum Request {
+        Add(u32, u32),
+        Stop,
+    }
+
+    #[repr(C)]
+    #[derive(TypeInfo, Debug, PartialEq, Clone, Copy)]
+    struct Response {
+        sum: u64,
+        overflowed: bool,
+    }
+
+    #[test]
+    fn requests_and_responses() {
+        let (client, server) = UnixStream::pair().unwrap();
+
+        let server = thread::spawn(move || {
+            let mut server = Connection::<Response, Request>::new(server).unwrap();
+            while let Some(Request::Add(a, b)) = server.recv().unwrap() {
+                let (sum, overflowed) = a.overflowing_add(b);
+                server
+                    .send(&Response {
+                        sum: sum as u64,
+                        overflowed,
+                    })
+                    .unwrap();
+            }
+        });
+
+        let mut client = Connection::<Request, Response>::new(client).unwrap();
+        client.send(&Request::Add(2, 3)).unwrap();
+        assert_eq!(
+            client.recv().unwrap(),
+            Some(Response {
+                sum: 5,
+                overflowed: false
+            })
+        );
+        client.send(&Request::Add(u32::MAX, 1)).unwrap();
+        assert_eq!(
+            client.recv().unwrap(),
+            Some(Response {
+                sum: 0,
+                overflowed: true
+            })
+        );
+        client.send(&Request::Stop).unwrap();
+
+        server.join().unwrap();
+        // The server closed the connection
+        assert_eq!(client.recv().unwrap(), None);
+    }
+
+    #[test]
+    fn layout_mismatch() {
+        let (a, b) = UnixStream::pair().unwrap();
+
+        let b = thread::spawn(move || Connection::<u64, u32>::new(b).err().unwrap());
+        let a = Connection::<u32, u32>::new(a).err().unwrap();
+
+        assert!(matches!(a, SocketError::LayoutMismatch { sent: false, .. }));
+        assert_eq!(
+            a.to_string(),
+            "the peer sends messages of a different type: `U64` is not compatible with `U32`"
+        );
+        assert!(matches!(
+            b.join().unwrap(),
+            SocketError::LayoutMismatch { sent: true, .. }
+        ));
+
+        assert!(matches!(
+            Connection::<SStr, u32>::new(UnixStream::pair().unwrap().0),
+            Err(SocketError::Unsupported { .. })
+        ));
+    }
+
+    #[test]
+    fn invalid_message() {
+        let (a, b) = UnixStream::pair().unwrap();
+
+        let b = thread::spawn(move || Connection::<Response, Response>::new(b).unwrap());
+        let mut a = Connection::<Response, Response>::new(a).unwrap();
+        let mut b = b.join().unwrap();
+
+        let response = Response {
+            sum: 1,
+            overflowed: true,
+        };
+        a.send(&response).unwrap();
+        assert_eq!(b.recv().unwrap(), Some(response));
+
+        // The peer may send anything
+        let mut bytes = vec![0; size_of::<Response>()];
+        bytes[8] = 2;
+        a.stream.write_all(&bytes).unwrap();
+        assert!(matches!(
+            b.recv(),
+            Err(SocketError::InvalidValue(InvalidValue::InvalidBool {
+                offset: 8,
+                value: 2
+            }))
+        ));
+
+        a.send(&response).unwrap();
+        assert_eq!(b.recv().unwrap(), Some(response));
+    }
+}
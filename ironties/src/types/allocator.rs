@@ -15,6 +15,7 @@ pub struct SGlobal {
 
 #[repr(C)]
 #[derive(TypeInfo, Clone, Copy, PartialEq)]
+#[allow(unpredictable_function_pointer_comparisons)]
 struct SGlobalVTable {
     allocate: unsafe extern "C" fn(layout: SLayout) -> STuple2<*mut u8, usize>,
     deallocate: unsafe extern "C" fn(ptr: NonNull<u8>, layout: SLayout),
@@ -9,8 +9,19 @@ use std::mem::{forget, ManuallyDrop};
 use std::ops::{Deref, DerefMut};
 
 /// FFI-safe equivalent of [`Box<T>`]
+///
+/// Like `Box<T>`, it can only be sent to another thread if `T` is [`Send`]:
+///
+/// ```compile_fail
+/// # use ironties::types::SBox;
+/// # use std::rc::Rc;
+/// fn assert_send<T: Send>(_: T) {}
+///
+/// assert_send(SBox::new(Rc::new(1u8)));
+/// ```
 #[repr(C)]
 #[derive(TypeInfo)]
+#[ironties(known = "Box")]
 pub struct SBox<T, A: Allocator = SGlobal> {
     ptr: *const T,
     // ManuallyDrop to avoid a double-free, because on drop (see Drop impl) the whole thing
@@ -136,11 +147,7 @@ impl<T: Display, A: Allocator> Display for SBox<T, A> {
     }
 }
 
-impl<T: Eq, A: Allocator> Eq for SBox<T, A> {
-    fn assert_receiver_is_total_eq(&self) {
-        T::assert_receiver_is_total_eq(self.as_ref())
-    }
-}
+impl<T: Eq, A: Allocator> Eq for SBox<T, A> {}
 
 impl<T> From<T> for SBox<T> {
     fn from(value: T) -> Self {
@@ -202,3 +209,6 @@ impl<T, A: Allocator> Pointer for SBox<T, A> {
 }
 
 impl<T, A: Allocator> Unpin for SBox<T, A> {}
+
+unsafe impl<T: Send, A: Allocator + Send> Send for SBox<T, A> {}
+unsafe impl<T: Sync, A: Allocator + Sync> Sync for SBox<T, A> {}
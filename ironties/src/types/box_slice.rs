@@ -0,0 +1,425 @@
+use super::allocator::SGlobal;
+use super::{FfiSafeEquivalent, SString, SVec};
+use crate::TypeInfo;
+use std::alloc::Allocator;
+use std::borrow::{Borrow, BorrowMut};
+use std::fmt::{Debug, Display};
+use std::hash::Hash;
+use std::mem::{forget, ManuallyDrop};
+use std::ops::{Deref, DerefMut, Index, IndexMut};
+use std::ptr::slice_from_raw_parts_mut;
+
+/// FFI-safe equivalent of [`Box<[T]>`][Box]
+///
+/// Unlike [`SVec`], this doesn't store the capacity, so it's a better fit for buffers that are never resized.
+#[repr(C)]
+#[derive(TypeInfo)]
+#[ironties(known = "Slice")]
+pub struct SBoxSlice<T, A: Allocator = SGlobal> {
+    ptr: *const T,
+    len: usize,
+    // ManuallyDrop to avoid a double-free, because on drop (see Drop impl) the whole thing
+    // will be converted to a Box and that dropped, which takes care of dropping the allocator.
+    allocator: ManuallyDrop<A>,
+}
+
+/// FFI-safe equivalent of [`Box<str>`][Box]
+#[repr(C)]
+#[derive(TypeInfo)]
+#[ironties(known = "Str")]
+pub struct SBoxStr<A: Allocator = SGlobal> {
+    inner: SBoxSlice<u8, A>,
+}
+
+impl<T, A: Allocator> FfiSafeEquivalent for SBoxSlice<T, A> {
+    type Normal = Box<[T], A>;
+
+    fn from_normal(normal: Self::Normal) -> Self {
+        let (ptr, allocator) = Box::into_raw_with_allocator(normal);
+
+        Self {
+            ptr: ptr as *const T,
+            len: ptr.len(),
+            allocator: ManuallyDrop::new(allocator),
+        }
+    }
+    fn into_normal(self) -> Self::Normal {
+        // SAFETY: we construct a Box for the same object as our SBoxSlice, and then forget the original to avoid a double-free.
+        // The allocator is moved out with a bitwise copy, the same as in SBox.
+        let copy = unsafe {
+            Box::from_raw_in(
+                slice_from_raw_parts_mut(self.ptr as *mut T, self.len),
+                std::ptr::read(&*self.allocator),
+            )
+        };
+
+        forget(self);
+
+        copy
+    }
+}
+
+impl<T> SBoxSlice<T, SGlobal> {
+    /// Converts a `Box<[T], Global>` to `SBoxSlice`, converting the allocator to `SGlobal` like [`SBox::from_box`][super::SBox::from_box]
+    pub fn from_box(value: Box<[T], std::alloc::Global>) -> Self {
+        let ptr = Box::into_raw(value);
+
+        Self {
+            ptr: ptr as *const T,
+            len: ptr.len(),
+            allocator: ManuallyDrop::new(SGlobal::new()),
+        }
+    }
+}
+
+impl<T, A: Allocator> SBoxSlice<T, A> {
+    pub fn into_vec(self) -> SVec<T, A> {
+        SVec::from_normal(self.into_normal().into_vec())
+    }
+}
+
+impl<A: Allocator> FfiSafeEquivalent for SBoxStr<A> {
+    type Normal = Box<str, A>;
+
+    fn from_normal(normal: Self::Normal) -> Self {
+        let (ptr, allocator) = Box::into_raw_with_allocator(normal);
+
+        // SAFETY: str has the same layout as [u8]
+        Self {
+            inner: SBoxSlice::from_normal(unsafe { Box::from_raw_in(ptr as *mut [u8], allocator) }),
+        }
+    }
+    fn into_normal(self) -> Self::Normal {
+        let (ptr, allocator) = Box::into_raw_with_allocator(self.inner.into_normal());
+
+        // SAFETY: the contents are always valid UTF-8
+        unsafe { Box::from_raw_in(ptr as *mut str, allocator) }
+    }
+}
+
+impl SBoxStr<SGlobal> {
+    /// Converts a `Box<str, Global>` to `SBoxStr`, converting the allocator to `SGlobal` like [`SBox::from_box`][super::SBox::from_box]
+    pub fn from_box(value: Box<str, std::alloc::Global>) -> Self {
+        Self {
+            inner: SBoxSlice::from_box(value.into_boxed_bytes()),
+        }
+    }
+    pub fn into_string(self) -> SString {
+        // SAFETY: the contents are always valid UTF-8
+        unsafe { SString::from_utf8_unchecked(self.inner.into_vec()) }
+    }
+}
+
+impl<A: Allocator> SBoxStr<A> {
+    /// # Safety
+    ///
+    /// `bytes` must be valid UTF-8
+    pub unsafe fn from_utf8_unchecked(bytes: SBoxSlice<u8, A>) -> Self {
+        Self { inner: bytes }
+    }
+    pub fn as_str(&self) -> &str {
+        // SAFETY: the contents are always valid UTF-8
+        unsafe { std::str::from_utf8_unchecked(&self.inner) }
+    }
+    pub fn as_mut_str(&mut self) -> &mut str {
+        // SAFETY: the contents are always valid UTF-8
+        unsafe { std::str::from_utf8_unchecked_mut(&mut self.inner) }
+    }
+}
+
+impl<T, A: Allocator> Drop for SBoxSlice<T, A> {
+    fn drop(&mut self) {
+        // SAFETY: We make a Box for the same object as our SBoxSlice and drop it.
+        unsafe { std::ptr::read(self) }.into_normal();
+    }
+}
+
+impl<T, A: Allocator> AsMut<[T]> for SBoxSlice<T, A> {
+    fn as_mut(&mut self) -> &mut [T] {
+        // SAFETY: the pointer points to `len` valid elements as long as the SBoxSlice lives
+        unsafe { std::slice::from_raw_parts_mut(self.ptr as *mut T, self.len) }
+    }
+}
+
+impl<T, A: Allocator> AsRef<[T]> for SBoxSlice<T, A> {
+    fn as_ref(&self) -> &[T] {
+        // SAFETY: the pointer points to `len` valid elements as long as the SBoxSlice lives
+        unsafe { std::slice::from_raw_parts(self.ptr, self.len) }
+    }
+}
+
+impl<T, A: Allocator> Borrow<[T]> for SBoxSlice<T, A> {
+    fn borrow(&self) -> &[T] {
+        self.as_ref()
+    }
+}
+
+impl<T, A: Allocator> BorrowMut<[T]> for SBoxSlice<T, A> {
+    fn borrow_mut(&mut self) -> &mut [T] {
+        self.as_mut()
+    }
+}
+
+impl<T: Clone, A: Allocator + Clone> Clone for SBoxSlice<T, A> {
+    fn clone(&self) -> Self {
+        SBoxSlice::from_normal(self.as_normal(move |b| b.clone()))
+    }
+}
+
+impl<A: Allocator + Clone> Clone for SBoxStr<A> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<T: Debug, A: Allocator> Debug for SBoxSlice<T, A> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Debug::fmt(self.as_ref(), f)
+    }
+}
+
+impl<A: Allocator> Debug for SBoxStr<A> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl<T> Default for SBoxSlice<T> {
+    fn default() -> Self {
+        SVec::new().into_boxed_slice()
+    }
+}
+
+impl Default for SBoxStr {
+    fn default() -> Self {
+        SString::new().into_boxed_str()
+    }
+}
+
+impl<T, A: Allocator> Deref for SBoxSlice<T, A> {
+    type Target = [T];
+
+    fn deref(&self) -> &Self::Target {
+        self.as_ref()
+    }
+}
+
+impl<T, A: Allocator> DerefMut for SBoxSlice<T, A> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.as_mut()
+    }
+}
+
+impl<A: Allocator> Deref for SBoxStr<A> {
+    type Target = str;
+
+    fn deref(&self) -> &Self::Target {
+        self.as_str()
+    }
+}
+
+impl<A: Allocator> DerefMut for SBoxStr<A> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.as_mut_str()
+    }
+}
+
+impl<A: Allocator> Display for SBoxStr<A> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(self.as_str(), f)
+    }
+}
+
+impl<T: Eq, A: Allocator> Eq for SBoxSlice<T, A> {}
+
+impl<A: Allocator> Eq for SBoxStr<A> {}
+
+impl<T, A: Allocator> From<Box<[T], A>> for SBoxSlice<T, A> {
+    fn from(value: Box<[T], A>) -> Self {
+        Self::from_normal(value)
+    }
+}
+
+impl<T> From<Box<[T]>> for SBoxSlice<T> {
+    fn from(value: Box<[T]>) -> Self {
+        Self::from_box(value)
+    }
+}
+
+impl<T> From<SBoxSlice<T>> for Box<[T], SGlobal> {
+    fn from(value: SBoxSlice<T>) -> Self {
+        value.into_normal()
+    }
+}
+
+impl<T, A: Allocator> From<SVec<T, A>> for SBoxSlice<T, A> {
+    fn from(value: SVec<T, A>) -> Self {
+        value.into_boxed_slice()
+    }
+}
+
+impl<T, A: Allocator> From<SBoxSlice<T, A>> for SVec<T, A> {
+    fn from(value: SBoxSlice<T, A>) -> Self {
+        value.into_vec()
+    }
+}
+
+impl<A: Allocator> From<Box<str, A>> for SBoxStr<A> {
+    fn from(value: Box<str, A>) -> Self {
+        Self::from_normal(value)
+    }
+}
+
+impl From<Box<str>> for SBoxStr {
+    fn from(value: Box<str>) -> Self {
+        Self::from_box(value)
+    }
+}
+
+impl From<SBoxStr> for Box<str, SGlobal> {
+    fn from(value: SBoxStr) -> Self {
+        value.into_normal()
+    }
+}
+
+impl From<&str> for SBoxStr {
+    fn from(value: &str) -> Self {
+        SString::from(value).into_boxed_str()
+    }
+}
+
+impl From<SString> for SBoxStr {
+    fn from(value: SString) -> Self {
+        value.into_boxed_str()
+    }
+}
+
+impl From<SBoxStr> for SString {
+    fn from(value: SBoxStr) -> Self {
+        value.into_string()
+    }
+}
+
+impl<T: Hash, A: Allocator> Hash for SBoxSlice<T, A> {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        Hash::hash(&**self, state)
+    }
+}
+
+impl<A: Allocator> Hash for SBoxStr<A> {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        Hash::hash(self.as_str(), state)
+    }
+}
+
+impl<T, I: std::slice::SliceIndex<[T]>, A: Allocator> Index<I> for SBoxSlice<T, A> {
+    type Output = <I as std::slice::SliceIndex<[T]>>::Output;
+
+    fn index(&self, index: I) -> &Self::Output {
+        Index::index(&**self, index)
+    }
+}
+
+impl<T, I: std::slice::SliceIndex<[T]>, A: Allocator> IndexMut<I> for SBoxSlice<T, A> {
+    fn index_mut(&mut self, index: I) -> &mut Self::Output {
+        IndexMut::index_mut(&mut **self, index)
+    }
+}
+
+impl<'a, T, A: Allocator> IntoIterator for &'a SBoxSlice<T, A> {
+    type Item = &'a T;
+
+    type IntoIter = std::slice::Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, T, A: Allocator> IntoIterator for &'a mut SBoxSlice<T, A> {
+    type Item = &'a mut T;
+
+    type IntoIter = std::slice::IterMut<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+impl<T: Ord, A: Allocator> Ord for SBoxSlice<T, A> {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        Ord::cmp(&**self, &**other)
+    }
+}
+
+impl<A: Allocator> Ord for SBoxStr<A> {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        Ord::cmp(self.as_str(), other.as_str())
+    }
+}
+
+impl<T: PartialEq, A: Allocator> PartialEq for SBoxSlice<T, A> {
+    fn eq(&self, other: &Self) -> bool {
+        PartialEq::eq(&**self, &**other)
+    }
+}
+
+impl<A: Allocator> PartialEq for SBoxStr<A> {
+    fn eq(&self, other: &Self) -> bool {
+        PartialEq::eq(self.as_str(), other.as_str())
+    }
+}
+
+impl<A: Allocator> PartialEq<str> for SBoxStr<A> {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl<A: Allocator> PartialEq<&str> for SBoxStr<A> {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl<T: PartialOrd, A: Allocator> PartialOrd for SBoxSlice<T, A> {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        PartialOrd::partial_cmp(&**self, &**other)
+    }
+}
+
+impl<A: Allocator> PartialOrd for SBoxStr<A> {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+unsafe impl<T: Send, A: Allocator + Send> Send for SBoxSlice<T, A> {}
+unsafe impl<T: Sync, A: Allocator + Sync> Sync for SBoxSlice<T, A> {}
+
+#[cfg(test)]
+mod tests {
+    use crate::{types::SVec, TypeInfo};
+
+    #[test]
+    fn boxed_slices() {
+        use crate::types::{SBoxSlice, SBoxStr, SString};
+
+        let boxed = SVec::from_vec(vec![1u32, 2, 3]).into_boxed_slice();
+        assert_eq!(&*boxed, &[1, 2, 3]);
+        assert_eq!(boxed.clone().into_vec(), boxed.into_vec());
+
+        let from_box: SBoxSlice<u8> = vec![4, 5].into_boxed_slice().into();
+        assert_eq!(&*from_box, &[4, 5]);
+
+        let string: SBoxStr = Box::<str>::from("hello").into();
+        assert_eq!(string, "hello");
+        assert_eq!(SString::from("hello").into_boxed_str(), string);
+        assert_eq!(string.into_string(), "hello");
+
+        assert_eq!(size_of::<SBoxSlice<u8>>(), 3 * size_of::<usize>());
+        assert!(!<SBoxStr>::layout().is_compatible_with(&SString::layout()));
+    }
+}
@@ -0,0 +1,257 @@
+use super::{CChar, FfiSafeEquivalent, SStr, SString, SVec};
+use crate::{NicheOptimized, TypeInfo};
+use std::{
+    ffi::{c_char, CStr, CString, FromBytesWithNulError, NulError},
+    fmt::Debug,
+    marker::PhantomData,
+    ops::Deref,
+    ptr::NonNull,
+    str::Utf8Error,
+};
+
+/// FFI-safe equivalent of [`&'a CStr`][CStr]
+///
+/// Unlike [`SStr`], this is a thin pointer to a NUL-terminated string, so it can be passed
+/// directly to C functions expecting a `const char*`.
+#[repr(transparent)]
+#[derive(TypeInfo, Clone, Copy)]
+pub struct SCStr<'a> {
+    ptr: NonNull<CChar>,
+    _phantom: PhantomData<&'a CStr>,
+}
+
+/// FFI-safe equivalent of [`CString`]
+///
+/// The bytes are stored together with the NUL terminator, so [`SCString::as_ptr`] can be passed to C directly.
+#[repr(C)]
+#[derive(TypeInfo, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SCString {
+    inner: SVec<u8>,
+}
+
+impl<'a> FfiSafeEquivalent for SCStr<'a> {
+    type Normal = &'a CStr;
+
+    fn from_normal(normal: Self::Normal) -> Self {
+        Self::new(normal)
+    }
+    fn into_normal(self) -> Self::Normal {
+        self.to_cstr()
+    }
+}
+
+impl<'a> SCStr<'a> {
+    pub const fn new(normal: &'a CStr) -> Self {
+        Self {
+            // SAFETY: references are never null
+            ptr: unsafe { NonNull::new_unchecked(normal.as_ptr() as *mut CChar) },
+            _phantom: PhantomData,
+        }
+    }
+    /// # Safety
+    ///
+    /// `ptr` must point to a NUL-terminated string valid for `'a`, see [`CStr::from_ptr`].
+    pub const unsafe fn from_ptr(ptr: NonNull<c_char>) -> Self {
+        Self {
+            ptr: ptr.cast(),
+            _phantom: PhantomData,
+        }
+    }
+    pub const fn as_ptr(&self) -> *const c_char {
+        self.ptr.as_ptr().cast()
+    }
+    pub fn to_cstr(&self) -> &'a CStr {
+        // SAFETY: the pointer always points to a NUL-terminated string valid for 'a
+        unsafe { CStr::from_ptr(self.as_ptr()) }
+    }
+    /// Converts to an [`SStr`] (without the NUL terminator) if the string is valid UTF-8
+    pub fn to_sstr(&self) -> Result<SStr<'a>, Utf8Error> {
+        self.to_cstr().to_str().map(SStr::new)
+    }
+}
+
+impl FfiSafeEquivalent for SCString {
+    type Normal = CString;
+
+    fn from_normal(normal: Self::Normal) -> Self {
+        Self {
+            inner: SVec::from_vec(normal.into_bytes_with_nul()),
+        }
+    }
+    fn into_normal(self) -> Self::Normal {
+        // The buffer might have been allocated by a different global allocator,
+        // and `CString` has no way to carry it along, so the contents have to be copied.
+        self.as_cstr().to_owned()
+    }
+}
+
+impl SCString {
+    /// Fails if `value` contains a NUL byte
+    pub fn new(value: impl Into<Vec<u8>>) -> Result<Self, NulError> {
+        CString::new(value).map(Self::from_normal)
+    }
+    pub fn as_cstr(&self) -> &CStr {
+        // SAFETY: the bytes always end with the only NUL
+        unsafe { CStr::from_bytes_with_nul_unchecked(&self.inner) }
+    }
+    pub fn as_scstr(&self) -> SCStr<'_> {
+        SCStr::new(self.as_cstr())
+    }
+    pub fn as_ptr(&self) -> *const c_char {
+        self.inner.as_ptr().cast()
+    }
+    /// Converts to an [`SString`] (without the NUL terminator) if the string is valid UTF-8, without copying
+    pub fn into_sstring(self) -> Result<SString, Utf8Error> {
+        let mut bytes = self.inner;
+        bytes.as_normal_mut(|bytes| bytes.pop());
+
+        SString::from_utf8(bytes)
+    }
+}
+
+impl<'a> Debug for SCStr<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Debug::fmt(self.to_cstr(), f)
+    }
+}
+
+impl Debug for SCString {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Debug::fmt(self.as_cstr(), f)
+    }
+}
+
+impl Default for SCString {
+    fn default() -> Self {
+        Self::from_normal(CString::default())
+    }
+}
+
+impl<'a> Deref for SCStr<'a> {
+    type Target = CStr;
+
+    fn deref(&self) -> &Self::Target {
+        self.to_cstr()
+    }
+}
+
+impl Deref for SCString {
+    type Target = CStr;
+
+    fn deref(&self) -> &Self::Target {
+        self.as_cstr()
+    }
+}
+
+impl<'a> PartialEq for SCStr<'a> {
+    fn eq(&self, other: &Self) -> bool {
+        self.to_cstr() == other.to_cstr()
+    }
+}
+
+impl<'a> Eq for SCStr<'a> {}
+
+impl<'a> From<&'a CStr> for SCStr<'a> {
+    fn from(value: &'a CStr) -> Self {
+        Self::from_normal(value)
+    }
+}
+
+impl<'a> From<&'a SCString> for SCStr<'a> {
+    fn from(value: &'a SCString) -> Self {
+        value.as_scstr()
+    }
+}
+
+/// The string must end with a NUL byte, and contain no other NUL bytes
+impl<'a> TryFrom<SStr<'a>> for SCStr<'a> {
+    type Error = FromBytesWithNulError;
+
+    fn try_from(value: SStr<'a>) -> Result<Self, Self::Error> {
+        let value: &'a str = value.into_normal();
+
+        CStr::from_bytes_with_nul(value.as_bytes()).map(Self::new)
+    }
+}
+
+impl<'a> TryFrom<SCStr<'a>> for SStr<'a> {
+    type Error = Utf8Error;
+
+    fn try_from(value: SCStr<'a>) -> Result<Self, Self::Error> {
+        value.to_sstr()
+    }
+}
+
+impl From<CString> for SCString {
+    fn from(value: CString) -> Self {
+        Self::from_normal(value)
+    }
+}
+
+impl<'a> From<SCStr<'a>> for SCString {
+    fn from(value: SCStr<'a>) -> Self {
+        Self::from_normal(value.to_cstr().to_owned())
+    }
+}
+
+impl From<SCString> for CString {
+    fn from(value: SCString) -> Self {
+        value.into_normal()
+    }
+}
+
+impl TryFrom<SString> for SCString {
+    type Error = NulError;
+
+    fn try_from(value: SString) -> Result<Self, Self::Error> {
+        Self::new(value.as_bytes())
+    }
+}
+
+impl<'a> TryFrom<SStr<'a>> for SCString {
+    type Error = NulError;
+
+    fn try_from(value: SStr<'a>) -> Result<Self, Self::Error> {
+        Self::new(value.as_bytes())
+    }
+}
+
+impl TryFrom<SCString> for SString {
+    type Error = Utf8Error;
+
+    fn try_from(value: SCString) -> Result<Self, Self::Error> {
+        value.into_sstring()
+    }
+}
+
+unsafe impl<'a> NicheOptimized for SCStr<'a> {}
+
+unsafe impl<'a> Send for SCStr<'a> {}
+unsafe impl<'a> Sync for SCStr<'a> {}
+
+#[cfg(test)]
+mod tests {
+    use crate::{types::SStr, TypeInfo};
+
+    #[test]
+    fn c_strings() {
+        use crate::types::{SCStr, SCString, SString};
+        use std::ffi::c_char;
+
+        let scstr = SCStr::new(c"hello");
+        assert_eq!(scstr.to_sstr(), Ok(SStr::new("hello")));
+        assert_eq!(SCStr::try_from(SStr::new("hi\0")).unwrap().to_cstr(), c"hi");
+        assert!(SCStr::try_from(SStr::new("hi")).is_err());
+        assert!(SCStr::new(c"\xff").to_sstr().is_err());
+
+        let scstring = SCString::try_from(SString::from("owned")).unwrap();
+        assert_eq!(scstring.as_scstr(), SCStr::new(c"owned"));
+        assert_eq!(scstring.clone().into_sstring().unwrap(), "owned");
+        assert!(SCString::try_from(SStr::new("a\0b")).is_err());
+
+        assert_eq!(size_of::<SCStr>(), size_of::<*const c_char>());
+        assert_eq!(size_of::<Option<SCStr>>(), size_of::<*const c_char>());
+        assert!(!SCStr::layout().is_compatible_with(&<*const c_char>::layout()));
+        assert_ne!(SCString::layout(), SString::layout());
+    }
+}
@@ -0,0 +1,126 @@
+use super::FfiSafeEquivalent;
+use crate::{
+    _TypeInfoImpl, id,
+    layout::{CType, DefinedTypes, Fingerprinter, FullLayout, Layout},
+    types::SBox,
+    TypeUid,
+};
+use std::ffi::{
+    c_char, c_int, c_long, c_longlong, c_schar, c_short, c_uchar, c_uint, c_ulong, c_ulonglong,
+    c_ushort,
+};
+
+macro_rules! c_types {
+    ( $( $(#[$attr:meta])* $name:ident($ty:ty) = $ctype:ident ),* ) => {
+        $(
+            $(#[$attr])*
+            #[repr(transparent)]
+            #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+            pub struct $name(pub $ty);
+
+            impl FfiSafeEquivalent for $name {
+                type Normal = $ty;
+
+                fn from_normal(normal: Self::Normal) -> Self {
+                    Self(normal)
+                }
+                fn into_normal(self) -> Self::Normal {
+                    self.0
+                }
+            }
+
+            impl From<$ty> for $name {
+                fn from(value: $ty) -> Self {
+                    Self(value)
+                }
+            }
+
+            impl From<$name> for $ty {
+                fn from(value: $name) -> Self {
+                    value.0
+                }
+            }
+
+            unsafe impl _TypeInfoImpl for $name {
+                const _UID: TypeUid = id!($name);
+                const _FINGERPRINT: u128 = Fingerprinter::new()
+                    .str("C")
+                    .str(stringify!($ctype))
+                    .u128(<$ty>::_FINGERPRINT)
+                    .finish();
+
+                fn _layout_impl(defined_types: DefinedTypes) -> FullLayout {
+                    let FullLayout {
+                        layout,
+                        defined_types,
+                    } = <$ty>::_layout_impl(defined_types);
+
+                    FullLayout {
+                        layout: Layout::C {
+                            ty: CType::$ctype,
+                            layout: SBox::new(layout),
+                        },
+                        defined_types,
+                    }
+                }
+            }
+        )*
+    };
+}
+
+c_types!(
+    /// FFI-safe `char`, which is signed or unsigned depending on the target
+    CChar(c_char) = Char,
+    /// FFI-safe `signed char`
+    CSChar(c_schar) = SChar,
+    /// FFI-safe `unsigned char`
+    CUChar(c_uchar) = UChar,
+    /// FFI-safe `short`
+    CShort(c_short) = Short,
+    /// FFI-safe `unsigned short`
+    CUShort(c_ushort) = UShort,
+    /// FFI-safe `int`
+    CInt(c_int) = Int,
+    /// FFI-safe `unsigned int`
+    CUInt(c_uint) = UInt,
+    /// FFI-safe `long`, which is 32-bit on Windows and 64-bit on most other 64-bit targets
+    CLong(c_long) = Long,
+    /// FFI-safe `unsigned long`, which is 32-bit on Windows and 64-bit on most other 64-bit targets
+    CULong(c_ulong) = ULong,
+    /// FFI-safe `long long`
+    CLongLong(c_longlong) = LongLong,
+    /// FFI-safe `unsigned long long`
+    CULongLong(c_ulonglong) = ULongLong,
+    /// FFI-safe `size_t`
+    CSizeT(usize) = SizeT,
+    /// FFI-safe `ssize_t`
+    CSSizeT(isize) = SSizeT
+);
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        fingerprint,
+        layout::{CType, Layout},
+        types::SBox,
+        TypeInfo,
+    };
+
+    #[test]
+    fn c_types() {
+        use crate::types::{CChar, CLong};
+        use std::ffi::{c_char, c_long};
+
+        assert_eq!(
+            CLong::layout().layout,
+            Layout::C {
+                ty: CType::Long,
+                layout: SBox::new(c_long::layout().layout),
+            }
+        );
+        assert!(!CLong::layout().is_compatible_with(&c_long::layout()));
+        assert!(!CChar::layout().is_compatible_with(&c_char::layout()));
+
+        const _: () = assert!(fingerprint::<CLong>() != fingerprint::<c_long>());
+    }
+}
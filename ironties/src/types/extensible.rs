@@ -0,0 +1,165 @@
+use crate::{
+    dynamic::size_of_layout,
+    layout::{DefinedType, Layout, TypeType},
+    TypeInfo,
+};
+use std::{
+    marker::PhantomData,
+    mem::{offset_of, size_of, MaybeUninit},
+    ptr::NonNull,
+};
+
+/// A `#[repr(C)]` value prefixed with its size, for structs that evolve by appending fields
+///
+/// When a struct only ever gains new fields at the end (see [`TypeLayout::extends`][crate::TypeLayout::extends]),
+/// an older producer creates a smaller value than what a newer consumer expects. The size header
+/// allows the consumer to find out which of the fields are actually present, through an [`ExtensibleRef`].
+///
+/// The size doesn't include trailing padding, since a newer version of the struct may put fields there.
+#[repr(C)]
+#[derive(TypeInfo, Debug, Clone, Copy)]
+pub struct Extensible<T> {
+    size: usize,
+    value: T,
+}
+
+/// A reference to an [`Extensible<T>`] which might have been created with an older, shorter version of `T`
+///
+/// Use the [`extensible_field!`][crate::extensible_field] macro to access the fields of `T`,
+/// which returns `None` for fields that the producer of the value didn't know about.
+#[repr(transparent)]
+#[derive(TypeInfo)]
+pub struct ExtensibleRef<'a, T> {
+    ptr: NonNull<Extensible<T>>,
+    _phantom: PhantomData<&'a Extensible<T>>,
+}
+
+impl<T: TypeInfo> Extensible<T> {
+    pub fn new(value: T) -> Self {
+        Self {
+            size: unpadded_size::<T>(),
+            value,
+        }
+    }
+}
+
+impl<T> Extensible<T> {
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+}
+
+impl<'a, T> ExtensibleRef<'a, T> {
+    pub const fn new(extensible: &'a Extensible<T>) -> Self {
+        Self {
+            // SAFETY: references are never null
+            ptr: unsafe { NonNull::new_unchecked(extensible as *const _ as *mut _) },
+            _phantom: PhantomData,
+        }
+    }
+    /// # Safety
+    ///
+    /// `ptr` must point to an [`Extensible<U>`] valid for `'a`, where the layout of `T` [extends][crate::TypeLayout::extends] the layout of `U`.
+    pub const unsafe fn from_ptr(ptr: NonNull<Extensible<T>>) -> Self {
+        Self {
+            ptr,
+            _phantom: PhantomData,
+        }
+    }
+    /// The size of the whole [`Extensible`] as reported by it's producer, up to the end of the last field
+    pub fn size(&self) -> usize {
+        // SAFETY: the size header is present in all versions
+        unsafe { self.ptr.cast::<usize>().read() }
+    }
+    #[doc(hidden)]
+    #[allow(clippy::missing_safety_doc)]
+    pub unsafe fn _field<F>(&self, project: impl FnOnce(*const T) -> *const F) -> Option<&'a F> {
+        // Find out the offset of the field using a value which is guaranteed to be complete,
+        // the offset of the value itself doesn't change since extending layouts keep the same alignment
+        let dummy = MaybeUninit::<T>::uninit();
+        let field_offset = project(dummy.as_ptr()) as usize - dummy.as_ptr() as usize;
+        let offset = offset_of!(Extensible<T>, value) + field_offset;
+
+        if offset + size_of::<F>() > self.size() {
+            return None;
+        }
+
+        Some(&*self.ptr.as_ptr().cast::<u8>().add(offset).cast::<F>())
+    }
+}
+
+impl<'a, T: TypeInfo> ExtensibleRef<'a, T> {
+    /// Returns the value if it has all the fields of `T`
+    pub fn get(&self) -> Option<&'a T> {
+        if self.size() < unpadded_size::<T>() {
+            return None;
+        }
+
+        // SAFETY: the value is complete
+        Some(unsafe { &self.ptr.as_ref().value })
+    }
+}
+
+/// The size of an [`Extensible<T>`] up to the end of the last field of `T`
+fn unpadded_size<T: TypeInfo>() -> usize {
+    fn end(defined_types: &[DefinedType], layout: &Layout) -> usize {
+        let Layout::DefinedType { id } = layout else {
+            return size_of_layout(defined_types, layout);
+        };
+        match &defined_types[*id].ty {
+            TypeType::StructNamed { fields } => fields
+                .iter()
+                .map(|f| f.offset + end(defined_types, &f.layout))
+                .max(),
+            TypeType::StructUnnamed { fields } => fields
+                .iter()
+                .map(|f| f.offset + end(defined_types, &f.layout))
+                .max(),
+            _ => Some(defined_types[*id].size),
+        }
+        .unwrap_or(0)
+    }
+
+    let layout = T::layout_ref();
+    offset_of!(Extensible<T>, value) + end(&layout.defined_types, &layout.layout)
+}
+
+/// Accesses a field of the value behind an [`ExtensibleRef`][crate::types::ExtensibleRef], returning
+/// `None` if the producer of the value had an older version of the struct without it.
+///
+/// ```
+/// # use ironties::{TypeInfo, types::{Extensible, ExtensibleRef}, extensible_field};
+/// #[repr(C)]
+/// #[derive(TypeInfo)]
+/// struct VTable {
+///     a: u32,
+/// }
+///
+/// let vtable = Extensible::new(VTable { a: 5 });
+/// let r = ExtensibleRef::new(&vtable);
+///
+/// assert_eq!(extensible_field!(r, a), Some(&5));
+/// ```
+#[macro_export]
+macro_rules! extensible_field {
+    ($extensible_ref:expr, $field:ident) => {
+        match &$extensible_ref {
+            // SAFETY: the closure only projects the pointer to one of the fields
+            extensible_ref => unsafe {
+                $crate::types::ExtensibleRef::_field(extensible_ref, |value| {
+                    ::core::ptr::addr_of!((*value).$field)
+                })
+            },
+        }
+    };
+}
+
+impl<'a, T> Clone for ExtensibleRef<'a, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+impl<'a, T> Copy for ExtensibleRef<'a, T> {}
+
+unsafe impl<'a, T: Sync> Send for ExtensibleRef<'a, T> {}
+unsafe impl<'a, T: Sync> Sync for ExtensibleRef<'a, T> {}
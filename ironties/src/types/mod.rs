@@ -1,19 +1,36 @@
 pub mod allocator;
 mod r#box;
+mod box_slice;
+mod cstr;
+mod ctypes;
+mod extensible;
 mod maybe_panicked;
+mod non_exhaustive;
 mod option;
+mod rel;
 mod result;
 mod slice;
 mod str;
+mod string;
 mod tuple;
 mod vec;
 
 pub use self::str::{SMutStr, SStr};
+pub use box_slice::{SBoxSlice, SBoxStr};
+pub use cstr::{SCStr, SCString};
+pub use ctypes::{
+    CChar, CInt, CLong, CLongLong, CSChar, CSSizeT, CShort, CSizeT, CUChar, CUInt, CULong,
+    CULongLong, CUShort,
+};
+pub use extensible::{Extensible, ExtensibleRef};
 pub use maybe_panicked::MaybePanicked;
+pub use non_exhaustive::{MaybeKnown, SNonExhaustive};
 pub use option::SOption;
 pub use r#box::SBox;
+pub use rel::{RelPtr, RelSlice, RelStr};
 pub use result::SResult;
 pub use slice::{SMutSlice, SSlice};
+pub use string::SString;
 pub use tuple::*;
 pub use vec::SVec;
 
@@ -53,3 +70,29 @@ pub trait FfiSafeEquivalent: Sized {
         r
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::types::{SBox, SStr, SVec};
+
+    #[test]
+    fn auto_traits() {
+        use crate::types::{SBoxSlice, SCStr, SCString, SMutSlice, SSlice, SString};
+        use std::cell::Cell;
+
+        fn assert_send<T: Send>() {}
+        fn assert_sync<T: Sync>() {}
+
+        assert_send::<SSlice<u8>>();
+        assert_sync::<SSlice<u8>>();
+        assert_send::<SMutSlice<Cell<u8>>>();
+        assert_send::<SBox<Cell<u8>>>();
+        assert_send::<SVec<Cell<u8>>>();
+        assert_send::<SBoxSlice<Cell<u8>>>();
+        assert_sync::<SVec<u8>>();
+        assert_send::<SStr>();
+        assert_sync::<SString>();
+        assert_send::<SCStr>();
+        assert_sync::<SCString>();
+    }
+}
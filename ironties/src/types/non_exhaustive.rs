@@ -0,0 +1,223 @@
+use crate::{
+    _TypeInfoImpl,
+    dynamic::read_discriminant,
+    id,
+    layout::{
+        DefinedType, DefinedTypes, Discriminant, Fingerprinter, FullLayout, Layout, TypeType,
+    },
+    types::SBox,
+    TypeInfo, TypeUid,
+};
+use std::{
+    fmt::Debug,
+    mem::{needs_drop, ManuallyDrop, MaybeUninit},
+};
+
+/// FFI-safe wrapper for an enum which might have been produced by code that knows about more variants
+///
+/// Receiving an enum value with a discriminant that doesn't belong to any of its variants is
+/// undefined behaviour, which is exactly what happens when a newer plugin sends a newly added variant to an older host.
+/// This wrapper stores the value as raw bytes, and only gives access to it as `E` after checking
+/// that the discriminant is one of the variants listed in `E`'s layout.
+///
+/// In [`TypeLayout`][crate::TypeLayout]s the enum is recorded as open, so adding variants is considered
+/// [compatible][crate::TypeLayout::is_compatible_with], as long as that doesn't change the size of the enum.
+///
+/// If the value is of an unknown variant, it is never dropped, since there is no way to know how.
+#[repr(transparent)]
+pub struct SNonExhaustive<E: TypeInfo> {
+    value: MaybeUninit<E>,
+}
+
+/// The contents of an [`SNonExhaustive`]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum MaybeKnown<T> {
+    Known(T),
+    /// A variant which is not present in the enum, with the given discriminant
+    Unknown(Discriminant),
+}
+
+impl<E: TypeInfo> SNonExhaustive<E> {
+    pub const fn new(value: E) -> Self {
+        Self {
+            value: MaybeUninit::new(value),
+        }
+    }
+    /// Returns the raw discriminant of the value
+    pub fn discriminant(&self) -> Discriminant {
+        self.check().0
+    }
+    pub fn get(&self) -> MaybeKnown<&E> {
+        match self.check() {
+            // SAFETY: the discriminant belongs to one of the variants
+            (_, true) => MaybeKnown::Known(unsafe { self.value.assume_init_ref() }),
+            (discriminant, false) => MaybeKnown::Unknown(discriminant),
+        }
+    }
+    pub fn get_mut(&mut self) -> MaybeKnown<&mut E> {
+        match self.check() {
+            // SAFETY: the discriminant belongs to one of the variants
+            (_, true) => MaybeKnown::Known(unsafe { self.value.assume_init_mut() }),
+            (discriminant, false) => MaybeKnown::Unknown(discriminant),
+        }
+    }
+    pub fn into_inner(self) -> MaybeKnown<E> {
+        let this = ManuallyDrop::new(self);
+
+        match this.check() {
+            // SAFETY: the discriminant belongs to one of the variants, and `self` is never going to be dropped
+            (_, true) => MaybeKnown::Known(unsafe { this.value.assume_init_read() }),
+            (discriminant, false) => MaybeKnown::Unknown(discriminant),
+        }
+    }
+    /// Reads the discriminant and checks whether it belongs to any of the variants
+    fn check(&self) -> (Discriminant, bool) {
+        let layout = E::layout_ref();
+        let (repr, variants) = match &layout.layout {
+            Layout::DefinedType { id } => match &layout.defined_types[*id] {
+                DefinedType {
+                    repr,
+                    ty: TypeType::Enum { variants, .. },
+                    ..
+                } => (repr, variants),
+                _ => panic!("SNonExhaustive can only be used with enums"),
+            },
+            _ => panic!("SNonExhaustive can only be used with enums"),
+        };
+
+        // SAFETY: the value is of the enum with this repr
+        let Some(discriminant) = (unsafe { read_discriminant(repr, self.value.as_ptr().cast()) })
+        else {
+            panic!("SNonExhaustive can only be used with enums with a stable repr")
+        };
+
+        (
+            discriminant,
+            variants.iter().any(|v| v.discriminant == discriminant),
+        )
+    }
+}
+
+impl<E: TypeInfo> From<E> for SNonExhaustive<E> {
+    fn from(value: E) -> Self {
+        Self::new(value)
+    }
+}
+
+impl<E: TypeInfo + Debug> Debug for SNonExhaustive<E> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.get().fmt(f)
+    }
+}
+
+impl<E: TypeInfo + Clone> Clone for SNonExhaustive<E> {
+    fn clone(&self) -> Self {
+        match self.get() {
+            MaybeKnown::Known(value) => Self::new(value.clone()),
+            // SAFETY: values of unknown variants are never dropped, so a bitwise copy can't cause a double-free
+            MaybeKnown::Unknown(_) => Self {
+                value: unsafe { std::ptr::read(&self.value) },
+            },
+        }
+    }
+}
+
+impl<E: TypeInfo> Drop for SNonExhaustive<E> {
+    fn drop(&mut self) {
+        if !needs_drop::<E>() {
+            return;
+        }
+
+        if let MaybeKnown::Known(value) = self.get_mut() {
+            // SAFETY: the value is of a known variant and is never going to be used again
+            unsafe { std::ptr::drop_in_place(value) };
+        }
+    }
+}
+
+unsafe impl<E: TypeInfo> _TypeInfoImpl for SNonExhaustive<E> {
+    const _UID: TypeUid = id!(SNonExhaustive<E>);
+    const _FINGERPRINT: u128 = Fingerprinter::new()
+        .str("NonExhaustive")
+        .u128(E::_FINGERPRINT)
+        .finish();
+    const _SHALLOW_FINGERPRINT: u128 = Fingerprinter::new()
+        .str("NonExhaustive")
+        .u128(E::_SHALLOW_FINGERPRINT)
+        .finish();
+
+    fn _layout_impl(defined_types: DefinedTypes) -> FullLayout {
+        let FullLayout {
+            layout,
+            defined_types,
+        } = E::_layout_impl(defined_types);
+
+        FullLayout {
+            layout: Layout::NonExhaustive(SBox::new(layout)),
+            defined_types,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MaybeKnown, SNonExhaustive};
+    use crate::{layout::Discriminant, TypeInfo};
+    use std::mem::transmute;
+
+    mod v1 {
+        use crate::TypeInfo;
+
+        #[repr(u8)]
+        #[derive(TypeInfo, Debug, PartialEq, Clone)]
+        pub enum Event {
+            Start,
+            Data(u32),
+        }
+    }
+
+    mod v2 {
+        use crate::TypeInfo;
+
+        #[repr(u8)]
+        #[derive(TypeInfo, Debug, PartialEq, Clone)]
+        pub enum Event {
+            Start,
+            Data(u32),
+            Progress(u16),
+        }
+    }
+
+    #[test]
+    fn unknown_variant() {
+        assert_eq!(
+            SNonExhaustive::new(v1::Event::Start).discriminant(),
+            Discriminant::Unsigned(0)
+        );
+        assert_eq!(
+            SNonExhaustive::new(v2::Event::Start).discriminant(),
+            Discriminant::Unsigned(0)
+        );
+
+        let new = SNonExhaustive::new(v2::Event::Progress(3));
+        // SAFETY: both enums have the same size, and the unknown variant is never accessed as v1::Event
+        let old: SNonExhaustive<v1::Event> = unsafe { transmute(new) };
+        assert_eq!(old.get(), MaybeKnown::Unknown(Discriminant::Unsigned(2)));
+
+        let new = SNonExhaustive::new(v2::Event::Data(1));
+        let old: SNonExhaustive<v1::Event> = unsafe { transmute(new) };
+        assert_eq!(old.into_inner(), MaybeKnown::Known(v1::Event::Data(1)));
+    }
+
+    #[test]
+    fn open_enum_compatibility() {
+        let (old, new) = (
+            SNonExhaustive::<v1::Event>::layout(),
+            SNonExhaustive::<v2::Event>::layout(),
+        );
+
+        assert!(old.is_compatible_with(&new));
+        assert!(new.is_compatible_with(&old));
+        assert!(!v1::Event::layout().is_compatible_with(&v2::Event::layout()));
+    }
+}
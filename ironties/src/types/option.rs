@@ -4,6 +4,7 @@ use crate::TypeInfo;
 /// FFI-safe equivalent of [`Option<T>`]
 #[repr(u8)]
 #[derive(TypeInfo, Debug, PartialEq, Clone, Copy)]
+#[ironties(known = "Option")]
 pub enum SOption<T> {
     Some(T),
     None,
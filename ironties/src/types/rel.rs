@@ -0,0 +1,420 @@
+use crate::{
+    _TypeInfoImpl,
+    dynamic::{unchecked_type, validate_relative, InvalidValue},
+    id,
+    layout::{DefinedTypes, Fingerprinter, FullLayout, Layout},
+    types::SBox,
+    TypeInfo, TypeUid,
+};
+use std::{any::type_name, fmt::Debug, marker::PhantomData, mem::size_of};
+
+/// A pointer stored as an offset from its own address
+///
+/// Unlike normal pointers, relative pointers stay valid when the memory containing both them and their targets
+/// is moved, written to a file or mapped at a different address, so they can be used in position-independent data.
+///
+/// The target can only be accessed through [`get`][Self::get], which checks that it's a valid value
+/// within a buffer that contains the pointer too.
+#[repr(transparent)]
+pub struct RelPtr<T> {
+    offset: isize,
+    _phantom: PhantomData<T>,
+}
+
+/// A slice stored as a [relative pointer][RelPtr] to its elements and their number
+#[repr(C)]
+#[derive(TypeInfo)]
+#[ironties(known = "Slice")]
+pub struct RelSlice<T> {
+    ptr: RelPtr<T>,
+    len: usize,
+}
+
+/// A string stored as a [relative pointer][RelPtr] to its bytes and their number
+#[repr(C)]
+#[derive(TypeInfo)]
+#[ironties(known = "Str")]
+pub struct RelStr {
+    inner: RelSlice<u8>,
+}
+
+impl<T> RelPtr<T> {
+    /// A pointer to the address `offset` bytes away from its own
+    pub const fn from_offset(offset: isize) -> Self {
+        Self {
+            offset,
+            _phantom: PhantomData,
+        }
+    }
+    pub const fn offset(&self) -> isize {
+        self.offset
+    }
+    /// Points to `target`. The pointer stays valid only as long as the distance between them doesn't change.
+    pub fn set(&mut self, target: *const T) {
+        self.offset = (target as isize).wrapping_sub(self as *const Self as isize);
+    }
+    /// The address pointed to at the current location of the pointer
+    pub fn as_ptr(&self) -> *const T {
+        (self as *const Self)
+            .cast::<u8>()
+            .wrapping_offset(self.offset)
+            .cast()
+    }
+    /// Validates `len` values pointed to, and returns the offset of the first one in the buffer
+    fn target(&self, buffer: &[u8], len: usize) -> Result<usize, InvalidValue>
+    where
+        T: TypeInfo,
+    {
+        let layout = T::layout_ref();
+        if let Some(name) = unchecked_type(layout, type_name::<T>()) {
+            panic!(
+                "RelPtr can't point to values of `{name}`, since their validity can't be checked"
+            );
+        }
+
+        let offset = offset_in(self, buffer);
+        validate_relative(buffer, offset, layout, len)
+    }
+}
+
+impl<T: TypeInfo + Copy> RelPtr<T> {
+    /// Returns the target, after checking that it's a valid value within `buffer`
+    ///
+    /// # Panics
+    ///
+    /// If the pointer itself is not within `buffer`, or `T` contains pointers (other than relative ones),
+    /// unions or opaque types, whose validity can't be checked.
+    pub fn get<'a>(&self, buffer: &'a [u8]) -> Result<&'a T, InvalidValue> {
+        let start = self.target(buffer, 1)?;
+
+        // SAFETY: the bytes at `start` are a valid and aligned value, and since `T` is `Copy`,
+        // it has no interior mutability
+        Ok(unsafe { &*buffer.as_ptr().add(start).cast() })
+    }
+}
+
+impl<T> RelSlice<T> {
+    /// An empty slice
+    pub const fn new() -> Self {
+        Self {
+            ptr: RelPtr::from_offset(0),
+            len: 0,
+        }
+    }
+    /// Points to `elements`. The slice stays valid only as long as the distance between them doesn't change.
+    pub fn set(&mut self, elements: &[T]) {
+        self.ptr.set(elements.as_ptr());
+        self.len = elements.len();
+    }
+    pub const fn len(&self) -> usize {
+        self.len
+    }
+    pub const fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl<T: TypeInfo + Copy> RelSlice<T> {
+    /// Returns the elements, after checking that they are valid values within `buffer`
+    ///
+    /// # Panics
+    ///
+    /// Same as [`RelPtr::get`].
+    pub fn get<'a>(&self, buffer: &'a [u8]) -> Result<&'a [T], InvalidValue> {
+        if self.len == 0 {
+            return Ok(&[]);
+        }
+        let start = self.ptr.target(buffer, self.len)?;
+
+        // SAFETY: the bytes at `start` are `len` valid and aligned values, and since `T` is `Copy`,
+        // it has no interior mutability
+        Ok(unsafe { std::slice::from_raw_parts(buffer.as_ptr().add(start).cast(), self.len) })
+    }
+}
+
+impl RelStr {
+    /// An empty string
+    pub const fn new() -> Self {
+        Self {
+            inner: RelSlice::new(),
+        }
+    }
+    /// Points to `s`. The string stays valid only as long as the distance between them doesn't change.
+    pub fn set(&mut self, s: &str) {
+        self.inner.set(s.as_bytes());
+    }
+    pub const fn len(&self) -> usize {
+        self.inner.len()
+    }
+    pub const fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+    /// Returns the string, after checking that it's valid UTF-8 within `buffer`
+    ///
+    /// # Panics
+    ///
+    /// If the string itself is not within `buffer`.
+    pub fn get<'a>(&self, buffer: &'a [u8]) -> Result<&'a str, InvalidValue> {
+        let bytes = self.inner.get(buffer)?;
+
+        std::str::from_utf8(bytes).map_err(|_| InvalidValue::InvalidUtf8 {
+            offset: offset_in(self, buffer),
+        })
+    }
+}
+
+/// The offset of a value from the start of the buffer containing it
+///
+/// # Panics
+///
+/// If the value is not within the buffer.
+fn offset_in<T>(value: &T, buffer: &[u8]) -> usize {
+    let offset = (value as *const T as usize).wrapping_sub(buffer.as_ptr() as usize);
+    assert!(
+        offset
+            .checked_add(size_of::<T>())
+            .is_some_and(|end| end <= buffer.len()),
+        "the relative pointer is not within the buffer"
+    );
+
+    offset
+}
+
+impl<T> Default for RelSlice<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Default for RelStr {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Implemented manually, since deriving them would introduce unnecessary bounds on T
+impl<T> Clone for RelPtr<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+impl<T> Copy for RelPtr<T> {}
+
+impl<T> Clone for RelSlice<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+impl<T> Copy for RelSlice<T> {}
+
+impl Clone for RelStr {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+impl Copy for RelStr {}
+
+impl<T> Debug for RelPtr<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("RelPtr").field(&self.offset).finish()
+    }
+}
+
+impl<T> Debug for RelSlice<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RelSlice")
+            .field("offset", &self.ptr.offset)
+            .field("len", &self.len)
+            .finish()
+    }
+}
+
+impl Debug for RelStr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RelStr")
+            .field("offset", &self.inner.ptr.offset)
+            .field("len", &self.inner.len)
+            .finish()
+    }
+}
+
+unsafe impl<T: TypeInfo> _TypeInfoImpl for RelPtr<T> {
+    const _UID: TypeUid = id!(RelPtr<T>);
+    const _FINGERPRINT: u128 = Fingerprinter::new()
+        .str("RelPtr")
+        .u128(T::_SHALLOW_FINGERPRINT)
+        .finish();
+
+    fn _layout_impl(defined_types: DefinedTypes) -> FullLayout {
+        let FullLayout {
+            layout,
+            defined_types,
+        } = T::_layout_impl(defined_types);
+
+        FullLayout {
+            layout: Layout::RelPtr(SBox::new(layout)),
+            defined_types,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RelPtr, RelSlice, RelStr};
+    use crate::{
+        dynamic::{validate, Fields, InvalidValue, Value},
+        TypeInfo,
+    };
+    use std::mem::{size_of, size_of_val};
+
+    #[repr(C)]
+    #[derive(TypeInfo, Debug, PartialEq, Clone, Copy)]
+    struct Point {
+        x: u32,
+        y: u32,
+    }
+
+    #[repr(C)]
+    #[derive(TypeInfo)]
+    struct Shape {
+        name: RelStr,
+        points: RelSlice<Point>,
+        first: RelPtr<Point>,
+        name_bytes: [u8; 8],
+        point_data: [Point; 2],
+    }
+
+    fn bytes(shape: &Shape) -> &[u8] {
+        // SAFETY: `Shape` has no padding
+        unsafe { std::slice::from_raw_parts((shape as *const Shape).cast(), size_of::<Shape>()) }
+    }
+
+    #[test]
+    fn rel_pointers() {
+        let mut shape = Box::new(Shape {
+            name: RelStr::new(),
+            points: RelSlice::new(),
+            first: RelPtr::from_offset(0),
+            name_bytes: *b"line\0\0\0\0",
+            point_data: [Point { x: 1, y: 2 }, Point { x: 3, y: 4 }],
+        });
+        shape
+            .name
+            .set(std::str::from_utf8(&shape.name_bytes[..4]).unwrap());
+        shape.points.set(&shape.point_data);
+        shape.first.set(&shape.point_data[0]);
+
+        // Moving the value doesn't invalidate the pointers
+        let shape = Box::new(*shape);
+        let buffer = bytes(&shape);
+
+        assert_eq!(shape.name.get(buffer), Ok("line"));
+        assert_eq!(shape.points.get(buffer).unwrap(), &shape.point_data);
+        assert_eq!(shape.first.get(buffer), Ok(&Point { x: 1, y: 2 }));
+        assert_eq!(validate(buffer, Shape::layout_ref()), Ok(()));
+
+        let Ok(Value::Struct {
+            fields: Fields::Named(fields),
+            ..
+        }) = Value::of(&*shape)
+        else {
+            panic!("not a struct")
+        };
+        assert_eq!(fields[0], ("name", Value::Str("line".to_owned())));
+
+        // The contents are not within the buffer
+        assert_eq!(
+            shape.name.get(&buffer[..40]),
+            Err(InvalidValue::OutOfBounds { offset: 0 })
+        );
+        assert_eq!(
+            validate(&buffer[..40], Shape::layout_ref()),
+            Err(InvalidValue::TooShort {
+                size: size_of::<Shape>(),
+                len: 40
+            })
+        );
+    }
+
+    #[test]
+    fn invalid_contents() {
+        let mut shape = Shape {
+            name: RelStr::new(),
+            points: RelSlice::new(),
+            first: RelPtr::from_offset(-40),
+            name_bytes: [0xff; 8],
+            point_data: [Point { x: 1, y: 2 }; 2],
+        };
+        shape.name.inner.set(&shape.name_bytes);
+
+        let buffer = bytes(&shape);
+        assert_eq!(
+            shape.name.get(buffer),
+            Err(InvalidValue::InvalidUtf8 { offset: 0 })
+        );
+        assert_eq!(
+            validate(buffer, Shape::layout_ref()),
+            Err(InvalidValue::InvalidUtf8 { offset: 0 })
+        );
+        // Points before the start of the buffer
+        assert_eq!(
+            shape.first.get(buffer),
+            Err(InvalidValue::OutOfBounds { offset: 32 })
+        );
+    }
+
+    #[test]
+    #[should_panic = "not within the buffer"]
+    fn outside_buffer() {
+        let point = Point { x: 1, y: 2 };
+        let ptr = RelPtr::<Point>::from_offset(0);
+
+        let _ = ptr.get(bytes_of(&point));
+    }
+
+    fn bytes_of(point: &Point) -> &[u8] {
+        // SAFETY: `Point` has no padding
+        unsafe { std::slice::from_raw_parts((point as *const Point).cast(), size_of::<Point>()) }
+    }
+
+    #[repr(C)]
+    #[derive(TypeInfo, Clone, Copy)]
+    struct Node {
+        next: RelSlice<Node>,
+        value: u64,
+    }
+
+    /// Nodes that each point to the next one
+    fn chain(len: usize) -> Vec<Node> {
+        (0..len)
+            .map(|i| Node {
+                next: RelSlice {
+                    ptr: RelPtr::from_offset(size_of::<Node>() as isize),
+                    len: usize::from(i + 1 < len),
+                },
+                value: i as u64,
+            })
+            .collect()
+    }
+
+    fn chain_bytes(nodes: &[Node]) -> &[u8] {
+        // SAFETY: `Node` has no padding
+        unsafe { std::slice::from_raw_parts(nodes.as_ptr().cast(), size_of_val(nodes)) }
+    }
+
+    #[test]
+    fn long_chain() {
+        // Validated without overflowing the stack
+        let mut nodes = chain(20_000);
+        assert_eq!(validate(chain_bytes(&nodes), Node::layout_ref()), Ok(()));
+        assert_eq!(nodes[0].next.get(chain_bytes(&nodes)).unwrap().len(), 1);
+
+        // The last node points past the end
+        nodes.last_mut().unwrap().next.len = 1;
+        let offset = (nodes.len() - 1) * size_of::<Node>();
+        assert_eq!(
+            validate(chain_bytes(&nodes), Node::layout_ref()),
+            Err(InvalidValue::OutOfBounds { offset })
+        );
+    }
+}
@@ -10,8 +10,20 @@ use std::{
 use super::FfiSafeEquivalent;
 
 /// FFI-safe equivalent of [`&'a [T]`][slice]
+///
+/// Like `&[T]`, it can only be sent to another thread if `T` is [`Sync`]:
+///
+/// ```compile_fail
+/// # use ironties::types::SSlice;
+/// # use std::cell::Cell;
+/// fn assert_send<T: Send>(_: T) {}
+///
+/// let cells = [Cell::new(1u8)];
+/// assert_send(SSlice::new(&cells[..]));
+/// ```
 #[repr(C)]
 #[derive(TypeInfo)]
+#[ironties(known = "Slice")]
 pub struct SSlice<'a, T: 'a> {
     ptr: *const T,
     len: usize,
@@ -19,8 +31,20 @@ pub struct SSlice<'a, T: 'a> {
 }
 
 /// FFI-safe equivalent of [`&'a mut [T]`][slice]
+///
+/// Like `&mut [T]`, it can only be sent to another thread if `T` is [`Send`]:
+///
+/// ```compile_fail
+/// # use ironties::types::{FfiSafeEquivalent, SMutSlice};
+/// # use std::rc::Rc;
+/// fn assert_send<T: Send>(_: T) {}
+///
+/// let mut rcs = [Rc::new(1u8)];
+/// assert_send(SMutSlice::from_normal(&mut rcs[..]));
+/// ```
 #[repr(C)]
 #[derive(TypeInfo)]
+#[ironties(known = "Slice")]
 pub struct SMutSlice<'a, T: 'a> {
     ptr: *mut T,
     len: usize,
@@ -46,9 +70,9 @@ impl<'a, T> SSlice<'a, T> {
             _phantom: PhantomData,
         }
     }
-    pub fn to_slice<'b>(&'b self) -> &'b [T] {
-        // SAFETY: since 'a strictly outlives 'b, it is safe to assume that
-        // the slice is valid for the lifetime of 'b
+    pub fn to_slice(&self) -> &[T] {
+        // SAFETY: the slice is valid for 'a, and the returned reference borrows self,
+        // so it can't outlive 'a
         unsafe { std::slice::from_raw_parts(self.ptr, self.len) }
     }
 }
@@ -69,14 +93,14 @@ impl<'a, T> FfiSafeEquivalent for SMutSlice<'a, T> {
 }
 
 impl<'a, T> SMutSlice<'a, T> {
-    pub fn to_slice<'b>(&'b self) -> &'b [T] {
-        // SAFETY: since 'a strictly outlives 'b, it is safe to assume that
-        // the slice is valid for the lifetime of 'b
+    pub fn to_slice(&self) -> &[T] {
+        // SAFETY: the slice is valid for 'a, and the returned reference borrows self,
+        // so it can't outlive 'a
         unsafe { std::slice::from_raw_parts(self.ptr, self.len) }
     }
-    pub fn to_slice_mut<'b>(&'b mut self) -> &'b mut [T] {
-        // SAFETY: since 'a strictly outlives 'b, it is safe to assume that
-        // the slice is valid for the lifetime of 'b
+    pub fn to_slice_mut(&mut self) -> &mut [T] {
+        // SAFETY: the slice is valid for 'a, and the returned reference borrows self mutably,
+        // so it can't outlive 'a or alias another reference to the slice
         unsafe { std::slice::from_raw_parts_mut(self.ptr, self.len) }
     }
 }
@@ -85,7 +109,7 @@ impl<'a, T> SMutSlice<'a, T> {
 // Is because deriving them automatically introduces unnecessary generic bounds on T.
 impl<'a, T> Clone for SSlice<'a, T> {
     fn clone(&self) -> Self {
-        Self { ..*self }
+        *self
     }
 }
 impl<'a, T> Copy for SSlice<'a, T> {}
@@ -319,7 +343,7 @@ impl<'a> Write for SMutSlice<'a, u8> {
     }
 }
 
-unsafe impl<'a, T> Send for SSlice<'a, T> {}
-unsafe impl<'a, T> Sync for SSlice<'a, T> {}
-unsafe impl<'a, T> Send for SMutSlice<'a, T> {}
-unsafe impl<'a, T> Sync for SMutSlice<'a, T> {}
+unsafe impl<'a, T: Sync> Send for SSlice<'a, T> {}
+unsafe impl<'a, T: Sync> Sync for SSlice<'a, T> {}
+unsafe impl<'a, T: Send> Send for SMutSlice<'a, T> {}
+unsafe impl<'a, T: Sync> Sync for SMutSlice<'a, T> {}
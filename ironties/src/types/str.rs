@@ -8,6 +8,7 @@ use std::{
 /// FFI-safe equivalent of [`&'a str`][str]
 #[repr(C)]
 #[derive(TypeInfo, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[ironties(known = "Str")]
 pub struct SStr<'a> {
     inner: SSlice<'a, u8>,
 }
@@ -15,6 +16,7 @@ pub struct SStr<'a> {
 /// FFI-safe equivalent of [`&'a mut str`][str]
 #[repr(C)]
 #[derive(TypeInfo)]
+#[ironties(known = "Str")]
 pub struct SMutStr<'a> {
     inner: SMutSlice<'a, u8>,
 }
@@ -36,7 +38,9 @@ impl<'a> SStr<'a> {
             inner: SSlice::new(normal.as_bytes()),
         }
     }
-    pub fn to_str<'b>(&'b self) -> &'b str {
+    pub fn to_str(&self) -> &str {
+        // SAFETY: the string was created from a `&'a str`, and the returned reference
+        // borrows self, so it can't outlive 'a
         unsafe { std::str::from_utf8_unchecked(self.inner.into_normal()) }
     }
 }
@@ -56,9 +60,11 @@ impl<'a> FfiSafeEquivalent for SMutStr<'a> {
 
 impl<'a> SMutStr<'a> {
     pub fn to_str(&self) -> &str {
+        // SAFETY: the string was created from a `&'a mut str`, so its bytes are valid UTF-8
         unsafe { std::str::from_utf8_unchecked(self.inner.to_slice()) }
     }
     pub fn to_str_mut(&mut self) -> &mut str {
+        // SAFETY: the string was created from a `&'a mut str`, so its bytes are valid UTF-8
         unsafe { std::str::from_utf8_unchecked_mut(self.inner.to_slice_mut()) }
     }
 }
@@ -0,0 +1,165 @@
+use super::{FfiSafeEquivalent, SBoxStr, SStr, SVec};
+use crate::TypeInfo;
+use std::{
+    borrow::Borrow,
+    fmt::{Debug, Display},
+    ops::{Deref, DerefMut, Index, IndexMut},
+};
+
+/// FFI-safe equivalent of [`String`]
+#[repr(C)]
+#[derive(TypeInfo, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[ironties(known = "Str")]
+pub struct SString {
+    inner: SVec<u8>,
+}
+
+impl FfiSafeEquivalent for SString {
+    type Normal = String;
+
+    fn from_normal(normal: Self::Normal) -> Self {
+        Self {
+            inner: SVec::from_vec(normal.into_bytes()),
+        }
+    }
+    fn into_normal(self) -> Self::Normal {
+        // The buffer might have been allocated by a different global allocator,
+        // and `String` has no way to carry it along, so the contents have to be copied.
+        self.as_str().to_owned()
+    }
+}
+
+impl SString {
+    pub fn new() -> Self {
+        Self { inner: SVec::new() }
+    }
+    pub fn as_str(&self) -> &str {
+        // SAFETY: the contents are always valid UTF-8
+        unsafe { std::str::from_utf8_unchecked(&self.inner) }
+    }
+    pub fn as_mut_str(&mut self) -> &mut str {
+        // SAFETY: the contents are always valid UTF-8
+        unsafe { std::str::from_utf8_unchecked_mut(&mut self.inner) }
+    }
+    pub fn as_sstr(&self) -> SStr<'_> {
+        SStr::new(self.as_str())
+    }
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.inner
+    }
+    /// Converts the string to its raw bytes, without copying
+    pub fn into_bytes(self) -> SVec<u8> {
+        self.inner
+    }
+    /// Converts a vector of bytes to a string, without copying, if the bytes are valid UTF-8
+    pub fn from_utf8(bytes: SVec<u8>) -> Result<Self, std::str::Utf8Error> {
+        std::str::from_utf8(&bytes)?;
+
+        Ok(Self { inner: bytes })
+    }
+    /// # Safety
+    ///
+    /// `bytes` must be valid UTF-8
+    pub unsafe fn from_utf8_unchecked(bytes: SVec<u8>) -> Self {
+        Self { inner: bytes }
+    }
+    /// Converts the string to an [`SBoxStr`], dropping any excess capacity
+    pub fn into_boxed_str(self) -> SBoxStr {
+        // SAFETY: the contents are always valid UTF-8
+        unsafe { SBoxStr::from_utf8_unchecked(self.inner.into_boxed_slice()) }
+    }
+}
+
+impl AsRef<str> for SString {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl AsRef<[u8]> for SString {
+    fn as_ref(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+impl Borrow<str> for SString {
+    fn borrow(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl Debug for SString {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl Deref for SString {
+    type Target = str;
+
+    fn deref(&self) -> &Self::Target {
+        self.as_str()
+    }
+}
+
+impl DerefMut for SString {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.as_mut_str()
+    }
+}
+
+impl Display for SString {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(self.as_str(), f)
+    }
+}
+
+impl From<String> for SString {
+    fn from(value: String) -> Self {
+        Self::from_normal(value)
+    }
+}
+
+impl From<&str> for SString {
+    fn from(value: &str) -> Self {
+        Self::from_normal(value.to_owned())
+    }
+}
+
+impl<'a> From<SStr<'a>> for SString {
+    fn from(value: SStr<'a>) -> Self {
+        Self::from(value.to_str())
+    }
+}
+
+impl From<SString> for String {
+    fn from(value: SString) -> Self {
+        value.into_normal()
+    }
+}
+
+impl<I: std::slice::SliceIndex<str>> Index<I> for SString {
+    type Output = <I as std::slice::SliceIndex<str>>::Output;
+
+    fn index(&self, index: I) -> &Self::Output {
+        self.as_str().index(index)
+    }
+}
+
+impl<I: std::slice::SliceIndex<str>> IndexMut<I> for SString {
+    fn index_mut(&mut self, index: I) -> &mut Self::Output {
+        self.as_mut_str().index_mut(index)
+    }
+}
+
+impl PartialEq<str> for SString {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for SString {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
@@ -28,9 +28,7 @@ impl FfiSafeEquivalent for SUnit {
     fn from_normal(_: Self::Normal) -> Self {
         Self::new()
     }
-    fn into_normal(self) -> Self::Normal {
-        ()
-    }
+    fn into_normal(self) -> Self::Normal {}
 }
 
 /// FFI-safe equivalent of `(T1, T2)`
@@ -1,5 +1,5 @@
 use super::allocator::SGlobal;
-use super::FfiSafeEquivalent;
+use super::{FfiSafeEquivalent, SBoxSlice};
 use ironties::TypeInfo;
 use std::alloc::Allocator;
 use std::borrow::{Borrow, BorrowMut};
@@ -10,8 +10,19 @@ use std::ops::{Deref, DerefMut, Index, IndexMut};
 use std::slice;
 
 /// FFI-safe equivalent of [`Vec<T>`]
+///
+/// Like `Vec<T>`, it can only be shared between threads if `T` is [`Sync`]:
+///
+/// ```compile_fail
+/// # use ironties::types::SVec;
+/// # use std::cell::Cell;
+/// fn assert_sync<T: Sync>(_: T) {}
+///
+/// assert_sync(SVec::from_vec(vec![Cell::new(1u8)]));
+/// ```
 #[repr(C)]
 #[derive(TypeInfo)]
+#[ironties(known = "Slice")]
 pub struct SVec<T, A: Allocator = SGlobal> {
     ptr: *const T,
     len: usize,
@@ -67,6 +78,13 @@ impl<T> SVec<T, SGlobal> {
     }
 }
 
+impl<T, A: Allocator> SVec<T, A> {
+    /// Converts the vector to an [`SBoxSlice`], dropping any excess capacity
+    pub fn into_boxed_slice(self) -> SBoxSlice<T, A> {
+        SBoxSlice::from_normal(self.into_normal().into_boxed_slice())
+    }
+}
+
 impl<T, A: Allocator> Drop for SVec<T, A> {
     fn drop(&mut self) {
         // SAFETY: We make a Vec for the same object as our SVec and drop it.
@@ -215,3 +233,6 @@ impl<T: PartialOrd, A: Allocator> PartialOrd for SVec<T, A> {
         self.as_normal(move |v1| other.as_normal(move |v2| v1.partial_cmp(v2)))
     }
 }
+
+unsafe impl<T: Send, A: Allocator + Send> Send for SVec<T, A> {}
+unsafe impl<T: Sync, A: Allocator + Sync> Sync for SVec<T, A> {}
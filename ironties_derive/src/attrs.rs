@@ -0,0 +1,129 @@
+use syn::{
+    punctuated::Punctuated, spanned::Spanned, token::Comma, Attribute, Error, Ident, Lit, LitStr,
+    Meta, NestedMeta, Path, Type, WherePredicate,
+};
+
+/// The parsed `#[ironties(..)]` attributes of a type
+#[derive(Default)]
+pub struct Attrs {
+    /// Allows deriving for types without a stable layout
+    pub unsafe_allow_repr_rust: bool,
+    /// Record the type without its contents, only with its size and alignment
+    pub opaque: bool,
+    /// The name of the type in the layout
+    pub rename: Option<LitStr>,
+    /// Replaces the `T: TypeInfo` bounds on type parameters
+    pub bound: Option<Punctuated<WherePredicate, Comma>>,
+    /// The path to the `ironties` crate
+    pub krate: Option<Path>,
+    /// The foreign type that this type mirrors
+    pub remote: Option<Type>,
+    /// Auto traits and `Copy` that the type is required to implement
+    pub send: bool,
+    pub sync: bool,
+    pub copy: bool,
+    /// Marks the types of `ironties` that are read as the values they stand for (`known = "Slice"`),
+    /// which only compiles inside of `ironties`
+    pub known: Option<Ident>,
+}
+
+/// The parsed `#[ironties(..)]` attributes of a field
+#[derive(Default)]
+pub struct FieldAttrs {
+    /// The mirror type which provides the layout of the field
+    pub with: Option<Type>,
+}
+
+impl Attrs {
+    pub fn parse(attrs: &[Attribute]) -> syn::Result<Self> {
+        let mut result = Self::default();
+
+        for nested in ironties_attrs(attrs)? {
+            match &nested {
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("unsafe_allow_repr_rust") => {
+                    result.unsafe_allow_repr_rust = true;
+                }
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("opaque") => {
+                    result.opaque = true;
+                }
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("send") => {
+                    result.send = true;
+                }
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("sync") => {
+                    result.sync = true;
+                }
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("copy") => {
+                    result.copy = true;
+                }
+                NestedMeta::Meta(Meta::NameValue(name_value)) => {
+                    let value = string_value(&name_value.lit)?;
+
+                    if name_value.path.is_ident("rename") {
+                        result.rename = Some(value.clone());
+                    } else if name_value.path.is_ident("bound") {
+                        result.bound = Some(value.parse_with(Punctuated::parse_terminated)?);
+                    } else if name_value.path.is_ident("crate") {
+                        result.krate = Some(value.parse()?);
+                    } else if name_value.path.is_ident("remote") {
+                        result.remote = Some(value.parse()?);
+                    } else if name_value.path.is_ident("known") {
+                        result.known = Some(value.parse()?);
+                    } else {
+                        return Err(unknown(&nested));
+                    }
+                }
+                _ => return Err(unknown(&nested)),
+            }
+        }
+
+        Ok(result)
+    }
+}
+
+impl FieldAttrs {
+    pub fn parse(attrs: &[Attribute]) -> syn::Result<Self> {
+        let mut result = Self::default();
+
+        for nested in ironties_attrs(attrs)? {
+            match &nested {
+                NestedMeta::Meta(Meta::NameValue(name_value))
+                    if name_value.path.is_ident("with") =>
+                {
+                    result.with = Some(string_value(&name_value.lit)?.parse()?);
+                }
+                _ => return Err(unknown(&nested)),
+            }
+        }
+
+        Ok(result)
+    }
+}
+
+/// The contents of all `#[ironties(..)]` attributes
+fn ironties_attrs(attrs: &[Attribute]) -> syn::Result<Vec<NestedMeta>> {
+    let mut result = Vec::new();
+
+    for attr in attrs {
+        if !attr.path.is_ident("ironties") {
+            continue;
+        }
+
+        match attr.parse_meta()? {
+            Meta::List(list) => result.extend(list.nested),
+            other => return Err(Error::new(other.span(), "expected #[ironties(..)]")),
+        }
+    }
+
+    Ok(result)
+}
+
+fn string_value(lit: &Lit) -> syn::Result<&LitStr> {
+    match lit {
+        Lit::Str(s) => Ok(s),
+        other => Err(Error::new(other.span(), "expected a string")),
+    }
+}
+
+fn unknown(nested: &NestedMeta) -> Error {
+    Error::new(nested.span(), "unknown ironties attribute")
+}
@@ -1,87 +1,85 @@
-use crate::impl_fields::{impl_named_fields, impl_unnamed_fields};
+use crate::impl_fields::{
+    fingerprint_named_fields, fingerprint_unnamed_fields, impl_named_fields, impl_unnamed_fields,
+    Offsets,
+};
+use crate::repr::Repr;
 use proc_macro2::TokenStream;
 use quote::quote;
-use syn::{punctuated::Punctuated, token::Comma, Ident, Variant};
+use syn::{punctuated::Punctuated, token::Comma, LitStr, Variant};
 
-pub fn impl_enum(name: &Ident, repr: Ident, variants: &Punctuated<Variant, Comma>) -> TokenStream {
-    let variants = {
-        let discriminants = variants.iter().scan(quote! { 0 }, |d, v| {
-            *d = match &v.discriminant {
-                Some((_, d)) => quote! { #d },
-                None => quote! { #d },
-            };
-            let current_d = d.clone();
-            *d = quote! {( #d + 1 )};
-
-            Some(current_d)
-        });
+pub fn impl_enum(
+    name: &LitStr,
+    repr: &Repr,
+    flags: &TokenStream,
+    known: &TokenStream,
+    variants: &Punctuated<Variant, Comma>,
+) -> TokenStream {
+    let repr_layout = repr.to_layout();
+    let (enum_layout, payload_offsets) = enum_layout(repr, variants);
 
+    let variants = {
         variants
             .iter()
-            .zip(discriminants)
-            .map(|(variant, discriminant)| {
+            .zip(discriminants(variants, repr.discriminant_type()))
+            .zip(payload_offsets)
+            .map(|((variant, discriminant), payload_offset)| {
                 let variant_name = &variant.ident;
-                match &variant.fields {
-                    syn::Fields::Named(fields) => {
-                        let fields = impl_named_fields(fields);
-
-                        quote! {
-                            let mut fields = Vec::new();
-
-                            #fields
-
+                let (fields, ty) = match &variant.fields {
+                    syn::Fields::Named(fields) => (
+                        impl_named_fields(fields, &Offsets::Sequential(payload_offset.clone())),
+                        quote! { EnumVariantType::Struct(SVec::from_vec(fields)) },
+                    ),
+                    syn::Fields::Unnamed(fields) => (
+                        impl_unnamed_fields(fields, &Offsets::Sequential(payload_offset.clone())),
+                        quote! { EnumVariantType::Tuple(SVec::from_vec(fields)) },
+                    ),
+                    syn::Fields::Unit => {
+                        return quote! {
                             variants.push(EnumVariant {
                                 name: SStr::from_normal(stringify!(#variant_name)),
-                                ty: EnumVariantType::Struct(SVec::from_vec(fields)),
-                                discriminant: #discriminant,
+                                ty: EnumVariantType::Unit,
+                                discriminant: Discriminant::from(#discriminant),
+                                payload_offset: #payload_offset,
                             });
                         }
                     }
-                    syn::Fields::Unnamed(fields) => {
-                        let fields = impl_unnamed_fields(fields);
+                };
 
-                        quote! {
-                            let mut fields = Vec::new();
+                quote! {
+                    let mut fields = Vec::new();
 
-                            #fields
+                    #fields
 
-                            variants.push(EnumVariant {
-                                name: SStr::from_normal(stringify!(#variant_name)),
-                                ty: EnumVariantType::Tuple(SVec::from_vec(fields)),
-                                discriminant: #discriminant,
-                            });
-                        }
-                    }
-                    syn::Fields::Unit => quote! {
-                        variants.push(EnumVariant {
-                            name: SStr::from_normal(stringify!(#variant_name)),
-                            ty: EnumVariantType::Unit,
-                            discriminant: #discriminant,
-                        });
-                    },
+                    variants.push(EnumVariant {
+                        name: SStr::from_normal(stringify!(#variant_name)),
+                        ty: #ty,
+                        discriminant: Discriminant::from(#discriminant),
+                        payload_offset: #payload_offset,
+                    });
                 }
             })
     };
 
     quote! {
-        defined_types.push((
-            Self::_UID,
-            DefinedType {
-                name: SStr::from_normal(stringify!(#name)),
-                ty: TypeType::Enum {
-                    // Temporary:
-                    variants: SVec::new(),
-                    repr: SStr::from_normal(stringify!(#repr)),
-                },
+        let my_type_id = defined_types.define::<Self>(DefinedType {
+            name: SStr::from_normal(#name),
+            repr: #repr_layout,
+            flags: #flags,
+            known: #known,
+            size: ::std::mem::size_of::<Self>(),
+            align: ::std::mem::align_of::<Self>(),
+            ty: TypeType::Enum {
+                // Temporary:
+                variants: SVec::new(),
+                layout: #enum_layout,
             },
-        ));
-        let my_type_id = defined_types.len() - 1;
+        });
 
         let mut variants = Vec::new();
 
         #( #variants )*
 
-        if let TypeType::Enum{ variants: ref mut v, ..} = defined_types[my_type_id].1.ty {
+        if let TypeType::Enum{ variants: ref mut v, ..} = defined_types[my_type_id].ty {
             *v = SVec::from_vec(variants);
         }
 
@@ -91,3 +89,110 @@ pub fn impl_enum(name: &Ident, repr: Ident, variants: &Punctuated<Variant, Comma
         }
     }
 }
+
+/// The `EnumLayout` of the enum and expressions for the payload offsets of all variants
+fn enum_layout(
+    repr: &Repr,
+    variants: &Punctuated<Variant, Comma>,
+) -> (TokenStream, Vec<TokenStream>) {
+    let Some(tag) = repr.tag_type() else {
+        return (
+            quote! { EnumLayout::Unspecified },
+            vec![quote! { 0 }; variants.len()],
+        );
+    };
+    let tag_size = quote! { ::std::mem::size_of::<#tag>() };
+
+    if repr.c {
+        // The payload is a union of all variants, so it's aligned to the largest alignment of all fields
+        let field_types = variants.iter().flat_map(|v| v.fields.iter().map(|f| &f.ty));
+        let payload_offset = quote! {
+            #tag_size.next_multiple_of(1usize #( .max(::std::mem::align_of::<#field_types>()) )*)
+        };
+
+        (
+            quote! { EnumLayout::Separate { tag_size: #tag_size } },
+            vec![payload_offset; variants.len()],
+        )
+    } else {
+        // Every variant is a struct starting with the tag, so the fields follow it directly
+        let payload_offsets = variants
+            .iter()
+            .map(|v| match v.fields.iter().next() {
+                Some(first) => {
+                    let ty = &first.ty;
+                    quote! { #tag_size.next_multiple_of(::std::mem::align_of::<#ty>()) }
+                }
+                None => tag_size.clone(),
+            })
+            .collect();
+
+        (
+            quote! { EnumLayout::Inline { tag_size: #tag_size } },
+            payload_offsets,
+        )
+    }
+}
+
+pub fn fingerprint_enum(
+    name: &LitStr,
+    repr: &Repr,
+    flags: &TokenStream,
+    known: &TokenStream,
+    variants: &Punctuated<Variant, Comma>,
+) -> TokenStream {
+    let repr_layout = repr.to_layout();
+    let count = variants.len() as u64;
+    let variants = variants
+        .iter()
+        .zip(discriminants(variants, repr.discriminant_type()))
+        .map(|(variant, discriminant)| {
+            let variant_name = &variant.ident;
+            let fields = match &variant.fields {
+                syn::Fields::Named(fields) => {
+                    let fields = fingerprint_named_fields(fields);
+                    quote! { .str("Struct") #fields }
+                }
+                syn::Fields::Unnamed(fields) => {
+                    let fields = fingerprint_unnamed_fields(fields);
+                    quote! { .str("Tuple") #fields }
+                }
+                syn::Fields::Unit => quote! { .str("Unit") },
+            };
+
+            quote! {
+                .str(stringify!(#variant_name))
+                .u128(#discriminant as u128)
+                #fields
+            }
+        });
+
+    quote! {
+        Fingerprinter::new()
+            .str("Enum")
+            .str(#name)
+            .repr(&#repr_layout)
+            .flags(&#flags)
+            .known(&#known)
+            .u64(#count)
+            #( #variants )*
+            .finish()
+    }
+}
+
+/// Expressions for the discriminants of all variants, evaluated in the type `ty`
+fn discriminants<'a>(
+    variants: &'a Punctuated<Variant, Comma>,
+    ty: TokenStream,
+) -> impl Iterator<Item = TokenStream> + 'a {
+    variants.iter().scan(None, move |previous, v| {
+        let current = match (&v.discriminant, &previous) {
+            (Some((_, d)), _) => quote! { { let d: #ty = #d; d } },
+            (None, Some(previous)) => quote! { (#previous + 1) },
+            (None, None) => quote! { { let d: #ty = 0; d } },
+        };
+        *previous = Some(current.clone());
+
+        Some(current)
+    })
+}
@@ -1,29 +1,101 @@
 use proc_macro2::TokenStream;
 use quote::quote;
-use syn::{FieldsNamed, FieldsUnnamed};
+use syn::{Field, FieldsNamed, FieldsUnnamed, Index, Member};
 
-pub fn impl_named_fields(fields: &FieldsNamed) -> TokenStream {
+/// How the offsets of the fields are found
+pub enum Offsets {
+    /// With `offset_of!`, for structs and unions
+    OffsetOf,
+    /// By placing the fields one after another like in a `#[repr(C)]` struct, starting at the given offset.
+    /// For fields of enum variants, which `offset_of!` doesn't support.
+    Sequential(TokenStream),
+}
+
+pub fn impl_named_fields(fields: &FieldsNamed, offsets: &Offsets) -> TokenStream {
     let field_names = fields.named.iter().map(|f| f.ident.as_ref().unwrap());
     let field_types = fields.named.iter().map(|f| &f.ty);
+    let offsets = field_offsets(fields.named.iter(), offsets);
 
     quote! {
         #(
-            let FullLayout { layout, mut defined_types } = <#field_types as _TypeInfoImpl>::_layout_impl(defined_types);
+            let FullLayout { layout, mut defined_types } = field_layout::<#field_types>(defined_types);
+            #offsets
             fields.push(NamedField {
                 name: SStr::from_normal(stringify!(#field_names)),
                 layout,
+                offset,
             });
         )*
     }
 }
 
-pub fn impl_unnamed_fields(fields: &FieldsUnnamed) -> TokenStream {
+pub fn impl_unnamed_fields(fields: &FieldsUnnamed, offsets: &Offsets) -> TokenStream {
+    let field_types = fields.unnamed.iter().map(|f| &f.ty);
+    let offsets = field_offsets(fields.unnamed.iter(), offsets);
+
+    quote! {
+        #(
+            let FullLayout { layout, mut defined_types } = field_layout::<#field_types>(defined_types);
+            #offsets
+            fields.push(UnnamedField { layout, offset });
+        )*
+    }
+}
+
+/// Statements which define `offset` for each of the fields
+fn field_offsets<'a>(
+    fields: impl Iterator<Item = &'a Field>,
+    offsets: &Offsets,
+) -> Vec<TokenStream> {
+    let mut previous: Option<&syn::Type> = None;
+
+    fields
+        .enumerate()
+        .map(|(i, field)| match offsets {
+            Offsets::OffsetOf => {
+                let member = match &field.ident {
+                    Some(ident) => Member::Named(ident.clone()),
+                    None => Member::Unnamed(Index::from(i)),
+                };
+                quote! { let offset = ::std::mem::offset_of!(Self, #member); }
+            }
+            Offsets::Sequential(start) => {
+                let ty = &field.ty;
+                // `offset` of the previous field is shadowed
+                let end = match previous.replace(ty) {
+                    Some(previous) => quote! { offset + ::std::mem::size_of::<#previous>() },
+                    None => start.clone(),
+                };
+                quote! { let offset = (#end).next_multiple_of(::std::mem::align_of::<#ty>()); }
+            }
+        })
+        .collect()
+}
+
+/// Fingerprints of the fields, as a chain of `Fingerprinter` method calls
+pub fn fingerprint_named_fields(fields: &FieldsNamed) -> TokenStream {
+    let count = fields.named.len() as u64;
+    let field_names = fields.named.iter().map(|f| f.ident.as_ref().unwrap());
+    let field_types = fields.named.iter().map(|f| &f.ty);
+
+    quote! {
+        .u64(#count)
+        #(
+            .str(stringify!(#field_names))
+            .u128(field_fingerprint::<#field_types>())
+        )*
+    }
+}
+
+/// Fingerprints of the fields, as a chain of `Fingerprinter` method calls
+pub fn fingerprint_unnamed_fields(fields: &FieldsUnnamed) -> TokenStream {
+    let count = fields.unnamed.len() as u64;
     let field_types = fields.unnamed.iter().map(|f| &f.ty);
 
     quote! {
+        .u64(#count)
         #(
-            let FullLayout { layout, mut defined_types } = <#field_types as _TypeInfoImpl>::_layout_impl(defined_types);
-            fields.push(layout);
+            .u128(field_fingerprint::<#field_types>())
         )*
     }
 }
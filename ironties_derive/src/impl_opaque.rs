@@ -0,0 +1,46 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::LitStr;
+
+pub fn impl_opaque(
+    name: &LitStr,
+    repr: &TokenStream,
+    flags: &TokenStream,
+    known: &TokenStream,
+) -> TokenStream {
+    quote! {
+        let my_type_id = defined_types.define::<Self>(DefinedType {
+            name: SStr::from_normal(#name),
+            repr: #repr,
+            flags: #flags,
+            known: #known,
+            size: ::std::mem::size_of::<Self>(),
+            align: ::std::mem::align_of::<Self>(),
+            ty: TypeType::Opaque,
+        });
+
+        FullLayout {
+            layout: Layout::DefinedType { id: my_type_id },
+            defined_types,
+        }
+    }
+}
+
+pub fn fingerprint_opaque(
+    name: &LitStr,
+    repr: &TokenStream,
+    flags: &TokenStream,
+    known: &TokenStream,
+) -> TokenStream {
+    quote! {
+        Fingerprinter::new()
+            .str("Opaque")
+            .str(#name)
+            .repr(&#repr)
+            .flags(&#flags)
+            .known(&#known)
+            .u64(::std::mem::size_of::<Self>() as u64)
+            .u64(::std::mem::align_of::<Self>() as u64)
+            .finish()
+    }
+}
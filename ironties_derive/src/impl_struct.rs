@@ -1,29 +1,39 @@
-use crate::impl_fields::{impl_named_fields, impl_unnamed_fields};
+use crate::impl_fields::{
+    fingerprint_named_fields, fingerprint_unnamed_fields, impl_named_fields, impl_unnamed_fields,
+    Offsets,
+};
 use proc_macro2::TokenStream;
 use quote::quote;
-use syn::{DataStruct, Ident};
+use syn::{DataStruct, LitStr};
 
-pub fn impl_struct(name: &Ident, s: &DataStruct) -> TokenStream {
+pub fn impl_struct(
+    name: &LitStr,
+    repr: &TokenStream,
+    flags: &TokenStream,
+    known: &TokenStream,
+    s: &DataStruct,
+) -> TokenStream {
     match &s.fields {
         syn::Fields::Named(fields) => {
-            let fields = impl_named_fields(fields);
+            let fields = impl_named_fields(fields, &Offsets::OffsetOf);
 
             quote! {
-                defined_types.push((
-                    Self::_UID,
-                    DefinedType {
-                        name: SStr::from_normal(stringify!(#name)),
-                        // Temporary:
-                        ty: TypeType::StructUnit,
-                    },
-                ));
-                let my_type_id = defined_types.len() - 1;
+                let my_type_id = defined_types.define::<Self>(DefinedType {
+                    name: SStr::from_normal(#name),
+                    repr: #repr,
+                    flags: #flags,
+                    known: #known,
+                    size: ::std::mem::size_of::<Self>(),
+                    align: ::std::mem::align_of::<Self>(),
+                    // Temporary:
+                    ty: TypeType::StructUnit,
+                });
 
                 let mut fields = Vec::new();
 
                 #fields
 
-                defined_types[my_type_id].1.ty = TypeType::StructNamed {
+                defined_types[my_type_id].ty = TypeType::StructNamed {
                     fields: SVec::from_vec(fields),
                 };
 
@@ -34,24 +44,25 @@ pub fn impl_struct(name: &Ident, s: &DataStruct) -> TokenStream {
             }
         }
         syn::Fields::Unnamed(fields) => {
-            let fields = impl_unnamed_fields(fields);
+            let fields = impl_unnamed_fields(fields, &Offsets::OffsetOf);
 
             quote! {
-                defined_types.push((
-                    Self::_UID,
-                    DefinedType {
-                        name: SStr::from_normal(stringify!(#name)),
-                        // Temporary:
-                        ty: TypeType::StructUnit,
-                    },
-                ));
-                let my_type_id = defined_types.len() - 1;
+                let my_type_id = defined_types.define::<Self>(DefinedType {
+                    name: SStr::from_normal(#name),
+                    repr: #repr,
+                    flags: #flags,
+                    known: #known,
+                    size: ::std::mem::size_of::<Self>(),
+                    align: ::std::mem::align_of::<Self>(),
+                    // Temporary:
+                    ty: TypeType::StructUnit,
+                });
 
                 let mut fields = Vec::new();
 
                 #fields
 
-                defined_types[my_type_id].1.ty = TypeType::StructUnnamed {
+                defined_types[my_type_id].ty = TypeType::StructUnnamed {
                     fields: SVec::from_vec(fields),
                 };
 
@@ -63,14 +74,15 @@ pub fn impl_struct(name: &Ident, s: &DataStruct) -> TokenStream {
         }
         syn::Fields::Unit => {
             quote! {
-                defined_types.push((
-                    Self::_UID,
-                    DefinedType {
-                        name: SStr::from_normal(stringify!(#name)),
-                        ty: TypeType::StructUnit,
-                    },
-                ));
-                let my_type_id = defined_types.len() - 1;
+                let my_type_id = defined_types.define::<Self>(DefinedType {
+                    name: SStr::from_normal(#name),
+                    repr: #repr,
+                    flags: #flags,
+                    known: #known,
+                    size: ::std::mem::size_of::<Self>(),
+                    align: ::std::mem::align_of::<Self>(),
+                    ty: TypeType::StructUnit,
+                });
 
                 FullLayout {
                     layout: Layout::DefinedType { id: my_type_id },
@@ -80,3 +92,51 @@ pub fn impl_struct(name: &Ident, s: &DataStruct) -> TokenStream {
         }
     }
 }
+
+pub fn fingerprint_struct(
+    name: &LitStr,
+    repr: &TokenStream,
+    flags: &TokenStream,
+    known: &TokenStream,
+    s: &DataStruct,
+) -> TokenStream {
+    match &s.fields {
+        syn::Fields::Named(fields) => {
+            let fields = fingerprint_named_fields(fields);
+
+            quote! {
+                Fingerprinter::new()
+                    .str("StructNamed")
+                    .str(#name)
+                    .repr(&#repr)
+                    .flags(&#flags)
+                    .known(&#known)
+                    #fields
+                    .finish()
+            }
+        }
+        syn::Fields::Unnamed(fields) => {
+            let fields = fingerprint_unnamed_fields(fields);
+
+            quote! {
+                Fingerprinter::new()
+                    .str("StructUnnamed")
+                    .str(#name)
+                    .repr(&#repr)
+                    .flags(&#flags)
+                    .known(&#known)
+                    #fields
+                    .finish()
+            }
+        }
+        syn::Fields::Unit => quote! {
+            Fingerprinter::new()
+                .str("StructUnit")
+                .str(#name)
+                .repr(&#repr)
+                .flags(&#flags)
+                .known(&#known)
+                .finish()
+        },
+    }
+}
@@ -1,27 +1,34 @@
-use crate::impl_fields::impl_named_fields;
+use crate::impl_fields::{fingerprint_named_fields, impl_named_fields, Offsets};
 use proc_macro2::TokenStream;
 use quote::quote;
-use syn::{DataUnion, Ident};
+use syn::{DataUnion, LitStr};
 
-pub fn impl_union(name: &Ident, u: &DataUnion) -> TokenStream {
-    let fields = impl_named_fields(&u.fields);
+pub fn impl_union(
+    name: &LitStr,
+    repr: &TokenStream,
+    flags: &TokenStream,
+    known: &TokenStream,
+    u: &DataUnion,
+) -> TokenStream {
+    let fields = impl_named_fields(&u.fields, &Offsets::OffsetOf);
 
     quote! {
-        defined_types.push((
-            Self::_UID,
-            DefinedType {
-                name: SStr::from_normal(stringify!(#name)),
-                // Temporary:
-                ty: TypeType::StructUnit,
-            },
-        ));
-        let my_type_id = defined_types.len() - 1;
+        let my_type_id = defined_types.define::<Self>(DefinedType {
+            name: SStr::from_normal(#name),
+            repr: #repr,
+            flags: #flags,
+            known: #known,
+            size: ::std::mem::size_of::<Self>(),
+            align: ::std::mem::align_of::<Self>(),
+            // Temporary:
+            ty: TypeType::StructUnit,
+        });
 
         let mut fields = Vec::new();
 
         #fields
 
-        defined_types[my_type_id].1.ty = TypeType::Union {
+        defined_types[my_type_id].ty = TypeType::Union {
             fields: SVec::from_vec(fields),
         };
 
@@ -31,3 +38,24 @@ pub fn impl_union(name: &Ident, u: &DataUnion) -> TokenStream {
         }
     }
 }
+
+pub fn fingerprint_union(
+    name: &LitStr,
+    repr: &TokenStream,
+    flags: &TokenStream,
+    known: &TokenStream,
+    u: &DataUnion,
+) -> TokenStream {
+    let fields = fingerprint_named_fields(&u.fields);
+
+    quote! {
+        Fingerprinter::new()
+            .str("Union")
+            .str(#name)
+            .repr(&#repr)
+            .flags(&#flags)
+            .known(&#known)
+            #fields
+            .finish()
+    }
+}
@@ -1,47 +1,176 @@
-use impl_enum::impl_enum;
-use impl_struct::impl_struct;
-use impl_union::impl_union;
+use attrs::Attrs;
+use impl_enum::{fingerprint_enum, impl_enum};
+use impl_opaque::{fingerprint_opaque, impl_opaque};
+use impl_struct::{fingerprint_struct, impl_struct};
+use impl_union::{fingerprint_union, impl_union};
 use proc_macro::TokenStream;
 use quote::quote;
-use syn::{parse_quote, spanned::Spanned, Data, DataEnum, DeriveInput, Ident, TypeParen};
+use remote::{apply_with, remote_checks};
+use repr::Repr;
+use syn::{parse_quote, spanned::Spanned, Data, DataEnum, DeriveInput, LitStr, Type};
 
+mod attrs;
 mod impl_enum;
 mod impl_fields;
+mod impl_opaque;
 mod impl_struct;
 mod impl_union;
+mod remote;
+mod repr;
 
 /// Automatically derives the `TypeInfo` trait for a type, if all of it's members implement `TypeInfo`
-#[proc_macro_derive(TypeInfo)]
+///
+/// The type must have a stable layout: structs and unions need `#[repr(C)]` or `#[repr(transparent)]`,
+/// and enums need `#[repr(C)]` or a primitive representation like `#[repr(u8)]`.
+/// This check can be bypassed with `#[ironties(unsafe_allow_repr_rust)]`.
+///
+/// Other attributes:
+///
+/// - `#[ironties(opaque)]` records only the name, size and alignment of the type, without its fields.
+///   The fields don't need to implement `TypeInfo`, and the layout doesn't have to be stable.
+/// - `#[ironties(rename = "Name")]` sets the name of the type in the layout, so the Rust type can be renamed without breaking compatibility.
+/// - `#[ironties(bound = "T: Trait")]` replaces the `T: TypeInfo` bounds which are added to all type parameters by default.
+///   Pointers to instances of the type with different type parameters are then told apart by the names of the parameters,
+///   instead of their layouts.
+/// - `#[ironties(send, sync, copy)]` declares that the type is `Send`, `Sync` or `Copy`, which is then checked at compile time.
+///   For generic types, `TypeInfo` is then only implemented for the instantiations that are.
+/// - `#[ironties(crate = "path::to::ironties")]` for when `ironties` is re-exported from another crate.
+/// - `#[ironties(remote = "other_crate::Type")]` makes the type a mirror of a type from another crate,
+///   which can't implement `TypeInfo` because of the orphan rule. The size, alignment, field offsets
+///   and flags (`Send`, `Sync`, `Copy`, needs drop) of the mirror are checked against the remote type at compile time,
+///   as well as structs having the same fields. Enums can't be mirrored. Fields of the remote type are then
+///   marked with `#[ironties(with = "Mirror")]` to use the layout of the mirror.
+#[proc_macro_derive(TypeInfo, attributes(ironties))]
 pub fn derive_typeinfo(input: TokenStream) -> TokenStream {
     let input: DeriveInput = match syn::parse(input) {
         Ok(input) => input,
         Err(err) => return err.to_compile_error().into(),
     };
 
-    let name = &input.ident;
-    let mut generics = input.generics.clone();
-    for generic in &mut generics.params {
-        if let syn::GenericParam::Type(ty) = generic {
-            ty.bounds.push(parse_quote!(::ironties::TypeInfo))
+    let attrs = match Attrs::parse(&input.attrs) {
+        Ok(a) => a,
+        Err(err) => return err.to_compile_error().into(),
+    };
+    let repr = match Repr::parse(&input.attrs) {
+        Ok(r) => r,
+        Err(err) => return err.to_compile_error().into(),
+    };
+    if !attrs.unsafe_allow_repr_rust && !attrs.opaque {
+        if let Err(err) = repr.check_stable(&input) {
+            return err.to_compile_error().into();
         }
     }
-    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let repr_layout = repr.to_layout();
+
+    let krate = attrs.krate.unwrap_or_else(|| parse_quote!(::ironties));
+    let name = &input.ident;
+    // Mirrors of remote types are named after the remote type by default
+    let default_name = match &attrs.remote {
+        Some(Type::Path(remote)) => &remote.path.segments.last().unwrap().ident,
+        _ => name,
+    };
+    let layout_name = attrs
+        .rename
+        .unwrap_or_else(|| LitStr::new(&default_name.to_string(), name.span()));
 
-    let layout_impl = match &input.data {
-        Data::Struct(s) => impl_struct(name, s),
-        Data::Enum(DataEnum { variants, .. }) => {
-            let repr = match get_enum_repr(&input) {
-                Ok(r) => r,
+    let mut data = input.data.clone();
+    let with_bounds = match apply_with(&mut data, &krate) {
+        Ok(b) => b,
+        Err(err) => return err.to_compile_error().into(),
+    };
+
+    let remote_impl = match &attrs.remote {
+        Some(_) if !input.generics.params.is_empty() => {
+            return syn::Error::new(
+                input.generics.span(),
+                "TypeInfo: remote types can't have generic parameters",
+            )
+            .to_compile_error()
+            .into();
+        }
+        Some(remote) => {
+            let checks = match remote_checks(remote, name, &input.data) {
+                Ok(c) => c,
                 Err(err) => return err.to_compile_error().into(),
             };
 
-            impl_enum(name, repr, variants)
+            quote! {
+                unsafe impl #krate::RemoteTypeInfo<#remote> for #name {}
+                #checks
+            }
         }
-        Data::Union(u) => impl_union(name, u),
+        None => quote! {},
     };
 
+    let mut generics = input.generics.clone();
+    match &attrs.bound {
+        Some(bound) => generics
+            .make_where_clause()
+            .predicates
+            .extend(bound.iter().cloned()),
+        None => {
+            for generic in &mut generics.params {
+                if let syn::GenericParam::Type(ty) = generic {
+                    ty.bounds.push(parse_quote!(#krate::TypeInfo))
+                }
+            }
+        }
+    }
+    generics.make_where_clause().predicates.extend(with_bounds);
+
+    // The declared flags are checked by requiring the traits in the impl
+    let self_ty = {
+        let (_, ty_generics, _) = input.generics.split_for_impl();
+        quote!(#name #ty_generics)
+    };
+    for (declared, bound) in [
+        (attrs.send, quote!(::std::marker::Send)),
+        (attrs.sync, quote!(::std::marker::Sync)),
+        (attrs.copy, quote!(::std::marker::Copy)),
+    ] {
+        if declared {
+            generics
+                .make_where_clause()
+                .predicates
+                .push(parse_quote!(#self_ty: #bound));
+        }
+    }
+    let flags = quote!(TypeFlags::of::<Self>());
+    let known = match &attrs.known {
+        Some(known) => quote!(KnownType::#known.mark()),
+        None => quote!(SOption::None),
+    };
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let (layout_impl, fingerprint) = match &data {
+        _ if attrs.opaque => (
+            impl_opaque(&layout_name, &repr_layout, &flags, &known),
+            fingerprint_opaque(&layout_name, &repr_layout, &flags, &known),
+        ),
+        Data::Struct(s) => (
+            impl_struct(&layout_name, &repr_layout, &flags, &known, s),
+            fingerprint_struct(&layout_name, &repr_layout, &flags, &known, s),
+        ),
+        Data::Enum(DataEnum { variants, .. }) => (
+            impl_enum(&layout_name, &repr, &flags, &known, variants),
+            fingerprint_enum(&layout_name, &repr, &flags, &known, variants),
+        ),
+        Data::Union(u) => (
+            impl_union(&layout_name, &repr_layout, &flags, &known, u),
+            fingerprint_union(&layout_name, &repr_layout, &flags, &known, u),
+        ),
+    };
+    // With custom bounds the type parameters might not implement `TypeInfo`, so they are identified by their names instead
+    let type_params = input.generics.type_params().map(|param| {
+        let param = &param.ident;
+        match &attrs.bound {
+            Some(_) => quote!(.type_name::<#param>()),
+            None => quote!(.u128(<#param as _TypeInfoImpl>::_SHALLOW_FINGERPRINT)),
+        }
+    });
+
     quote! {const _: () = {
-        use ::ironties::{_TypeInfoImpl, types::{FfiSafeEquivalent, SVec, SStr, SOption}, layout::{EnumVariantType, EnumVariant, Layout, DefinedType, NamedField, FullLayout, DefinedTypes, TypeUid, TypeType}};
+        use #krate::{_TypeInfoImpl, types::{FfiSafeEquivalent, SVec, SStr, SOption}, layout::{EnumVariantType, EnumVariant, EnumLayout, Discriminant, Layout, DefinedType, NamedField, UnnamedField, FullLayout, DefinedTypes, TypeUid, TypeType, Fingerprinter, Repr, ReprPrimitive, TypeFlags, KnownType, field_layout, field_fingerprint}};
         use ::std::vec::Vec;
         unsafe impl #impl_generics _TypeInfoImpl for #name #ty_generics #where_clause {
             const _UID: TypeUid = TypeUid {
@@ -50,9 +179,15 @@ pub fn derive_typeinfo(input: TokenStream) -> TokenStream {
                 line: ::std::line!(),
                 column: ::std::column!(),
             };
+            const _FINGERPRINT: u128 = #fingerprint;
+            const _SHALLOW_FINGERPRINT: u128 = Fingerprinter::new()
+                .str("DefinedType")
+                .str(#layout_name)
+                #( #type_params )*
+                .finish();
 
             fn _layout_impl(mut defined_types: DefinedTypes) -> FullLayout {
-                match defined_types.iter().position(|t| t.0 == Self::_UID) {
+                match defined_types.id_of::<Self>() {
                     Some(pos) => {
                         FullLayout {
                             layout: Layout::DefinedType { id: pos },
@@ -65,30 +200,8 @@ pub fn derive_typeinfo(input: TokenStream) -> TokenStream {
                 }
             }
         }
+
+        #remote_impl
     };}
     .into()
 }
-
-/// Parses and returns the X in `#[repr(X)]`
-fn get_enum_repr(input: &DeriveInput) -> syn::parse::Result<Ident> {
-    for attr in &input.attrs {
-        if let Some(ident) = attr.path.get_ident() {
-            if ident == "repr" {
-                let parsed: TypeParen = syn::parse2(attr.tokens.clone())?;
-
-                return match *parsed.elem {
-                    syn::Type::Path(p) => match p.path.get_ident() {
-                        Some(ident) => Ok(ident.clone()),
-                        None => Err(syn::parse::Error::new(attr.span(), "invalid repr")),
-                    },
-                    _ => Err(syn::parse::Error::new(attr.span(), "invalid repr")),
-                };
-            }
-        }
-    }
-
-    Err(syn::parse::Error::new(
-        input.span(),
-        "TypeInfo: #[repr(..)] attribute not found",
-    ))
-}
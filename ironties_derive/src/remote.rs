@@ -0,0 +1,102 @@
+use crate::attrs::FieldAttrs;
+use proc_macro2::TokenStream;
+use quote::{quote, quote_spanned};
+use syn::{parse_quote, Data, Field, Ident, Index, Member, Path, Type, WherePredicate};
+
+/// Replaces the types of fields with `#[ironties(with = "..")]` by their mirror types,
+/// and returns the bounds which check that the mirrors actually describe the original types.
+pub fn apply_with(data: &mut Data, krate: &Path) -> syn::Result<Vec<WherePredicate>> {
+    let fields: Vec<&mut Field> = match data {
+        Data::Struct(s) => s.fields.iter_mut().collect(),
+        Data::Enum(e) => e
+            .variants
+            .iter_mut()
+            .flat_map(|v| v.fields.iter_mut())
+            .collect(),
+        Data::Union(u) => u.fields.named.iter_mut().collect(),
+    };
+
+    let mut predicates = Vec::new();
+    for field in fields {
+        if let Some(with) = FieldAttrs::parse(&field.attrs)?.with {
+            let ty = &field.ty;
+            predicates.push(parse_quote!(#with: #krate::RemoteTypeInfo<#ty>));
+            field.ty = with;
+        }
+    }
+
+    Ok(predicates)
+}
+
+/// Compile-time assertions that the mirror has the same size, alignment, field offsets and `TypeFlags` as the remote type,
+/// and for structs, the same fields.
+///
+/// Enums are not supported, since their discriminants and payloads can't be checked.
+pub fn remote_checks(remote: &Type, mirror: &Ident, data: &Data) -> syn::Result<TokenStream> {
+    let (members, all_fields): (Vec<Member>, _) = match data {
+        Data::Struct(s) => {
+            let members: Vec<Member> = s
+                .fields
+                .iter()
+                .enumerate()
+                .map(|(i, field)| match &field.ident {
+                    Some(ident) => Member::Named(ident.clone()),
+                    None => Member::Unnamed(Index::from(i)),
+                })
+                .collect();
+            // The pattern doesn't compile if the remote struct has fields that the mirror doesn't
+            let all_fields = quote_spanned! {mirror.span()=>
+                type Remote = #remote;
+                #[allow(dead_code)]
+                fn all_fields(remote: &Remote) {
+                    let Remote { #(#members: _),* } = remote;
+                }
+            };
+            (members, all_fields)
+        }
+        Data::Union(u) => (
+            u.fields
+                .named
+                .iter()
+                .map(|field| Member::Named(field.ident.clone().unwrap()))
+                .collect(),
+            quote! {},
+        ),
+        Data::Enum(e) => {
+            return Err(syn::Error::new(
+                e.enum_token.span,
+                "TypeInfo: remote enums are not supported, since their discriminants and payloads can't be checked",
+            ))
+        }
+    };
+
+    Ok(quote! {
+        const _: () = {
+            #all_fields
+            ::std::assert!(
+                ::std::mem::size_of::<#remote>() == ::std::mem::size_of::<#mirror>(),
+                ::std::concat!("the size of `", ::std::stringify!(#mirror), "` doesn't match `", ::std::stringify!(#remote), "`"),
+            );
+            ::std::assert!(
+                ::std::mem::align_of::<#remote>() == ::std::mem::align_of::<#mirror>(),
+                ::std::concat!("the alignment of `", ::std::stringify!(#mirror), "` doesn't match `", ::std::stringify!(#remote), "`"),
+            );
+            {
+                let (remote, mirror) = (TypeFlags::of::<#remote>(), TypeFlags::of::<#mirror>());
+                ::std::assert!(
+                    remote.send == mirror.send
+                        && remote.sync == mirror.sync
+                        && remote.copy == mirror.copy
+                        && remote.needs_drop == mirror.needs_drop,
+                    ::std::concat!("`", ::std::stringify!(#mirror), "` doesn't match `", ::std::stringify!(#remote), "` in being `Send`, `Sync`, `Copy` or needing drop"),
+                );
+            }
+            #(
+                ::std::assert!(
+                    ::std::mem::offset_of!(#remote, #members) == ::std::mem::offset_of!(#mirror, #members),
+                    ::std::concat!("the offset of `", ::std::stringify!(#members), "` in `", ::std::stringify!(#mirror), "` doesn't match `", ::std::stringify!(#remote), "`"),
+                );
+            )*
+        };
+    })
+}
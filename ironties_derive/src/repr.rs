@@ -0,0 +1,166 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{
+    spanned::Spanned, Attribute, Data, DataStruct, DeriveInput, Error, Fields, Ident, Lit, Meta,
+    NestedMeta,
+};
+
+/// The parsed `#[repr(..)]` attributes of a type
+#[derive(Default)]
+pub struct Repr {
+    pub c: bool,
+    pub transparent: bool,
+    pub primitive: Option<Ident>,
+    pub packed: Option<usize>,
+    pub align: Option<usize>,
+}
+
+impl Repr {
+    /// Parses and combines all `#[repr(..)]` attributes
+    pub fn parse(attrs: &[Attribute]) -> syn::Result<Self> {
+        let mut repr = Self::default();
+
+        for attr in attrs {
+            if !attr.path.is_ident("repr") {
+                continue;
+            }
+
+            let list = match attr.parse_meta()? {
+                Meta::List(list) => list,
+                other => return Err(Error::new(other.span(), "invalid repr")),
+            };
+
+            for nested in &list.nested {
+                match nested {
+                    NestedMeta::Meta(Meta::Path(path)) => {
+                        let ident = path
+                            .get_ident()
+                            .ok_or_else(|| Error::new(path.span(), "invalid repr"))?;
+
+                        match ident.to_string().as_str() {
+                            "C" => repr.c = true,
+                            "transparent" => repr.transparent = true,
+                            "packed" => repr.packed = Some(1),
+                            _ if primitive_variant(ident).is_some() => {
+                                repr.primitive = Some(ident.clone())
+                            }
+                            _ => return Err(Error::new(ident.span(), "unsupported repr")),
+                        }
+                    }
+                    NestedMeta::Meta(Meta::List(list)) if list.nested.len() == 1 => {
+                        let value = match &list.nested[0] {
+                            NestedMeta::Lit(Lit::Int(int)) => int.base10_parse()?,
+                            other => return Err(Error::new(other.span(), "expected an integer")),
+                        };
+
+                        if list.path.is_ident("packed") {
+                            repr.packed = Some(value);
+                        } else if list.path.is_ident("align") {
+                            repr.align = Some(value);
+                        } else {
+                            return Err(Error::new(list.path.span(), "unsupported repr"));
+                        }
+                    }
+                    other => return Err(Error::new(other.span(), "invalid repr")),
+                }
+            }
+        }
+
+        Ok(repr)
+    }
+    /// Checks that the layout of the type is stable and can't be changed by the compiler
+    pub fn check_stable(&self, input: &DeriveInput) -> syn::Result<()> {
+        let (stable, expected) = match &input.data {
+            // Unit structs are always zero-sized
+            Data::Struct(DataStruct {
+                fields: Fields::Unit,
+                ..
+            }) => return Ok(()),
+            Data::Struct(_) | Data::Union(_) => (
+                self.c || self.transparent,
+                "#[repr(C)] or #[repr(transparent)]",
+            ),
+            Data::Enum(_) => (
+                self.c || self.primitive.is_some(),
+                "#[repr(C)] or a primitive representation like #[repr(u8)]",
+            ),
+        };
+
+        if stable {
+            return Ok(());
+        }
+
+        Err(Error::new(
+            input.ident.span(),
+            format!(
+                "TypeInfo: the layout of `{}` is not stable, it needs {expected}. \
+                Use #[ironties(unsafe_allow_repr_rust)] if you are sure that the layout doesn't matter.",
+                input.ident
+            ),
+        ))
+    }
+    /// The type of the tag of an enum, if it has a stable layout
+    pub fn tag_type(&self) -> Option<TokenStream> {
+        match &self.primitive {
+            Some(primitive) => Some(quote! { ::std::primitive::#primitive }),
+            None if self.c => Some(quote! { ::std::ffi::c_int }),
+            None => None,
+        }
+    }
+    /// The type in which the discriminants of an enum are evaluated
+    pub fn discriminant_type(&self) -> TokenStream {
+        match &self.primitive {
+            Some(primitive) => quote! { ::std::primitive::#primitive },
+            None => quote! { ::std::primitive::isize },
+        }
+    }
+    /// An expression constructing the equivalent `ironties::layout::Repr`
+    pub fn to_layout(&self) -> TokenStream {
+        let c = self.c;
+        let transparent = self.transparent;
+        let primitive = match self.primitive.as_ref().and_then(primitive_variant) {
+            Some(variant) => {
+                let variant = Ident::new(variant, proc_macro2::Span::call_site());
+                quote! { SOption::Some(ReprPrimitive::#variant) }
+            }
+            None => quote! { SOption::None },
+        };
+        let packed = match self.packed {
+            Some(packed) => quote! { SOption::Some(#packed) },
+            None => quote! { SOption::None },
+        };
+        let align = match self.align {
+            Some(align) => quote! { SOption::Some(#align) },
+            None => quote! { SOption::None },
+        };
+
+        quote! {
+            Repr {
+                c: #c,
+                transparent: #transparent,
+                primitive: #primitive,
+                packed: #packed,
+                align: #align,
+            }
+        }
+    }
+}
+
+/// The `ReprPrimitive` variant for a primitive repr
+fn primitive_variant(ident: &Ident) -> Option<&'static str> {
+    Some(match ident.to_string().as_str() {
+        "u8" => "U8",
+        "u16" => "U16",
+        "u32" => "U32",
+        "u64" => "U64",
+        "u128" => "U128",
+        "usize" => "USize",
+        "i8" => "I8",
+        "i16" => "I16",
+        "i32" => "I32",
+        "i64" => "I64",
+        "i128" => "I128",
+        "isize" => "ISize",
+        _ => return None,
+    })
+}